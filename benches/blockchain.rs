@@ -0,0 +1,86 @@
+//! Baseline timings of mining, validation and balance lookups.
+//!
+//! Run with `cargo bench`; criterion compares each run against the previous
+//! one and reports regressions.
+
+mod support;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use fchain::blockchain::Block;
+
+use support::{synthetic_chain, BenchWallet};
+
+/// Chain lengths, in mined blocks, for the benchmarks that scale with the chain
+const CHAIN_LENGTHS: [usize; 3] = [10, 100, 500];
+
+/// Signed transfers per block in the synthetic chains
+const TRANSACTIONS_PER_BLOCK: usize = 5;
+
+fn bench_mine(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Block::mine");
+    group.sample_size(20);
+    let genesis = Block::genesis(0);
+
+    for difficulty in 1..=3 {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(difficulty),
+            &difficulty,
+            |b, &difficulty| {
+                b.iter_batched(
+                    || Block::new(1, vec![], genesis.hash.clone(), difficulty),
+                    |mut block| block.mine(),
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_is_chain_valid(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Blockchain::is_chain_valid");
+    group.sample_size(20);
+
+    for blocks in CHAIN_LENGTHS {
+        let (blockchain, _, _) = synthetic_chain(blocks, TRANSACTIONS_PER_BLOCK);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(blocks),
+            &blockchain,
+            |b, chain| b.iter(|| chain.is_chain_valid().unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn bench_transaction_is_valid(c: &mut Criterion) {
+    let alice = BenchWallet::new(1);
+    let transaction = alice.transfer(&BenchWallet::new(2).address(), 1.0, 0.1, 0);
+
+    c.bench_function("Transaction::is_valid (signed)", |b| {
+        b.iter(|| transaction.is_valid())
+    });
+}
+
+fn bench_get_balance(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Blockchain::get_balance");
+
+    for blocks in CHAIN_LENGTHS {
+        let (blockchain, _, bob) = synthetic_chain(blocks, TRANSACTIONS_PER_BLOCK);
+        let address = bob.address().0;
+        group.bench_with_input(
+            BenchmarkId::from_parameter(blocks),
+            &blockchain,
+            |b, chain| b.iter(|| chain.get_balance(&address)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_mine,
+    bench_is_chain_valid,
+    bench_transaction_is_valid,
+    bench_get_balance
+);
+criterion_main!(benches);
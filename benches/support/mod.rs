@@ -0,0 +1,70 @@
+//! Helpers shared by the benchmarks
+
+use ed25519_dalek::{Signer, SigningKey};
+use fchain::blockchain::crypto::{Address, PublicKeyHex, TransactionSignature};
+use fchain::blockchain::{Blockchain, Transaction};
+
+/// Deterministic key pair for signing benchmark transactions
+pub struct BenchWallet {
+    signing_key: SigningKey,
+}
+
+impl BenchWallet {
+    /// Creates a wallet whose key is derived from a single seed byte
+    pub fn new(seed: u8) -> Self {
+        Self {
+            signing_key: SigningKey::from_bytes(&[seed; 32]),
+        }
+    }
+
+    /// Returns the hex encoded public key
+    pub fn public_key(&self) -> PublicKeyHex {
+        PublicKeyHex(hex::encode(self.signing_key.verifying_key().to_bytes()))
+    }
+
+    /// Returns the wallet address
+    pub fn address(&self) -> Address {
+        self.public_key().to_address().unwrap()
+    }
+
+    /// Creates a signed transaction from this wallet carrying the given nonce
+    pub fn transfer(&self, recipient: &Address, amount: f64, fee: f64, nonce: u64) -> Transaction {
+        let mut transaction =
+            Transaction::new_with_fee(self.address(), recipient.clone(), amount, fee)
+                .with_nonce(nonce);
+        let signature = self.signing_key.sign(transaction.hash.as_bytes());
+        transaction.signature = Some(TransactionSignature(hex::encode(signature.to_bytes())));
+        transaction.public_key = Some(self.public_key());
+        transaction
+    }
+}
+
+/// Builds a valid chain of `blocks` mined blocks at difficulty 1, each holding
+/// `transactions_per_block` signed transfers between two wallets besides the
+/// reward. Returns the chain and the wallets.
+pub fn synthetic_chain(
+    blocks: usize,
+    transactions_per_block: usize,
+) -> (Blockchain, BenchWallet, BenchWallet) {
+    let alice = BenchWallet::new(1);
+    let bob = BenchWallet::new(2);
+    let mut blockchain = Blockchain::new(1, 50.0);
+    let mut nonce = 0;
+
+    // Fund the sender before the first transfer
+    blockchain
+        .mine_pending_transactions(&alice.address().0)
+        .unwrap();
+    for _ in 1..blocks {
+        for _ in 0..transactions_per_block {
+            let transfer = alice.transfer(&bob.address(), 1.0, 0.1, nonce);
+            blockchain.create_transaction(transfer).unwrap();
+            nonce += 1;
+        }
+        blockchain
+            .mine_pending_transactions(&alice.address().0)
+            .unwrap();
+    }
+
+    (blockchain, alice, bob)
+}
@@ -0,0 +1,18 @@
+use std::process::Command;
+
+/// Exposes the commit the node is built from as `FCHAIN_GIT_COMMIT`, when
+/// building from a git checkout
+fn main() {
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=FCHAIN_GIT_COMMIT={}", commit.trim());
+    }
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use log::error;
+
+use super::handlers::common::ErrorResponse;
+use crate::blockchain::crypto::constant_time_eq;
+
+/// Header the trusted gateway of a permissioned chain authenticates with
+pub const GATEWAY_TOKEN_HEADER: &str = "x-gateway-token";
+
+/// Token shared with the trusted gateway of a permissioned chain
+#[derive(Debug, Clone)]
+pub struct GatewayToken(pub Arc<str>);
+
+impl GatewayToken {
+    /// Returns true if the request carries the gateway's token
+    pub fn authenticates(&self, headers: &HeaderMap) -> bool {
+        headers
+            .get(GATEWAY_TOKEN_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|provided| constant_time_eq(provided.as_bytes(), self.0.as_bytes()))
+    }
+}
+
+/// Rejects every request with 403, guarding the routes a read-only node
+/// doesn't serve
+pub async fn reject_read_only(request: Request, _next: Next) -> Response {
+    error!(
+        "{} {} - Node is read-only, returning status 403",
+        request.method(),
+        request.uri().path()
+    );
+    (
+        StatusCode::FORBIDDEN,
+        Json(ErrorResponse {
+            error: "This node is read-only".to_string(),
+            code: "read_only".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// Rejects requests that don't carry the expected `Authorization: Bearer <token>` header
+pub async fn require_bearer_token(
+    State(token): State<Arc<str>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(provided) if constant_time_eq(provided.as_bytes(), token.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => {
+            error!(
+                "{} {} - Missing or invalid bearer token, returning status 401",
+                request.method(),
+                request.uri().path()
+            );
+            (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                Json(ErrorResponse {
+                    error: "Missing or invalid bearer token".to_string(),
+                    code: "unauthorized".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use log::warn;
+use tokio::sync::Semaphore;
+
+use super::handlers::common::ErrorResponse;
+
+/// Seconds a client turned away by [`limit_concurrency`] is asked to wait
+const RETRY_AFTER_SECS: u64 = 1;
+
+/// Admits a request only while fewer than the configured number are in
+/// flight, answering 503 with `Retry-After` otherwise, so a flood of
+/// connections is shed instead of piling up until file descriptors run out
+pub async fn limit_concurrency(
+    State(permits): State<Arc<Semaphore>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // The permit is held until the response is ready
+    let Ok(_permit) = permits.try_acquire_owned() else {
+        warn!(
+            "{} {} - Too many requests in flight, returning status 503",
+            request.method(),
+            request.uri().path()
+        );
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, RETRY_AFTER_SECS.to_string())],
+            Json(ErrorResponse {
+                error: "The server is busy, try again shortly".to_string(),
+                code: "server_busy".to_string(),
+            }),
+        )
+            .into_response();
+    };
+    next.run(request).await
+}
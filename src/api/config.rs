@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use crate::webhook::WebhookConfig;
+
+/// Default maximum request body size for regular endpoints (64 KiB)
+pub const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Default maximum request body size for chain import endpoints (32 MiB)
+pub const DEFAULT_MAX_IMPORT_BODY_BYTES: usize = 32 * 1024 * 1024;
+
+/// Default time a request may take before it is answered with 408 Request Timeout
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default time a read waits for the chain lock before it is answered with 503
+pub const DEFAULT_READ_LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Default number of requests served at once before new ones are answered with 503
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 1024;
+
+/// Configuration for the HTTP API
+#[derive(Debug, Clone)]
+pub struct ApiConfig {
+    /// Maximum request body size in bytes for regular endpoints
+    pub max_body_bytes: usize,
+    /// Maximum request body size in bytes for endpoints that accept whole chains
+    pub max_import_body_bytes: usize,
+    /// Bearer token required by mutating endpoints; `None` leaves them open
+    pub auth_token: Option<String>,
+    /// Time a request may take before it is aborted; streaming routes are exempt
+    pub request_timeout: Duration,
+    /// Time a read may wait for the chain lock before it is answered with 503
+    /// Service Unavailable; `None` waits as long as it takes
+    pub read_lock_timeout: Option<Duration>,
+    /// Requests served at once; more are answered with 503 Service
+    /// Unavailable. `None` serves any number.
+    pub max_concurrent_requests: Option<usize>,
+    /// Webhook notified of every block mined through the API
+    pub webhook: Option<WebhookConfig>,
+    /// Refuse to mine or accept transactions, answering those routes with 403
+    pub read_only: bool,
+    /// Token of the trusted gateway of a permissioned chain. Transactions
+    /// created with it in the `X-Gateway-Token` header needn't be signed.
+    pub gateway_token: Option<String>,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_import_body_bytes: DEFAULT_MAX_IMPORT_BODY_BYTES,
+            auth_token: None,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            read_lock_timeout: Some(DEFAULT_READ_LOCK_TIMEOUT),
+            max_concurrent_requests: Some(DEFAULT_MAX_CONCURRENT_REQUESTS),
+            webhook: None,
+            read_only: false,
+            gateway_token: None,
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::{MutexGuard, TryLockError};
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use tokio::time::Instant;
+
+use crate::blockchain::{Blockchain, BlockchainError, SharedBlockchain};
+
+/// How often a waiting request checks whether the chain lock has been released
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+tokio::task_local! {
+    /// How long the handler of the current request may wait for the chain lock
+    static LOCK_WINDOW: Duration;
+}
+
+/// Bounds how long the request's handler waits for the chain lock to `window`,
+/// see [`lock_chain`], so a read never queues indefinitely behind a long-held
+/// lock
+pub async fn limit_lock_wait(
+    State(window): State<Duration>,
+    request: Request,
+    next: Next,
+) -> Response {
+    LOCK_WINDOW.scope(window, next.run(request)).await
+}
+
+/// Takes the chain lock for a handler.
+///
+/// Under [`limit_lock_wait`], gives up with [`BlockchainError::ChainBusy`]
+/// once the lock has been held elsewhere for the whole window, answered with
+/// 503 and `Retry-After`. Other requests wait for as long as it takes.
+pub async fn lock_chain(
+    blockchain: &SharedBlockchain,
+) -> Result<MutexGuard<'_, Blockchain>, BlockchainError> {
+    let Ok(window) = LOCK_WINDOW.try_with(|window| *window) else {
+        return Ok(blockchain.lock().unwrap());
+    };
+
+    let deadline = Instant::now() + window;
+    loop {
+        match blockchain.try_lock() {
+            Err(TryLockError::WouldBlock) => {}
+            result => return Ok(result.unwrap()),
+        }
+        if Instant::now() >= deadline {
+            return Err(BlockchainError::ChainBusy(window));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::create_shared_blockchain;
+
+    #[tokio::test]
+    async fn test_lock_chain_gives_up_after_the_window() {
+        let blockchain = create_shared_blockchain(1, 50.0);
+        let window = Duration::from_millis(20);
+
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let holder = {
+            let blockchain = blockchain.clone();
+            std::thread::spawn(move || {
+                let _guard = blockchain.lock().unwrap();
+                locked_tx.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(200));
+            })
+        };
+        locked_rx.recv().unwrap();
+
+        let result = LOCK_WINDOW.scope(window, lock_chain(&blockchain)).await;
+        assert!(matches!(result, Err(BlockchainError::ChainBusy(_))));
+
+        holder.join().unwrap();
+        let result = LOCK_WINDOW.scope(window, lock_chain(&blockchain)).await;
+        assert!(result.is_ok());
+    }
+}
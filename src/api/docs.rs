@@ -1,37 +1,181 @@
-use utoipa::OpenApi;
+use utoipa::openapi::{ObjectBuilder, RefOr, Schema, SchemaType};
+use utoipa::{Modify, OpenApi};
 
 use crate::api::handlers::{
-    CreateTransactionRequest, CreateTransactionResponse, MineBlockRequest, MineBlockResponse,
-    ValidateChainResponse,
+    AddressBalance, AddressGraphResponse, BalanceChangeEvent, BalanceChangeSource, BalanceResponse,
+    BalancesRequest, BalancesResponse, BlockWithConfirmations, ChainStatsResponse,
+    ChainTipResponse, ChainWorkResponse, CounterpartyFlowResponse, CreateTransactionRequest,
+    CreateTransactionResponse, DecodeTransactionResponse, ForkInfoResponse, GenesisResponse,
+    HashrateResponse, ImportChainResponse, ImportMempoolResponse, LastSpentResponse,
+    MineBlockRequest, MineBlockResponse, MiningJobResponse, MiningJobStatus, NonceResponse,
+    RebroadcastResponse, SearchResult, SimulateTransactionResponse, SkippedTransaction,
+    TransactionStatusResponse, ValidateAddressResponse, ValidateBlockResponse,
+    ValidateChainResponse, VerifySignatureRequest, VerifySignatureResponse, VersionResponse,
+};
+use crate::blockchain::crypto::{Address, PublicKeyHex, SignatureScheme, TransactionSignature};
+use crate::blockchain::{
+    Block, BlockHeader, BlockSignature, BlockTimeStats, BlockValidationIssue,
+    BlockValidationResult, CompetingTip, InclusionProof, MempoolEvent, MerkleSide, MerkleStep,
+    NonceGap, RewardRecipient, Transaction, TransactionCommitment, TransactionOutput,
+    TransactionStatus, ValidationIssueKind,
 };
-use crate::blockchain::crypto::{Address, PublicKeyHex, TransactionSignature};
-use crate::blockchain::{Block, Transaction};
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         crate::api::handlers::blocks::get_blocks,
+        crate::api::handlers::blocks::get_latest_blocks,
+        crate::api::handlers::blocks::get_block_range,
+        crate::api::handlers::blocks::get_headers,
         crate::api::handlers::transactions::create_transaction,
         crate::api::handlers::transactions::get_pending_transactions,
+        crate::api::handlers::transactions::get_pending_for_address,
+        crate::api::handlers::transactions::submit_raw_transaction,
+        crate::api::handlers::transactions::get_transaction_status,
+        crate::api::handlers::transactions::get_inclusion_proof,
+        crate::api::handlers::transactions::rebroadcast_transaction,
+        crate::api::handlers::transactions::simulate_transaction,
+        crate::api::handlers::transactions::decode_transaction,
         crate::api::handlers::blocks::mine_block,
+        crate::api::handlers::blocks::validate_block,
         crate::api::handlers::chain::validate_chain,
+        crate::api::handlers::chain::import_chain,
+        crate::api::handlers::mempool::export_mempool,
+        crate::api::handlers::mempool::import_mempool,
+        crate::api::handlers::chain::export_chain,
+        crate::api::handlers::chain::get_chain_stats,
+        crate::api::handlers::chain::get_block_times,
+        crate::api::handlers::chain::get_hashrate,
+        crate::api::handlers::chain::get_genesis,
+        crate::api::handlers::chain::get_version,
+        crate::api::handlers::chain::get_chain_tip,
+        crate::api::handlers::chain::get_chain_work,
+        crate::api::handlers::chain::get_fork_info,
+        crate::api::handlers::address::validate_address,
+        crate::api::handlers::address::get_top_addresses,
+        crate::api::handlers::address::get_address_graph,
+        crate::api::handlers::address::get_address_statement,
+        crate::api::handlers::address::get_balance,
+        crate::api::handlers::address::get_balances,
+        crate::api::handlers::address::get_nonce,
+        crate::api::handlers::address::get_last_spent,
+        crate::api::handlers::wallet::verify_signature,
+        crate::api::handlers::search::search,
+        crate::api::handlers::mining::start_mining_job,
+        crate::api::handlers::mining::get_mining_job,
+        crate::api::handlers::mining::cancel_mining_job,
+        crate::api::handlers::ws::mempool_ws,
+        crate::api::handlers::ws::address_ws,
     ),
     components(
         schemas(
             Block,
+            BlockWithConfirmations,
+            BlockHeader,
+            BlockSignature,
+            TransactionCommitment,
             Transaction,
+            TransactionOutput,
+            TransactionStatus,
+            TransactionStatusResponse,
+            InclusionProof,
+            MerkleStep,
+            MerkleSide,
+            RebroadcastResponse,
+            SimulateTransactionResponse,
+            DecodeTransactionResponse,
+            MempoolEvent,
+            BalanceChangeEvent,
+            BalanceChangeSource,
             CreateTransactionRequest,
             CreateTransactionResponse,
             MineBlockRequest,
+            RewardRecipient,
             MineBlockResponse,
+            MiningJobResponse,
+            MiningJobStatus,
+            ValidateBlockResponse,
             ValidateChainResponse,
+            BlockValidationIssue,
+            BlockValidationResult,
+            ValidationIssueKind,
+            ImportChainResponse,
+            ImportMempoolResponse,
+            SkippedTransaction,
+            ChainStatsResponse,
+            BlockTimeStats,
+            GenesisResponse,
+            VersionResponse,
+            ChainTipResponse,
+            ChainWorkResponse,
+            HashrateResponse,
+            ForkInfoResponse,
+            CompetingTip,
+            ValidateAddressResponse,
+            AddressBalance,
+            AddressGraphResponse,
+            CounterpartyFlowResponse,
+            BalanceResponse,
+            BalancesRequest,
+            NonceResponse,
+            LastSpentResponse,
+            NonceGap,
+            BalancesResponse,
+            VerifySignatureRequest,
+            VerifySignatureResponse,
+            SearchResult,
             Address,
             PublicKeyHex,
+            SignatureScheme,
             TransactionSignature,
         )
     ),
     tags(
         (name = "Blockchain", description = "Blockchain management endpoints")
-    )
+    ),
+    modifiers(&CoinbaseFlag)
 )]
 pub struct ApiDoc;
+
+/// Documents the `isCoinbase` flag that [`Transaction`]'s JSON serialization
+/// adds on top of its fields, which the derived schema doesn't know about
+struct CoinbaseFlag;
+
+impl Modify for CoinbaseFlag {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let Some(components) = openapi.components.as_mut() else {
+            return;
+        };
+        if let Some(RefOr::T(Schema::Object(transaction))) =
+            components.schemas.get_mut("Transaction")
+        {
+            let flag = ObjectBuilder::new()
+                .schema_type(SchemaType::Boolean)
+                .read_only(Some(true))
+                .description(Some(
+                    "Whether the transaction pays out a block reward and fees",
+                ));
+            transaction
+                .properties
+                .insert("isCoinbase".to_string(), flag.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_schema_documents_the_coinbase_flag() {
+        let components = ApiDoc::openapi().components.unwrap();
+        let Some(RefOr::T(Schema::Object(transaction))) = components.schemas.get("Transaction")
+        else {
+            panic!("Transaction schema is missing");
+        };
+        let Some(RefOr::T(Schema::Object(flag))) = transaction.properties.get("isCoinbase") else {
+            panic!("isCoinbase is not documented");
+        };
+        assert_eq!(flag.read_only, Some(true));
+    }
+}
@@ -0,0 +1,536 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::IntoResponse,
+    Json,
+};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use crate::api::contention::lock_chain;
+use crate::api::validation::ValidatedJson;
+use crate::blockchain::{
+    Address, AddressHistoryEntry, BlockchainError, NonceGap, SharedBlockchain,
+};
+
+/// Default number of addresses returned by the rich list
+const DEFAULT_TOP_ADDRESSES_LIMIT: usize = 10;
+
+/// Maximum number of addresses returned by the rich list
+const MAX_TOP_ADDRESSES_LIMIT: usize = 100;
+
+/// Response for address validation
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateAddressResponse {
+    /// The address that was checked
+    pub address: String,
+    /// Whether the address is well formed and usable on this node's network
+    pub valid: bool,
+    /// Explanation of the result, including why an invalid address was rejected
+    pub message: String,
+}
+
+/// Query parameters for the rich list
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct TopAddressesQuery {
+    /// Number of addresses to return (default 10, max 100)
+    pub limit: Option<usize>,
+}
+
+/// Confirmed balance of an address
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressBalance {
+    /// The address
+    pub address: String,
+    /// Its confirmed balance
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(value_type = String)
+    )]
+    pub balance: f64,
+}
+
+/// Request for the balances of several addresses
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BalancesRequest {
+    /// Addresses to look up, at most 100
+    #[schema(max_items = 100)]
+    pub addresses: Vec<String>,
+}
+
+/// Balances of several addresses
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BalancesResponse {
+    /// Balance of each requested address, including pending transactions
+    pub balances: BTreeMap<String, f64>,
+}
+
+/// Query parameters for a balance lookup
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct BalanceQuery {
+    /// Return the confirmed balance as of this block height instead of the
+    /// current balance including pending transactions
+    pub at_height: Option<u64>,
+}
+
+/// Balance of an address
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceResponse {
+    /// The address
+    pub address: String,
+    /// Its balance
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(value_type = String)
+    )]
+    pub balance: f64,
+    /// Block height the balance was computed at, if one was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at_height: Option<u64>,
+}
+
+/// Query parameters for an address graph
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct AddressGraphQuery {
+    /// Leave out counterparties the address sent exactly as much as it received from
+    #[serde(default)]
+    pub exclude_zero_net: bool,
+}
+
+/// Confirmed flows between an address and one counterparty
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CounterpartyFlowResponse {
+    /// The counterparty
+    pub counterparty: String,
+    /// Total sent to the counterparty, without fees
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(value_type = String)
+    )]
+    pub sent: f64,
+    /// Total received from the counterparty
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(value_type = String)
+    )]
+    pub received: f64,
+    /// Received minus sent
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(value_type = String)
+    )]
+    pub net: f64,
+}
+
+/// Counterparties of an address and the flows to and from each
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressGraphResponse {
+    /// The address
+    pub address: String,
+    /// One entry per counterparty, ordered by counterparty address
+    pub counterparties: Vec<CounterpartyFlowResponse>,
+}
+
+/// Nonce information for an address
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NonceResponse {
+    /// The address that was looked up
+    pub address: String,
+    /// Nonce the address's next transaction should carry
+    pub next_nonce: u64,
+    /// Highest nonce used by a confirmed transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmed_nonce: Option<u64>,
+    /// Nonces skipped by pending transactions, which hold them back from mining
+    pub gaps: Vec<NonceGap>,
+}
+
+/// Get the next expected nonce of an address
+#[utoipa::path(
+    get,
+    path = "/address/{address}/nonce",
+    tag = "Blockchain",
+    params(
+        ("address" = String, Path, description = "Address to look up")
+    ),
+    responses(
+        (status = 200, description = "Nonce information for the address", body = NonceResponse)
+    )
+)]
+pub async fn get_nonce(
+    State(blockchain): State<SharedBlockchain>,
+    Path(address): Path<String>,
+) -> Result<Json<NonceResponse>, BlockchainError> {
+    info!("GET /address/{}/nonce - Retrieving nonce state", address);
+
+    let state = lock_chain(&blockchain).await?.nonce_state(&address);
+
+    info!(
+        "GET /address/{}/nonce - Next nonce {} with {} gaps, returning status 200",
+        address,
+        state.next,
+        state.gaps.len()
+    );
+    Ok(Json(NonceResponse {
+        address,
+        next_nonce: state.next,
+        confirmed_nonce: state.confirmed,
+        gaps: state.gaps,
+    }))
+}
+
+/// Latest block in which an address spent funds
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LastSpentResponse {
+    /// The address that was looked up
+    pub address: String,
+    /// Index of the latest block with a transaction sent by the address, or
+    /// null if it has never sent one
+    pub last_spent_height: Option<u64>,
+    /// Index of the latest block, up to which the answer holds
+    pub height: u64,
+}
+
+/// Get the latest block in which an address sent a transaction.
+///
+/// An address whose funds haven't moved since a given height, e.g. cold
+/// storage backing a proof of reserve, has no spend after that height.
+#[utoipa::path(
+    get,
+    path = "/address/{address}/last-spent",
+    tag = "Blockchain",
+    params(
+        ("address" = String, Path, description = "Address to look up")
+    ),
+    responses(
+        (status = 200, description = "Latest block the address spent in", body = LastSpentResponse)
+    )
+)]
+pub async fn get_last_spent(
+    State(blockchain): State<SharedBlockchain>,
+    Path(address): Path<String>,
+) -> Result<Json<LastSpentResponse>, BlockchainError> {
+    info!("GET /address/{}/last-spent - Scanning for spends", address);
+
+    let blockchain = lock_chain(&blockchain).await?;
+    let last_spent_height = blockchain.last_spent_height(&address);
+    let height = blockchain.get_latest_block().map_or(0, |block| block.index);
+
+    info!(
+        "GET /address/{}/last-spent - Last spent at {:?}, returning status 200",
+        address, last_spent_height
+    );
+    Ok(Json(LastSpentResponse {
+        address,
+        last_spent_height,
+        height,
+    }))
+}
+
+/// Get the counterparties of an address with the confirmed flows to and from each
+#[utoipa::path(
+    get,
+    path = "/address/{address}/graph",
+    tag = "Blockchain",
+    params(
+        ("address" = String, Path, description = "Address to look up"),
+        AddressGraphQuery
+    ),
+    responses(
+        (status = 200, description = "Counterparties of the address", body = AddressGraphResponse)
+    )
+)]
+pub async fn get_address_graph(
+    State(blockchain): State<SharedBlockchain>,
+    Path(address): Path<String>,
+    Query(query): Query<AddressGraphQuery>,
+) -> Result<Json<AddressGraphResponse>, BlockchainError> {
+    info!("GET /address/{}/graph - Aggregating flows", address);
+
+    let counterparties: Vec<CounterpartyFlowResponse> = lock_chain(&blockchain)
+        .await?
+        .address_flows(&address)
+        .into_iter()
+        .filter(|flow| !query.exclude_zero_net || flow.net() != 0.0)
+        .map(|flow| CounterpartyFlowResponse {
+            net: flow.net(),
+            counterparty: flow.counterparty,
+            sent: flow.sent,
+            received: flow.received,
+        })
+        .collect();
+
+    info!(
+        "GET /address/{}/graph - Returning {} counterparties with status 200",
+        address,
+        counterparties.len()
+    );
+    Ok(Json(AddressGraphResponse {
+        address,
+        counterparties,
+    }))
+}
+
+/// Columns of an address statement
+const STATEMENT_HEADER: &str = "timestamp,block_index,counterparty,direction,amount,balance";
+
+/// Quotes a CSV field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Formats one statement row: who the address dealt with, which way the
+/// coins went and how much its balance changed, fees included
+fn statement_row(address: &str, entry: &AddressHistoryEntry) -> String {
+    let transaction = &entry.transaction;
+    let others: Vec<&str> = transaction
+        .credits()
+        .into_iter()
+        .map(|(recipient, _)| recipient.0.as_str())
+        .filter(|recipient| *recipient != address)
+        .collect();
+    let (counterparty, direction) = if transaction.sender.0 != address {
+        (transaction.sender.0.clone(), "in")
+    } else if others.is_empty() {
+        (address.to_string(), "self")
+    } else {
+        (others.join(";"), "out")
+    };
+
+    format!(
+        "{},{},{},{},{},{}",
+        entry.block_timestamp.to_rfc3339(),
+        entry.block_index,
+        csv_field(&counterparty),
+        direction,
+        entry.delta.abs(),
+        entry.balance
+    )
+}
+
+/// Download the confirmed transactions of an address as a CSV statement
+#[utoipa::path(
+    get,
+    path = "/address/{address}/statement.csv",
+    tag = "Blockchain",
+    params(
+        ("address" = String, Path, description = "Address to build the statement for")
+    ),
+    responses(
+        (status = 200, description = "One row per confirmed transaction with the running balance", body = String, content_type = "text/csv")
+    )
+)]
+pub async fn get_address_statement(
+    State(blockchain): State<SharedBlockchain>,
+    Path(address): Path<String>,
+) -> Result<impl IntoResponse, BlockchainError> {
+    info!(
+        "GET /address/{}/statement.csv - Building statement",
+        address
+    );
+
+    let history = lock_chain(&blockchain).await?.address_history(&address);
+    let mut csv = String::from(STATEMENT_HEADER);
+    csv.push('\n');
+    for entry in &history {
+        csv.push_str(&statement_row(&address, entry));
+        csv.push('\n');
+    }
+
+    // Only keep characters that are safe in a header value
+    let file_name: String = address
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+    info!(
+        "GET /address/{}/statement.csv - Returning {} rows with status 200",
+        address,
+        history.len()
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"statement-{}.csv\"", file_name),
+            ),
+        ],
+        csv,
+    ))
+}
+
+/// Get the balance of an address
+#[utoipa::path(
+    get,
+    path = "/balance/{address}",
+    tag = "Blockchain",
+    params(
+        ("address" = String, Path, description = "Address to look up"),
+        BalanceQuery
+    ),
+    responses(
+        (status = 200, description = "Balance of the address", body = BalanceResponse),
+        (status = 404, description = "The height is past the chain tip", body = ErrorResponse)
+    )
+)]
+pub async fn get_balance(
+    State(blockchain): State<SharedBlockchain>,
+    Path(address): Path<String>,
+    Query(query): Query<BalanceQuery>,
+) -> Result<Json<BalanceResponse>, BlockchainError> {
+    info!(
+        "GET /balance/{} - Retrieving balance at height {:?}",
+        address, query.at_height
+    );
+
+    let blockchain = lock_chain(&blockchain).await?;
+    let balance = match query.at_height {
+        Some(height) => {
+            let tip = blockchain.chain_length().saturating_sub(1) as u64;
+            if height > tip {
+                error!(
+                    "GET /balance/{} - Height {} is past the tip {}",
+                    address, height, tip
+                );
+                return Err(BlockchainError::NotFound(format!(
+                    "block at height {} (tip is {})",
+                    height, tip
+                )));
+            }
+            blockchain.get_balance_at_height(&address, height)
+        }
+        None => blockchain.get_balance(&address),
+    };
+
+    info!(
+        "GET /balance/{} - Returning balance {} with status 200",
+        address, balance
+    );
+    Ok(Json(BalanceResponse {
+        address,
+        balance,
+        at_height: query.at_height,
+    }))
+}
+
+/// Get the balances of several addresses at once
+#[utoipa::path(
+    post,
+    path = "/balances",
+    tag = "Blockchain",
+    request_body = BalancesRequest,
+    responses(
+        (status = 200, description = "Balance of each address", body = BalancesResponse),
+        (status = 400, description = "Too many addresses", body = ErrorResponse)
+    )
+)]
+pub async fn get_balances(
+    State(blockchain): State<SharedBlockchain>,
+    ValidatedJson(request): ValidatedJson<BalancesRequest>,
+) -> Result<Json<BalancesResponse>, BlockchainError> {
+    info!(
+        "POST /balances - Retrieving balances of {} addresses",
+        request.addresses.len()
+    );
+
+    let balances = lock_chain(&blockchain)
+        .await?
+        .get_balances(&request.addresses)
+        .into_iter()
+        .collect();
+
+    info!("POST /balances - Returning balances with status 200");
+    Ok(Json(BalancesResponse { balances }))
+}
+
+/// Validate an address
+#[utoipa::path(
+    get,
+    path = "/address/{address}/validate",
+    tag = "Blockchain",
+    params(
+        ("address" = String, Path, description = "Address to validate")
+    ),
+    responses(
+        (status = 200, description = "Address validation result", body = ValidateAddressResponse)
+    )
+)]
+pub async fn validate_address(
+    State(blockchain): State<SharedBlockchain>,
+    Path(address): Path<String>,
+) -> Result<Json<ValidateAddressResponse>, BlockchainError> {
+    info!("GET /address/{}/validate - Validating address", address);
+
+    let network = lock_chain(&blockchain).await?.network;
+    let (valid, message) = match Address(address.clone()).validate_for(network) {
+        Ok(_) => (true, "Address is valid".to_string()),
+        Err(err) => (false, err.to_string()),
+    };
+
+    info!(
+        "GET /address/{}/validate - Address valid: {}, returning status 200",
+        address, valid
+    );
+    Ok(Json(ValidateAddressResponse {
+        address,
+        valid,
+        message,
+    }))
+}
+
+/// Get the addresses with the highest confirmed balances
+#[utoipa::path(
+    get,
+    path = "/addresses/top",
+    tag = "Blockchain",
+    params(TopAddressesQuery),
+    responses(
+        (status = 200, description = "Addresses by confirmed balance, richest first", body = Vec<AddressBalance>)
+    )
+)]
+pub async fn get_top_addresses(
+    State(blockchain): State<SharedBlockchain>,
+    Query(query): Query<TopAddressesQuery>,
+) -> Result<Json<Vec<AddressBalance>>, BlockchainError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_TOP_ADDRESSES_LIMIT)
+        .min(MAX_TOP_ADDRESSES_LIMIT);
+    info!(
+        "GET /addresses/top - Retrieving the top {} addresses",
+        limit
+    );
+
+    let top: Vec<AddressBalance> = lock_chain(&blockchain)
+        .await?
+        .richest_addresses(limit)
+        .into_iter()
+        .map(|(address, balance)| AddressBalance { address, balance })
+        .collect();
+
+    info!(
+        "GET /addresses/top - Returning {} addresses with status 200",
+        top.len()
+    );
+    Ok(Json(top))
+}
@@ -1,46 +1,323 @@
-use axum::{extract::State, Json};
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::{
+    extract::{Query, State},
+    response::Response,
+    Extension, Json,
+};
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::blockchain::{Block, BlockchainError, SharedBlockchain};
+use super::common::ResponseFormat;
+use crate::api::contention::lock_chain;
+use crate::api::validation::ValidatedJson;
+use crate::blockchain::{
+    mine_shared_split, Block, Blockchain, BlockchainError, RewardRecipient, SharedBlockchain,
+};
+use crate::webhook::Webhook;
+
+/// Default number of blocks returned by the latest blocks endpoint
+const DEFAULT_LATEST_BLOCKS_COUNT: usize = 10;
+
+/// Maximum number of blocks returned by the latest blocks endpoint
+const MAX_LATEST_BLOCKS_COUNT: usize = 100;
+
+/// Default number of headers returned per page
+const DEFAULT_HEADERS_LIMIT: usize = 100;
+
+/// Maximum number of headers returned per page
+const MAX_HEADERS_LIMIT: usize = 1000;
+
+/// Maximum number of blocks returned by the block range endpoint
+const MAX_BLOCK_RANGE: u64 = 500;
+
+/// A block in a JSON response, with how deep it is in the chain
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockWithConfirmations<'a> {
+    #[serde(flatten)]
+    pub block: &'a Block,
+    /// Number of blocks from this one up to and including the tip
+    pub confirmations: u64,
+}
+
+/// Query parameters for the latest blocks
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct LatestBlocksQuery {
+    /// Number of blocks to return (default 10, max 100)
+    pub count: Option<usize>,
+}
+
+/// Query parameters for a page of block headers
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct HeadersQuery {
+    /// Index of the first header to return (default 0)
+    pub start: Option<u64>,
+    /// Number of headers to return (default 100, max 1000)
+    pub limit: Option<usize>,
+}
+
+/// Query parameters for a range of blocks
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct BlockRangeQuery {
+    /// Index of the first block to return
+    pub from: u64,
+    /// Index of the last block to return (at most 500 blocks after `from`)
+    pub to: u64,
+}
 
 /// Request to mine a new block
 #[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct MineBlockRequest {
-    /// The address where mining rewards should be sent
+    /// The address where mining rewards should be sent; ignored when
+    /// `rewardRecipients` is given
+    #[schema(max_length = 64)]
     pub miner_address: String,
+    /// Addresses to split the reward, fees included, among by weight, e.g.
+    /// the payout addresses of a mining pool, at most 100
+    #[schema(max_items = 100)]
+    pub reward_recipients: Option<Vec<RewardRecipient>>,
+}
+
+impl MineBlockRequest {
+    /// Addresses the block's reward goes to: `rewardRecipients` if given,
+    /// otherwise the miner address alone
+    pub fn into_recipients(self) -> Vec<RewardRecipient> {
+        self.reward_recipients
+            .unwrap_or_else(|| vec![RewardRecipient::new(self.miner_address, 1)])
+    }
 }
 
 /// Response for a successful block mining
 #[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct MineBlockResponse {
     /// Success message
     pub message: String,
     /// The mined block
     pub block: Block,
+    /// Block subsidy paid to the miner, excluding fees
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(value_type = String)
+    )]
+    pub reward: f64,
+    /// Fees collected from the block's transactions
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(value_type = String)
+    )]
+    pub total_fees: f64,
+    /// Number of transactions in the block, not counting the rewards
+    pub transaction_count: usize,
+    /// Time spent assembling and mining the block, in milliseconds
+    pub mining_duration_ms: u64,
+}
+
+/// Outcome of validating a block against the current tip
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateBlockResponse {
+    /// Whether the block could be appended on top of the current tip
+    pub valid: bool,
+    /// Why the block would be rejected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
 }
 
-/// Get all blocks in the chain
+/// Get all blocks in the chain.
+///
+/// The response body is serialized from behind the chain lock; the chain
+/// itself is not cloned.
+/// Send `Accept: application/octet-stream` to receive bincode instead of JSON.
 #[utoipa::path(
     get,
     path = "/blocks",
     tag = "Blockchain",
     responses(
-        (status = 200, description = "List of all blocks in the chain", body = Vec<Block>)
+        (status = 200, description = "List of all blocks in the chain", body = Vec<Block>,
+            content_type = ["application/json", "application/octet-stream"])
     )
 )]
-pub async fn get_blocks(State(blockchain): State<SharedBlockchain>) -> Json<Vec<Block>> {
-    info!("GET /blocks - Retrieving all blocks");
+pub async fn get_blocks(
+    State(blockchain): State<SharedBlockchain>,
+    format: ResponseFormat,
+) -> Result<Response, BlockchainError> {
+    info!("GET /blocks - Retrieving all blocks as {:?}", format);
 
-    let blockchain = blockchain.lock().unwrap();
-    let blocks = blockchain.chain.clone();
+    let (count, response) = {
+        let blockchain = lock_chain(&blockchain).await?;
+        (blockchain.chain.len(), format.respond(&blockchain.chain))
+    };
 
     info!(
-        "GET /blocks - Returning {} blocks with status 200",
-        blocks.len()
+        "GET /blocks - Returning {} blocks with status {}",
+        count,
+        response.status().as_u16()
     );
-    Json(blocks)
+    Ok(response)
+}
+
+/// Get the most recent blocks, newest first
+#[utoipa::path(
+    get,
+    path = "/blocks/latest",
+    tag = "Blockchain",
+    params(LatestBlocksQuery),
+    responses(
+        (status = 200, description = "The most recent blocks, newest first", body = Vec<BlockWithConfirmations>,
+            content_type = ["application/json", "application/octet-stream"])
+    )
+)]
+pub async fn get_latest_blocks(
+    State(blockchain): State<SharedBlockchain>,
+    Query(query): Query<LatestBlocksQuery>,
+    format: ResponseFormat,
+) -> Result<Response, BlockchainError> {
+    let count = query
+        .count
+        .unwrap_or(DEFAULT_LATEST_BLOCKS_COUNT)
+        .min(MAX_LATEST_BLOCKS_COUNT);
+    info!(
+        "GET /blocks/latest - Retrieving the latest {} blocks",
+        count
+    );
+
+    let (returned, response) = {
+        let blockchain = lock_chain(&blockchain).await?;
+        let blocks = blockchain.latest_blocks(count);
+        (
+            blocks.len(),
+            respond_with_blocks(format, &blockchain, &blocks),
+        )
+    };
+
+    info!(
+        "GET /blocks/latest - Returning {} blocks with status {}",
+        returned,
+        response.status().as_u16()
+    );
+    Ok(response)
+}
+
+/// Get the blocks with index `from` through `to`, inclusive, oldest first.
+///
+/// Lets a syncing peer fetch a contiguous slice of the chain in one request.
+/// Send `Accept: application/octet-stream` to receive bincode instead of JSON.
+#[utoipa::path(
+    get,
+    path = "/blocks/range",
+    tag = "Blockchain",
+    params(BlockRangeQuery),
+    responses(
+        (status = 200, description = "Blocks in chain order", body = Vec<BlockWithConfirmations>,
+            content_type = ["application/json", "application/octet-stream"]),
+        (status = 400, description = "The range is inverted, past the tip or too large", body = ErrorResponse)
+    )
+)]
+pub async fn get_block_range(
+    State(blockchain): State<SharedBlockchain>,
+    Query(query): Query<BlockRangeQuery>,
+    format: ResponseFormat,
+) -> Result<Response, BlockchainError> {
+    info!(
+        "GET /blocks/range - Retrieving blocks {} to {}",
+        query.from, query.to
+    );
+    if query.to.saturating_sub(query.from) >= MAX_BLOCK_RANGE {
+        error!(
+            "GET /blocks/range - Range {} to {} exceeds {} blocks",
+            query.from, query.to, MAX_BLOCK_RANGE
+        );
+        return Err(BlockchainError::InvalidRange(format!(
+            "at most {} blocks may be requested at once",
+            MAX_BLOCK_RANGE
+        )));
+    }
+
+    let (count, response) = {
+        let blockchain = lock_chain(&blockchain).await?;
+        let range = blockchain.blocks_in_range(query.from, query.to)?;
+        let blocks: Vec<&Block> = range.iter().collect();
+        (
+            blocks.len(),
+            respond_with_blocks(format, &blockchain, &blocks),
+        )
+    };
+
+    info!(
+        "GET /blocks/range - Returning {} blocks with status {}",
+        count,
+        response.status().as_u16()
+    );
+    Ok(response)
+}
+
+/// Encodes blocks in the requested format. JSON adds each block's
+/// confirmations; bincode keeps the plain blocks peers decode.
+fn respond_with_blocks(
+    format: ResponseFormat,
+    blockchain: &Blockchain,
+    blocks: &[&Block],
+) -> Response {
+    match format {
+        ResponseFormat::Json => {
+            let blocks: Vec<BlockWithConfirmations> = blocks
+                .iter()
+                .map(|block| BlockWithConfirmations {
+                    block,
+                    confirmations: blockchain.confirmations_for(block.index),
+                })
+                .collect();
+            format.respond(&blocks)
+        }
+        ResponseFormat::Binary => format.respond(blocks),
+    }
+}
+
+/// Get a page of block headers, oldest first.
+///
+/// Headers carry everything needed to check proof of work and linkage without
+/// downloading the transactions.
+#[utoipa::path(
+    get,
+    path = "/headers",
+    tag = "Blockchain",
+    params(HeadersQuery),
+    responses(
+        (status = 200, description = "Block headers in chain order", body = Vec<BlockHeader>,
+            content_type = ["application/json", "application/octet-stream"])
+    )
+)]
+pub async fn get_headers(
+    State(blockchain): State<SharedBlockchain>,
+    Query(query): Query<HeadersQuery>,
+    format: ResponseFormat,
+) -> Result<Response, BlockchainError> {
+    let start = query.start.unwrap_or(0);
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_HEADERS_LIMIT)
+        .min(MAX_HEADERS_LIMIT);
+    info!(
+        "GET /headers - Retrieving up to {} headers from index {}",
+        limit, start
+    );
+
+    let headers = lock_chain(&blockchain).await?.headers(start, limit);
+    let response = format.respond(&headers);
+
+    info!(
+        "GET /headers - Returning {} headers with status {}",
+        headers.len(),
+        response.status().as_u16()
+    );
+    Ok(response)
 }
 
 /// Mine a new block
@@ -56,22 +333,50 @@ pub async fn get_blocks(State(blockchain): State<SharedBlockchain>) -> Json<Vec<
 )]
 pub async fn mine_block(
     State(blockchain): State<SharedBlockchain>,
-    Json(request): Json<MineBlockRequest>,
+    webhook: Option<Extension<Arc<Webhook>>>,
+    ValidatedJson(request): ValidatedJson<MineBlockRequest>,
 ) -> Result<Json<MineBlockResponse>, BlockchainError> {
     info!(
         "POST /blocks/mine - Mining new block for miner: {}",
         request.miner_address
     );
 
-    let mut blockchain = blockchain.lock().unwrap();
-    match blockchain.mine_pending_transactions(&request.miner_address) {
-        Ok(block) => {
+    // Proof of work runs off the async runtime and without holding the chain lock
+    let recipients = request.into_recipients();
+    let result = tokio::task::spawn_blocking(move || {
+        let started = Instant::now();
+        let block = mine_shared_split(&blockchain, &recipients)?;
+        let reward = blockchain.lock().unwrap().mining_reward;
+        Ok((block, reward, started.elapsed()))
+    })
+    .await
+    .unwrap_or_else(|e| {
+        Err(BlockchainError::ValidationFailed(format!(
+            "Mining task failed: {}",
+            e
+        )))
+    });
+
+    match result {
+        Ok((block, reward, duration)) => {
             info!(
                 "POST /blocks/mine - Block #{} mined successfully with status 200",
                 block.index
             );
+            if let Some(Extension(webhook)) = webhook {
+                let block = block.clone();
+                tokio::spawn(async move { webhook.notify_block_mined(&block).await });
+            }
             Ok(Json(MineBlockResponse {
                 message: "Block mined successfully".to_string(),
+                reward,
+                total_fees: block.total_fees(),
+                transaction_count: block
+                    .transactions
+                    .iter()
+                    .filter(|tx| !tx.is_coinbase())
+                    .count(),
+                mining_duration_ms: u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
                 block,
             }))
         }
@@ -81,3 +386,83 @@ pub async fn mine_block(
         }
     }
 }
+
+/// Check whether a block could be appended to the chain, without appending it
+#[utoipa::path(
+    post,
+    path = "/blocks/validate",
+    tag = "Blockchain",
+    request_body = Block,
+    responses(
+        (status = 200, description = "Validation result", body = ValidateBlockResponse)
+    )
+)]
+pub async fn validate_block(
+    State(blockchain): State<SharedBlockchain>,
+    Json(block): Json<Block>,
+) -> Result<Json<ValidateBlockResponse>, BlockchainError> {
+    info!(
+        "POST /blocks/validate - Validating block #{} with hash {}",
+        block.index, block.hash
+    );
+
+    let mut blockchain = lock_chain(&blockchain).await?;
+    let response = match blockchain.validate_next_block(&block) {
+        Ok(()) => ValidateBlockResponse {
+            valid: true,
+            reason: None,
+        },
+        Err(err) => {
+            // A block for a height the chain already has may signal a fork
+            blockchain.note_competing_block(&block);
+            ValidateBlockResponse {
+                valid: false,
+                reason: Some(err.to_string()),
+            }
+        }
+    };
+
+    info!(
+        "POST /blocks/validate - Valid: {}, returning status 200",
+        response.valid
+    );
+    Ok(Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::create_shared_blockchain;
+    use crate::blockchain::test_utils::MINER;
+    use axum::http::header;
+    use axum::response::IntoResponse;
+
+    #[tokio::test]
+    async fn test_get_blocks_matches_cloned_json() {
+        let blockchain = create_shared_blockchain(1, 50.0);
+        for _ in 0..3 {
+            blockchain
+                .lock()
+                .unwrap()
+                .mine_pending_transactions(MINER)
+                .unwrap();
+        }
+
+        let response = get_blocks(State(blockchain.clone()), ResponseFormat::Json)
+            .await
+            .unwrap();
+        let expected = Json(blockchain.lock().unwrap().chain.clone()).into_response();
+
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            expected.headers()[header::CONTENT_TYPE]
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let expected = axum::body::to_bytes(expected.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, expected);
+    }
+}
@@ -1,24 +1,182 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::{Query, State},
+    response::Response,
+    Json,
+};
+use chrono::{DateTime, Utc};
 use log::{error, info};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::blockchain::{BlockchainError, SharedBlockchain};
+use super::common::ResponseFormat;
+use crate::api::contention::lock_chain;
+use crate::blockchain::crypto::{ADDRESS_SCHEME, HASH_ALGORITHM};
+use crate::blockchain::{
+    Block, BlockTimeStats, BlockValidationIssue, BlockValidationResult, BlockchainError,
+    CompetingTip, SharedBlockchain, PROTOCOL_VERSION,
+};
+
+/// Default number of blocks covered by the block time statistics
+const DEFAULT_BLOCK_TIME_COUNT: usize = 10;
+
+/// Maximum number of blocks covered by the block time statistics
+const MAX_BLOCK_TIME_COUNT: usize = 1000;
 
 /// Response for chain validation
 #[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct ValidateChainResponse {
     /// Whether the chain is valid
     pub valid: bool,
     /// Additional information about the validation
     pub message: String,
+    /// Every problem found, when validating verbosely
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issues: Option<Vec<BlockValidationIssue>>,
+    /// Verdict on every block, when validating in detail
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocks: Option<Vec<BlockValidationResult>>,
+}
+
+/// Query parameters for chain validation
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct ValidateChainQuery {
+    /// Report every problem instead of failing on the first one
+    pub verbose: Option<bool>,
+    /// Also give a verdict on every block
+    pub detailed: Option<bool>,
+}
+
+/// Response for a successful chain import
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportChainResponse {
+    /// Success message
+    pub message: String,
+    /// Length of the chain after the import
+    pub length: usize,
+}
+
+/// Summary statistics about the chain
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainStatsResponse {
+    /// Index of the latest block
+    pub height: u64,
+    /// Number of pending transactions
+    pub pending_transactions: usize,
+    /// Current mining difficulty
+    pub difficulty: usize,
+    /// Current mining reward
+    pub mining_reward: f64,
+    /// Total number of coins issued by the system
+    pub total_supply: f64,
+    /// Whether confirmed balances add up to the total supply
+    pub supply_conserved: bool,
+    /// Coins held by addresses nobody can spend from, e.g. malformed ones
+    pub unspendable_supply: f64,
+}
+
+/// Network descriptor: the genesis block and the parameters of the chain
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GenesisResponse {
+    /// Identifier of the network
+    pub chain_id: String,
+    /// The genesis block
+    pub genesis: Block,
+    /// Current mining difficulty
+    pub difficulty: usize,
+    /// Current mining reward
+    pub mining_reward: f64,
+    /// Intended time between blocks, in seconds
+    pub target_block_time: u64,
+    /// Hash function used for blocks, transactions and addresses
+    pub hash_algorithm: String,
+    /// How addresses are derived from public keys
+    pub address_scheme: String,
+}
+
+/// The latest block of the chain, for clients deciding whether to resync
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainTipResponse {
+    /// Index of the latest block
+    pub height: u64,
+    /// Hash of the latest block
+    pub hash: String,
+    /// When the latest block was mined
+    pub timestamp: DateTime<Utc>,
+    /// Difficulty the latest block was mined at
+    pub difficulty: usize,
+}
+
+/// Cumulative work of the chain, which fork choice compares
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainWorkResponse {
+    /// Index of the latest block
+    pub height: u64,
+    /// Combined work of every block from genesis to the tip, as a decimal
+    /// string since it may exceed what JSON numbers hold exactly
+    pub total_work: String,
 }
 
-/// Validate the blockchain
+/// Software and protocol a node runs
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionResponse {
+    /// Version of the node software
+    pub version: String,
+    /// Version of the block, transaction and peer formats
+    pub protocol_version: u32,
+    /// Identifier of the network
+    pub chain_id: String,
+    /// Commit the node was built from, if it was built from a git checkout
+    pub git_commit: Option<String>,
+}
+
+/// Estimated mining power of the network
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HashrateResponse {
+    /// Estimated hashes per second, absent when the chain is too short to tell
+    pub hashes_per_second: Option<f64>,
+    /// Number of most recent blocks the estimate covers
+    pub blocks: usize,
+}
+
+/// Whether competing blocks suggest the network has forked
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkInfoResponse {
+    /// True if any recently seen block competes with the chain
+    pub fork_detected: bool,
+    /// Height of the chain's own tip
+    pub tip_height: u64,
+    /// The competing blocks, oldest first
+    pub competing_tips: Vec<CompetingTip>,
+}
+
+/// Query parameters for block time statistics
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct BlockTimesQuery {
+    /// Number of most recent blocks to include (default 10, max 1000)
+    pub count: Option<usize>,
+}
+
+/// Validate the blockchain.
+///
+/// With `verbose=true` the whole chain is checked and every problem is listed
+/// in a 200 response, instead of failing with 400 on the first one.
+/// With `detailed=true` the response also lists, for every block, whether it
+/// is valid, meets its proof of work and links to the previous block, and is
+/// likewise 200 even when the chain is invalid.
 #[utoipa::path(
     get,
     path = "/chain/validate",
     tag = "Blockchain",
+    params(ValidateChainQuery),
     responses(
         (status = 200, description = "Chain validation result", body = ValidateChainResponse),
         (status = 400, description = "Validation failed", body = ErrorResponse)
@@ -26,16 +184,38 @@ pub struct ValidateChainResponse {
 )]
 pub async fn validate_chain(
     State(blockchain): State<SharedBlockchain>,
+    Query(query): Query<ValidateChainQuery>,
 ) -> Result<Json<ValidateChainResponse>, BlockchainError> {
     info!("GET /chain/validate - Validating blockchain");
 
-    let blockchain = blockchain.lock().unwrap();
+    let blockchain = lock_chain(&blockchain).await?;
+    let detailed = query.detailed.unwrap_or(false);
+    if query.verbose.unwrap_or(false) || detailed {
+        let issues = blockchain.validate_full();
+        info!(
+            "GET /chain/validate - Found {} issues, returning status 200",
+            issues.len()
+        );
+        return Ok(Json(ValidateChainResponse {
+            valid: issues.is_empty(),
+            message: if issues.is_empty() {
+                "Blockchain is valid".to_string()
+            } else {
+                format!("Found {} problems", issues.len())
+            },
+            issues: query.verbose.unwrap_or(false).then_some(issues),
+            blocks: detailed.then(|| blockchain.block_verdicts()),
+        }));
+    }
+
     match blockchain.is_chain_valid() {
         Ok(_) => {
             info!("GET /chain/validate - Blockchain is valid, returning status 200");
             Ok(Json(ValidateChainResponse {
                 valid: true,
                 message: "Blockchain is valid".to_string(),
+                issues: None,
+                blocks: None,
             }))
         }
         Err(err) => {
@@ -47,3 +227,345 @@ pub async fn validate_chain(
         }
     }
 }
+
+/// Import a chain from another node
+#[utoipa::path(
+    post,
+    path = "/chain/import",
+    tag = "Blockchain",
+    request_body = Vec<Block>,
+    responses(
+        (status = 200, description = "Chain replaced successfully", body = ImportChainResponse),
+        (status = 400, description = "Candidate chain rejected", body = ErrorResponse)
+    )
+)]
+pub async fn import_chain(
+    State(blockchain): State<SharedBlockchain>,
+    Json(blocks): Json<Vec<Block>>,
+) -> Result<Json<ImportChainResponse>, BlockchainError> {
+    info!(
+        "POST /chain/import - Importing candidate chain of {} blocks",
+        blocks.len()
+    );
+
+    let mut blockchain = blockchain.lock().unwrap();
+    let candidate_tip = blocks.last().cloned();
+    match blockchain.replace_chain(blocks) {
+        Ok(_) => {
+            let length = blockchain.chain_length();
+            info!(
+                "POST /chain/import - Chain replaced, new length {} with status 200",
+                length
+            );
+            Ok(Json(ImportChainResponse {
+                message: "Chain replaced successfully".to_string(),
+                length,
+            }))
+        }
+        Err(err) => {
+            error!("POST /chain/import - Candidate chain rejected: {}", err);
+            if let Some(tip) = candidate_tip {
+                blockchain.note_competing_block(&tip);
+            }
+            Err(err)
+        }
+    }
+}
+
+/// Export the chain for another node to import.
+///
+/// Send `Accept: application/octet-stream` to receive bincode instead of JSON.
+#[utoipa::path(
+    get,
+    path = "/chain/export",
+    tag = "Blockchain",
+    responses(
+        (status = 200, description = "All blocks in the chain", body = Vec<Block>,
+            content_type = ["application/json", "application/octet-stream"])
+    )
+)]
+pub async fn export_chain(
+    State(blockchain): State<SharedBlockchain>,
+    format: ResponseFormat,
+) -> Result<Response, BlockchainError> {
+    info!("GET /chain/export - Exporting chain as {:?}", format);
+
+    let (count, response) = {
+        let blockchain = lock_chain(&blockchain).await?;
+        (blockchain.chain.len(), format.respond(&blockchain.chain))
+    };
+
+    info!(
+        "GET /chain/export - Exported {} blocks with status {}",
+        count,
+        response.status().as_u16()
+    );
+    Ok(response)
+}
+
+/// Get chain statistics
+#[utoipa::path(
+    get,
+    path = "/chain/stats",
+    tag = "Blockchain",
+    responses(
+        (status = 200, description = "Chain statistics", body = ChainStatsResponse)
+    )
+)]
+pub async fn get_chain_stats(
+    State(blockchain): State<SharedBlockchain>,
+) -> Result<Json<ChainStatsResponse>, BlockchainError> {
+    info!("GET /chain/stats - Computing chain statistics");
+
+    let blockchain = lock_chain(&blockchain).await?;
+    let supply_conserved = match blockchain.check_supply_conservation() {
+        Ok(_) => true,
+        Err(err) => {
+            error!(
+                "GET /chain/stats - Supply conservation check failed: {}",
+                err
+            );
+            false
+        }
+    };
+
+    let stats = ChainStatsResponse {
+        height: blockchain.get_latest_block().map_or(0, |block| block.index),
+        pending_transactions: blockchain.pending_transactions.len(),
+        difficulty: blockchain.difficulty,
+        mining_reward: blockchain.mining_reward,
+        total_supply: blockchain.total_supply(),
+        supply_conserved,
+        unspendable_supply: blockchain.unspendable_supply(),
+    };
+
+    info!("GET /chain/stats - Returning statistics with status 200");
+    Ok(Json(stats))
+}
+
+/// Get the genesis block and network parameters
+#[utoipa::path(
+    get,
+    path = "/chain/genesis",
+    tag = "Blockchain",
+    responses(
+        (status = 200, description = "Network descriptor", body = GenesisResponse),
+        (status = 404, description = "The chain is empty", body = ErrorResponse)
+    )
+)]
+pub async fn get_genesis(
+    State(blockchain): State<SharedBlockchain>,
+) -> Result<Json<GenesisResponse>, BlockchainError> {
+    info!("GET /chain/genesis - Retrieving network descriptor");
+
+    let blockchain = lock_chain(&blockchain).await?;
+    let genesis = blockchain.genesis_block().cloned().ok_or_else(|| {
+        error!("GET /chain/genesis - Chain is empty");
+        BlockchainError::NotFound("genesis block".to_string())
+    })?;
+
+    info!("GET /chain/genesis - Returning network descriptor with status 200");
+    Ok(Json(GenesisResponse {
+        chain_id: blockchain.chain_id.clone(),
+        genesis,
+        difficulty: blockchain.difficulty,
+        mining_reward: blockchain.mining_reward,
+        target_block_time: blockchain.target_block_time_secs,
+        hash_algorithm: HASH_ALGORITHM.to_string(),
+        address_scheme: ADDRESS_SCHEME.to_string(),
+    }))
+}
+
+/// Get the height and hash of the latest block, without the rest of the chain
+#[utoipa::path(
+    get,
+    path = "/chain/tip",
+    tag = "Blockchain",
+    responses(
+        (status = 200, description = "Latest block", body = ChainTipResponse),
+        (status = 404, description = "The chain is empty", body = ErrorResponse)
+    )
+)]
+pub async fn get_chain_tip(
+    State(blockchain): State<SharedBlockchain>,
+) -> Result<Json<ChainTipResponse>, BlockchainError> {
+    let tip = lock_chain(&blockchain)
+        .await?
+        .get_latest_block()
+        .map(|block| ChainTipResponse {
+            height: block.index,
+            hash: block.hash.clone(),
+            timestamp: block.timestamp,
+            difficulty: block.difficulty,
+        });
+
+    match tip {
+        Some(tip) => {
+            info!(
+                "GET /chain/tip - Returning tip #{} with status 200",
+                tip.height
+            );
+            Ok(Json(tip))
+        }
+        None => {
+            error!("GET /chain/tip - Chain is empty");
+            Err(BlockchainError::NotFound("chain tip".to_string()))
+        }
+    }
+}
+
+/// Get the cumulative work of the chain from genesis to the tip.
+///
+/// Each block counts the expected number of hashes needed to mine it, so
+/// chains can be compared by work rather than length.
+#[utoipa::path(
+    get,
+    path = "/chain/work",
+    tag = "Blockchain",
+    responses(
+        (status = 200, description = "Total work of the chain", body = ChainWorkResponse)
+    )
+)]
+pub async fn get_chain_work(
+    State(blockchain): State<SharedBlockchain>,
+) -> Result<Json<ChainWorkResponse>, BlockchainError> {
+    let blockchain = lock_chain(&blockchain).await?;
+    let response = ChainWorkResponse {
+        height: blockchain.chain_length().saturating_sub(1) as u64,
+        total_work: blockchain.total_work().to_string(),
+    };
+
+    info!(
+        "GET /chain/work - Returning work {} at height {} with status 200",
+        response.total_work, response.height
+    );
+    Ok(Json(response))
+}
+
+/// Get the software and protocol version of the node
+#[utoipa::path(
+    get,
+    path = "/version",
+    tag = "Blockchain",
+    responses(
+        (status = 200, description = "Node version", body = VersionResponse)
+    )
+)]
+pub async fn get_version(
+    State(blockchain): State<SharedBlockchain>,
+) -> Result<Json<VersionResponse>, BlockchainError> {
+    let chain_id = lock_chain(&blockchain).await?.chain_id.clone();
+
+    info!("GET /version - Returning node version with status 200");
+    Ok(Json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        protocol_version: PROTOCOL_VERSION,
+        chain_id,
+        git_commit: option_env!("FCHAIN_GIT_COMMIT").map(str::to_string),
+    }))
+}
+
+/// Get block time statistics
+#[utoipa::path(
+    get,
+    path = "/chain/blocktimes",
+    tag = "Blockchain",
+    params(BlockTimesQuery),
+    responses(
+        (status = 200, description = "Block interval statistics", body = BlockTimeStats)
+    )
+)]
+pub async fn get_block_times(
+    State(blockchain): State<SharedBlockchain>,
+    Query(query): Query<BlockTimesQuery>,
+) -> Result<Json<BlockTimeStats>, BlockchainError> {
+    let count = query
+        .count
+        .unwrap_or(DEFAULT_BLOCK_TIME_COUNT)
+        .min(MAX_BLOCK_TIME_COUNT);
+    info!(
+        "GET /chain/blocktimes - Computing block times for the last {} blocks",
+        count
+    );
+
+    let blockchain = lock_chain(&blockchain).await?;
+    let stats = BlockTimeStats::from_intervals(blockchain.block_intervals(count));
+
+    info!(
+        "GET /chain/blocktimes - Returning {} intervals with status 200",
+        stats.intervals.len()
+    );
+    Ok(Json(stats))
+}
+
+/// Estimate the network hashrate
+///
+/// Assumes each block at difficulty `d` took 2^(4d) hashes and divides that
+/// work by the time the most recent blocks took to mine.
+#[utoipa::path(
+    get,
+    path = "/chain/hashrate",
+    tag = "Blockchain",
+    params(BlockTimesQuery),
+    responses(
+        (status = 200, description = "Estimated network hashrate", body = HashrateResponse)
+    )
+)]
+pub async fn get_hashrate(
+    State(blockchain): State<SharedBlockchain>,
+    Query(query): Query<BlockTimesQuery>,
+) -> Result<Json<HashrateResponse>, BlockchainError> {
+    let count = query
+        .count
+        .unwrap_or(DEFAULT_BLOCK_TIME_COUNT)
+        .min(MAX_BLOCK_TIME_COUNT);
+    info!(
+        "GET /chain/hashrate - Estimating hashrate over the last {} blocks",
+        count
+    );
+
+    let blockchain = lock_chain(&blockchain).await?;
+    let response = HashrateResponse {
+        hashes_per_second: blockchain.estimated_hashrate(count),
+        blocks: blockchain.block_intervals(count).len(),
+    };
+
+    info!(
+        "GET /chain/hashrate - Returning {:?} hashes per second with status 200",
+        response.hashes_per_second
+    );
+    Ok(Json(response))
+}
+
+/// Report blocks seen from elsewhere that compete with the chain.
+///
+/// Blocks sent to `POST /blocks/validate` or as the tip of a rejected
+/// `POST /chain/import` are remembered when they are properly mined but the
+/// chain has a different block at their height.
+#[utoipa::path(
+    get,
+    path = "/chain/fork-info",
+    tag = "Blockchain",
+    responses(
+        (status = 200, description = "Fork detection result", body = ForkInfoResponse)
+    )
+)]
+pub async fn get_fork_info(
+    State(blockchain): State<SharedBlockchain>,
+) -> Result<Json<ForkInfoResponse>, BlockchainError> {
+    info!("GET /chain/fork-info - Checking for competing tips");
+
+    let blockchain = lock_chain(&blockchain).await?;
+    let competing_tips = blockchain.competing_tips();
+    let response = ForkInfoResponse {
+        fork_detected: !competing_tips.is_empty(),
+        tip_height: blockchain.get_latest_block().map_or(0, |block| block.index),
+        competing_tips,
+    };
+
+    info!(
+        "GET /chain/fork-info - Fork detected: {}, returning status 200",
+        response.fork_detected
+    );
+    Ok(Json(response))
+}
@@ -1,25 +1,44 @@
 use axum::{
-    http::StatusCode,
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use log::error;
 use serde::Serialize;
+use std::convert::Infallible;
 use utoipa::ToSchema;
 
 use crate::blockchain::BlockchainError;
 
 /// Error response for the API
 #[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct ErrorResponse {
+    /// Human readable description of the error
     pub error: String,
+    /// Stable machine-readable identifier of the error, e.g. `insufficient_balance`
+    pub code: String,
 }
 
 /// Convert BlockchainError to an HTTP response
 impl IntoResponse for BlockchainError {
     fn into_response(self) -> Response {
-        let status = StatusCode::BAD_REQUEST;
+        let status = match self {
+            BlockchainError::NotFound(_) => StatusCode::NOT_FOUND,
+            BlockchainError::MempoolFull(_) | BlockchainError::ChainBusy(_) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            _ => StatusCode::BAD_REQUEST,
+        };
+        // Suggest retrying once the lock has had another window to free up
+        let retry_after = match self {
+            BlockchainError::ChainBusy(window) => Some(window.as_secs_f64().ceil().max(1.0) as u64),
+            _ => None,
+        };
         let error_message = self.to_string();
+        let code = self.code().to_string();
 
         error!(
             "Error response with status {}: {}",
@@ -29,7 +48,110 @@ impl IntoResponse for BlockchainError {
 
         let body = Json(ErrorResponse {
             error: error_message,
+            code,
         });
-        (status, body).into_response()
+        match retry_after {
+            Some(secs) => (status, [(header::RETRY_AFTER, secs.to_string())], body).into_response(),
+            None => (status, body).into_response(),
+        }
+    }
+}
+
+/// Content type of bincode encoded response bodies
+pub const BINARY_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Encoding of a response body, negotiated from the request's `Accept` header.
+///
+/// Clients asking for `application/octet-stream` get bincode, everyone else JSON.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    Binary,
+}
+
+impl ResponseFormat {
+    /// Picks the format requested by the `Accept` header
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let wants_binary = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|media_type| {
+                media_type
+                    .split(';')
+                    .next()
+                    .is_some_and(|media_type| media_type.trim() == BINARY_CONTENT_TYPE)
+            });
+
+        if wants_binary {
+            Self::Binary
+        } else {
+            Self::Json
+        }
+    }
+
+    /// Returns the content type of bodies in this format
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Binary => BINARY_CONTENT_TYPE,
+        }
+    }
+
+    /// Encodes a value in this format into a response
+    pub fn respond<T: Serialize + ?Sized>(self, value: &T) -> Response {
+        let body = match self {
+            Self::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            Self::Binary => bincode::serialize(value).map_err(|e| e.to_string()),
+        };
+
+        match body {
+            Ok(body) => ([(header::CONTENT_TYPE, self.content_type())], body).into_response(),
+            Err(err) => {
+                error!("Failed to encode response body: {}", err);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ResponseFormat {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn format_for(accept: &str) -> ResponseFormat {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_str(accept).unwrap());
+        ResponseFormat::from_headers(&headers)
+    }
+
+    #[test]
+    fn test_response_format_from_accept_header() {
+        assert_eq!(
+            ResponseFormat::from_headers(&HeaderMap::new()),
+            ResponseFormat::Json
+        );
+        assert_eq!(format_for("application/json"), ResponseFormat::Json);
+        assert_eq!(format_for("*/*"), ResponseFormat::Json);
+        assert_eq!(
+            format_for("application/octet-stream"),
+            ResponseFormat::Binary
+        );
+        assert_eq!(
+            format_for("application/json, application/octet-stream;q=0.9"),
+            ResponseFormat::Binary
+        );
     }
 }
@@ -0,0 +1,144 @@
+use std::convert::Infallible;
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::stream;
+use log::{error, info, warn};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::api::contention::lock_chain;
+use crate::blockchain::{BlockchainError, SharedBlockchain, Transaction};
+
+/// Content type of newline-delimited JSON bodies
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// A line of a mempool import that wasn't added
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedTransaction {
+    /// Line number in the import, counting from 1
+    pub line: usize,
+    /// Hash of the transaction, if the line could be parsed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Why it was skipped
+    pub error: String,
+}
+
+/// Outcome of a mempool import
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportMempoolResponse {
+    /// Number of transactions added to the pending pool
+    pub imported: usize,
+    /// Lines that were rejected, in order
+    pub skipped: Vec<SkippedTransaction>,
+}
+
+/// Export the pending transactions as newline-delimited JSON, one transaction per line
+#[utoipa::path(
+    get,
+    path = "/mempool/export.ndjson",
+    tag = "Blockchain",
+    responses(
+        (status = 200, description = "One pending transaction per line", body = String,
+            content_type = "application/x-ndjson")
+    )
+)]
+pub async fn export_mempool(
+    State(blockchain): State<SharedBlockchain>,
+) -> Result<Response, BlockchainError> {
+    // Snapshot the pool so that the lock isn't held while the body streams
+    let pending = lock_chain(&blockchain).await?.pending_transactions.clone();
+    info!(
+        "GET /mempool/export.ndjson - Streaming {} pending transactions",
+        pending.len()
+    );
+
+    let lines = stream::iter(pending.into_iter().filter_map(|transaction| {
+        match serde_json::to_string(&transaction) {
+            Ok(mut line) => {
+                line.push('\n');
+                Some(Ok::<_, Infallible>(line))
+            }
+            Err(err) => {
+                error!("Failed to encode transaction {}: {}", transaction.hash, err);
+                None
+            }
+        }
+    }));
+
+    Ok((
+        [(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)],
+        Body::from_stream(lines),
+    )
+        .into_response())
+}
+
+/// Import pending transactions exported by `GET /mempool/export.ndjson`.
+///
+/// Every transaction is validated as if it had just been submitted; the ones
+/// that fail, e.g. because they were mined in the meantime, are skipped.
+#[utoipa::path(
+    post,
+    path = "/mempool/import",
+    tag = "Blockchain",
+    request_body(content = String, content_type = "application/x-ndjson",
+        description = "One transaction per line"),
+    responses(
+        (status = 200, description = "Transactions imported", body = ImportMempoolResponse)
+    )
+)]
+pub async fn import_mempool(
+    State(blockchain): State<SharedBlockchain>,
+    body: String,
+) -> Json<ImportMempoolResponse> {
+    info!("POST /mempool/import - Importing pending transactions");
+
+    let mut imported = 0;
+    let mut skipped = Vec::new();
+    let mut blockchain = blockchain.lock().unwrap();
+    for (i, line) in body.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let line_number = i + 1;
+        let result = serde_json::from_str::<Transaction>(line)
+            .map_err(|err| (None, err.to_string()))
+            .and_then(|transaction| {
+                let hash = transaction.hash.clone();
+                blockchain
+                    .create_transaction(transaction)
+                    .map_err(|err| (Some(hash), err.to_string()))
+            });
+
+        match result {
+            Ok(()) => imported += 1,
+            Err((hash, error)) => {
+                warn!(
+                    "POST /mempool/import - Skipping line {}: {}",
+                    line_number, error
+                );
+                skipped.push(SkippedTransaction {
+                    line: line_number,
+                    hash,
+                    error,
+                });
+            }
+        }
+    }
+
+    info!(
+        "POST /mempool/import - Imported {} transactions, skipped {}, returning status 200",
+        imported,
+        skipped.len()
+    );
+    Json(ImportMempoolResponse { imported, skipped })
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use log::{error, info, warn};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::blocks::MineBlockRequest;
+use crate::api::validation::ValidatedJson;
+use crate::blockchain::{
+    mine_shared_split_cancellable, Block, BlockchainError, MiningCancellation, SharedBlockchain,
+};
+use crate::webhook::Webhook;
+
+/// State of a background mining job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MiningJobStatus {
+    /// Still looking for a proof of work
+    Running,
+    /// The block was mined and appended to the chain
+    Mined,
+    /// Mining stopped with an error
+    Failed,
+    /// Cancelled before a block was appended
+    Cancelled,
+}
+
+/// A background mining job
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MiningJobResponse {
+    /// Identifier to poll or cancel the job with
+    pub job_id: u64,
+    /// Current state of the job
+    pub status: MiningJobStatus,
+    /// The mined block, once the job is `mined`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<Block>,
+    /// Why the job failed, if it did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+struct MiningJob {
+    cancellation: Arc<MiningCancellation>,
+    response: MiningJobResponse,
+}
+
+/// Number of ended jobs kept for polling; older ones are forgotten
+const MAX_ENDED_JOBS: usize = 100;
+
+/// Mining jobs started through `POST /mine`, by id. Running jobs are always
+/// kept, ended ones only up to `MAX_ENDED_JOBS`, newest first.
+#[derive(Debug, Default)]
+pub struct MiningJobs {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<u64, MiningJob>>,
+}
+
+impl MiningJobs {
+    /// Registers a running job and returns it with its cancellation token
+    fn start(&self) -> (MiningJobResponse, Arc<MiningCancellation>) {
+        let job_id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancellation = Arc::new(MiningCancellation::new());
+        let response = MiningJobResponse {
+            job_id,
+            status: MiningJobStatus::Running,
+            block: None,
+            error: None,
+        };
+        self.jobs.lock().unwrap().insert(
+            job_id,
+            MiningJob {
+                cancellation: cancellation.clone(),
+                response: response.clone(),
+            },
+        );
+        (response, cancellation)
+    }
+
+    /// Records how a job ended, forgetting the oldest ended jobs beyond
+    /// [`MAX_ENDED_JOBS`]
+    fn finish(&self, job_id: u64, result: &Result<Block, BlockchainError>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        Self::record_result(&mut jobs, job_id, result);
+
+        let mut ended: Vec<u64> = jobs
+            .iter()
+            .filter(|(_, job)| job.response.status != MiningJobStatus::Running)
+            .map(|(&id, _)| id)
+            .collect();
+        if ended.len() > MAX_ENDED_JOBS {
+            ended.sort_unstable();
+            for id in &ended[..ended.len() - MAX_ENDED_JOBS] {
+                jobs.remove(id);
+            }
+        }
+    }
+
+    fn record_result(
+        jobs: &mut HashMap<u64, MiningJob>,
+        job_id: u64,
+        result: &Result<Block, BlockchainError>,
+    ) {
+        let Some(job) = jobs.get_mut(&job_id) else {
+            return;
+        };
+        let response = &mut job.response;
+        match result {
+            Ok(block) => {
+                response.status = MiningJobStatus::Mined;
+                response.block = Some(block.clone());
+            }
+            Err(BlockchainError::MiningCancelled) => response.status = MiningJobStatus::Cancelled,
+            Err(err) => {
+                response.status = MiningJobStatus::Failed;
+                response.error = Some(err.to_string());
+            }
+        }
+    }
+
+    fn get(&self, job_id: u64) -> Result<MiningJobResponse, BlockchainError> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(&job_id)
+            .map(|job| job.response.clone())
+            .ok_or_else(|| BlockchainError::NotFound(format!("Mining job {}", job_id)))
+    }
+
+    /// Cancels a running job. Jobs that already ended, or whose block is
+    /// already being appended, can't be cancelled.
+    fn cancel(&self, job_id: u64) -> Result<MiningJobResponse, BlockchainError> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs
+            .get_mut(&job_id)
+            .ok_or_else(|| BlockchainError::NotFound(format!("Mining job {}", job_id)))?;
+
+        if job.response.status != MiningJobStatus::Running || !job.cancellation.cancel() {
+            return Err(BlockchainError::ValidationFailed(format!(
+                "Mining job {} has already finished",
+                job_id
+            )));
+        }
+        job.response.status = MiningJobStatus::Cancelled;
+        Ok(job.response.clone())
+    }
+}
+
+/// Start mining a block in the background
+#[utoipa::path(
+    post,
+    path = "/mine",
+    tag = "Blockchain",
+    request_body = MineBlockRequest,
+    responses(
+        (status = 202, description = "Mining job started", body = MiningJobResponse)
+    )
+)]
+pub async fn start_mining_job(
+    State(blockchain): State<SharedBlockchain>,
+    Extension(jobs): Extension<Arc<MiningJobs>>,
+    webhook: Option<Extension<Arc<Webhook>>>,
+    ValidatedJson(request): ValidatedJson<MineBlockRequest>,
+) -> (StatusCode, Json<MiningJobResponse>) {
+    let (response, cancellation) = jobs.start();
+    let job_id = response.job_id;
+    info!(
+        "POST /mine - Started mining job {} for miner: {}",
+        job_id, request.miner_address
+    );
+
+    let recipients = request.into_recipients();
+    tokio::spawn(async move {
+        let result = tokio::task::spawn_blocking(move || {
+            mine_shared_split_cancellable(&blockchain, &recipients, &cancellation)
+        })
+        .await
+        .unwrap_or_else(|e| {
+            Err(BlockchainError::ValidationFailed(format!(
+                "Mining task failed: {}",
+                e
+            )))
+        });
+
+        match &result {
+            Ok(block) => {
+                info!("Mining job {} mined block #{}", job_id, block.index);
+                if let Some(Extension(webhook)) = webhook {
+                    webhook.notify_block_mined(block).await;
+                }
+            }
+            Err(BlockchainError::MiningCancelled) => warn!("Mining job {} was cancelled", job_id),
+            Err(err) => error!("Mining job {} failed: {}", job_id, err),
+        }
+        jobs.finish(job_id, &result);
+    });
+
+    (StatusCode::ACCEPTED, Json(response))
+}
+
+/// Get the state of a mining job
+#[utoipa::path(
+    get,
+    path = "/mine/{job_id}",
+    tag = "Blockchain",
+    params(
+        ("job_id" = u64, Path, description = "Mining job id")
+    ),
+    responses(
+        (status = 200, description = "Mining job", body = MiningJobResponse),
+        (status = 404, description = "Unknown job", body = ErrorResponse)
+    )
+)]
+pub async fn get_mining_job(
+    Extension(jobs): Extension<Arc<MiningJobs>>,
+    Path(job_id): Path<u64>,
+) -> Result<Json<MiningJobResponse>, BlockchainError> {
+    info!("GET /mine/{} - Retrieving mining job", job_id);
+    jobs.get(job_id).map(Json)
+}
+
+/// Cancel a running mining job; it never appends a block afterwards
+#[utoipa::path(
+    delete,
+    path = "/mine/{job_id}",
+    tag = "Blockchain",
+    params(
+        ("job_id" = u64, Path, description = "Mining job id")
+    ),
+    responses(
+        (status = 200, description = "Job cancelled", body = MiningJobResponse),
+        (status = 400, description = "Job already finished", body = ErrorResponse),
+        (status = 404, description = "Unknown job", body = ErrorResponse)
+    )
+)]
+pub async fn cancel_mining_job(
+    Extension(jobs): Extension<Arc<MiningJobs>>,
+    Path(job_id): Path<u64>,
+) -> Result<Json<MiningJobResponse>, BlockchainError> {
+    info!("DELETE /mine/{} - Cancelling mining job", job_id);
+
+    let response = jobs.cancel(job_id)?;
+    info!("DELETE /mine/{} - Cancelled with status 200", job_id);
+    Ok(Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_the_newest_ended_jobs_are_kept() {
+        let jobs = MiningJobs::default();
+        let (running, _) = jobs.start();
+        let ended: Vec<u64> = (0..MAX_ENDED_JOBS + 5)
+            .map(|_| jobs.start().0.job_id)
+            .collect();
+
+        for &job_id in &ended {
+            jobs.finish(job_id, &Err(BlockchainError::MiningCancelled));
+        }
+
+        assert_eq!(jobs.jobs.lock().unwrap().len(), MAX_ENDED_JOBS + 1);
+        assert!(jobs.get(running.job_id).is_ok());
+        assert!(jobs.get(ended[4]).is_err());
+        assert_eq!(
+            jobs.get(ended[5]).unwrap().status,
+            MiningJobStatus::Cancelled
+        );
+    }
+}
@@ -1,12 +1,43 @@
+pub mod address;
 pub mod blocks;
 pub mod chain;
 pub mod common;
+pub mod mempool;
+pub mod mining;
+pub mod search;
 pub mod transactions;
+pub mod wallet;
+pub mod ws;
 
 // Re-export handlers
-pub use blocks::{get_blocks, mine_block, MineBlockRequest, MineBlockResponse};
-pub use chain::{validate_chain, ValidateChainResponse};
+pub use address::{
+    get_address_graph, get_address_statement, get_balance, get_balances, get_last_spent, get_nonce,
+    get_top_addresses, validate_address, AddressBalance, AddressGraphResponse, BalanceResponse,
+    BalancesRequest, BalancesResponse, CounterpartyFlowResponse, LastSpentResponse, NonceResponse,
+    ValidateAddressResponse,
+};
+pub use blocks::{
+    get_block_range, get_blocks, get_headers, get_latest_blocks, mine_block, validate_block,
+    BlockWithConfirmations, MineBlockRequest, MineBlockResponse, ValidateBlockResponse,
+};
+pub use chain::{
+    export_chain, get_block_times, get_chain_stats, get_chain_tip, get_chain_work, get_fork_info,
+    get_genesis, get_hashrate, get_version, import_chain, validate_chain, ChainStatsResponse,
+    ChainTipResponse, ChainWorkResponse, ForkInfoResponse, GenesisResponse, HashrateResponse,
+    ImportChainResponse, ValidateChainResponse, VersionResponse,
+};
+pub use mempool::{export_mempool, import_mempool, ImportMempoolResponse, SkippedTransaction};
+pub use mining::{
+    cancel_mining_job, get_mining_job, start_mining_job, MiningJobResponse, MiningJobStatus,
+    MiningJobs,
+};
+pub use search::{search, SearchResult};
 pub use transactions::{
-    create_transaction, get_pending_transactions, CreateTransactionRequest,
-    CreateTransactionResponse,
+    create_transaction, decode_transaction, get_inclusion_proof, get_pending_for_address,
+    get_pending_transactions, get_transaction_status, rebroadcast_transaction,
+    simulate_transaction, submit_raw_transaction, CreateTransactionRequest,
+    CreateTransactionResponse, DecodeTransactionResponse, RebroadcastResponse,
+    SimulateTransactionResponse, TransactionStatusResponse,
 };
+pub use wallet::{verify_signature, VerifySignatureRequest, VerifySignatureResponse};
+pub use ws::{address_ws, mempool_ws, BalanceChangeEvent, BalanceChangeSource};
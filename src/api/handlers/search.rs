@@ -0,0 +1,101 @@
+use std::borrow::Cow;
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use log::{error, info};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::api::contention::lock_chain;
+use crate::blockchain::{Address, Block, BlockchainError, SharedBlockchain, Transaction};
+
+/// What a search query resolved to
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SearchResult {
+    /// A block, matched by index or hash
+    Block { block: Block },
+    /// A confirmed or pending transaction, matched by hash
+    #[serde(rename_all = "camelCase")]
+    Transaction {
+        transaction: Transaction,
+        /// Index of the block containing the transaction, absent while pending
+        block_index: Option<u64>,
+        /// Number of blocks on top of and including the containing one; 0 while pending
+        confirmations: u64,
+    },
+    /// An address, with its current balance
+    Address {
+        address: String,
+        #[cfg_attr(
+            feature = "string-amounts",
+            serde(with = "crate::blockchain::amount"),
+            schema(value_type = String)
+        )]
+        balance: f64,
+    },
+}
+
+/// Search for a block, transaction or address
+#[utoipa::path(
+    get,
+    path = "/search/{query}",
+    tag = "Blockchain",
+    params(
+        ("query" = String, Path, description = "Block index, block hash, transaction hash or address")
+    ),
+    responses(
+        (status = 200, description = "What the query resolved to", body = SearchResult),
+        (status = 404, description = "Nothing matched the query", body = ErrorResponse)
+    )
+)]
+pub async fn search(
+    State(blockchain): State<SharedBlockchain>,
+    Path(query): Path<String>,
+) -> Result<Json<SearchResult>, BlockchainError> {
+    info!("GET /search/{} - Searching", query);
+
+    let blockchain = lock_chain(&blockchain).await?;
+
+    // Try a block index, a block hash, a transaction hash and an address, in that order
+    let block = query
+        .parse::<u64>()
+        .ok()
+        .and_then(|index| blockchain.get_block_by_index(index))
+        .or_else(|| blockchain.get_block_by_hash(&query).map(Cow::Borrowed));
+
+    let result = if let Some(block) = block {
+        Some(SearchResult::Block {
+            block: block.into_owned(),
+        })
+    } else if let Some((transaction, block_index)) = blockchain.get_transaction(&query) {
+        Some(SearchResult::Transaction {
+            transaction: transaction.clone(),
+            block_index,
+            confirmations: block_index.map_or(0, |index| blockchain.confirmations_for(index)),
+        })
+    } else if Address(query.clone()).is_valid() || blockchain.has_address_activity(&query) {
+        Some(SearchResult::Address {
+            balance: blockchain.get_balance(&query),
+            address: query.clone(),
+        })
+    } else {
+        None
+    };
+
+    match result {
+        Some(result) => {
+            info!("GET /search/{} - Match found, returning status 200", query);
+            Ok(Json(result))
+        }
+        None => {
+            error!("GET /search/{} - No match found", query);
+            Err(BlockchainError::NotFound(format!(
+                "Nothing matches '{}'",
+                query
+            )))
+        }
+    }
+}
@@ -1,33 +1,173 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::api::auth::GatewayToken;
+use crate::api::contention::lock_chain;
+use crate::api::validation::ValidatedJson;
 use crate::blockchain::crypto::{PublicKeyHex, TransactionSignature};
-use crate::blockchain::{Address, BlockchainError, SharedBlockchain, Transaction};
+use crate::blockchain::{
+    Address, Blockchain, BlockchainError, InclusionProof, SharedBlockchain, Transaction,
+    TransactionError, TransactionOutput, TransactionStatus,
+};
 
 /// Request to create a new transaction
 #[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct CreateTransactionRequest {
     /// The sender's address
+    #[schema(max_length = 64)]
     pub sender: String,
-    /// The recipient address
+    /// The recipient address; ignored when `outputs` is given
+    #[serde(default)]
+    #[schema(max_length = 64)]
     pub recipient: String,
-    /// The amount to transfer
+    /// The amount to transfer; ignored when `outputs` is given
+    #[serde(default)]
+    #[cfg_attr(not(feature = "string-amounts"), schema(minimum = 0))]
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(schema_with = crate::blockchain::amount::non_negative_schema)
+    )]
     pub amount: f64,
-    /// The transaction signature (required)
+    /// The fee offered to the miner
+    #[serde(default)]
+    #[cfg_attr(not(feature = "string-amounts"), schema(minimum = 0))]
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(schema_with = crate::blockchain::amount::non_negative_schema)
+    )]
+    pub fee: f64,
+    /// Recipients to pay atomically instead of the single `recipient`
+    #[serde(default)]
+    pub outputs: Vec<TransactionOutput>,
+    /// The sender's nonce, see `GET /address/{address}/nonce`
+    pub nonce: Option<u64>,
+    /// Data to anchor with the transaction; requires `nonce`. A transaction
+    /// with a memo may have an amount of 0 if the node allows it.
+    #[schema(max_length = 256)]
+    pub memo: Option<String>,
+    /// Time until which the recipient can't spend the amount; requires `nonce`
+    pub locked_until: Option<DateTime<Utc>>,
+    /// The transaction signature (required); the gateway of a permissioned
+    /// chain may leave it empty
     pub signature: String,
-    /// The full public key of the sender (required for non-system transactions)
+    /// The full public key of the sender (required for signed non-system transactions)
     pub public_key: Option<String>,
 }
 
 /// Response for a successful transaction creation
 #[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct CreateTransactionResponse {
     /// Success message
     pub message: String,
     /// The created transaction
     pub transaction: Transaction,
+    /// Position of the transaction in the order blocks pick pending
+    /// transactions, 0 being next; absent while it is held as an orphan
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mempool_position: Option<usize>,
+}
+
+/// Response describing a transaction and where it is in its lifecycle
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionStatusResponse {
+    /// Hash of the transaction that was looked up
+    pub hash: String,
+    /// Stable id of the transaction, if it is known
+    pub id: Option<String>,
+    /// Lifecycle status of the transaction
+    pub status: TransactionStatus,
+    /// The transaction, if it is known
+    pub transaction: Option<Transaction>,
+    /// Number of blocks on top of and including the one containing the
+    /// transaction; 0 unless it is confirmed
+    pub confirmations: u64,
+}
+
+/// Response for a rebroadcast request
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RebroadcastResponse {
+    /// The transaction hash that was rebroadcast
+    pub hash: String,
+    /// Lifecycle status of the transaction after revalidation
+    pub status: TransactionStatus,
+    /// What happened to the transaction
+    pub message: String,
+}
+
+/// Breakdown of a raw transaction, for debugging how it was signed
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodeTransactionResponse {
+    /// Hash the transaction claims
+    pub hash: String,
+    /// Hash recomputed from the transaction's contents
+    pub computed_hash: String,
+    /// Whether the claimed hash matches the contents
+    pub hash_matches: bool,
+    /// Address derived from the public key, in the format of the sender
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub derived_address: Option<String>,
+    /// Whether the public key derives to the sender address
+    pub address_matches: bool,
+    /// Whether the signature verifies against the public key over the
+    /// recomputed hash
+    pub signature_valid: bool,
+    /// Why the public key or signature couldn't be checked, e.g. because one is missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature_error: Option<String>,
+    /// The sender's spendable balance
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(value_type = String)
+    )]
+    pub spendable_balance: f64,
+    /// Whether the spendable balance covers the amount plus the fee
+    pub sufficient_balance: bool,
+    /// Whether the transaction would be accepted right now
+    pub would_accept: bool,
+    /// Why the transaction would be rejected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Machine-readable code of the reason, as in error responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+/// Outcome of a simulated transaction
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulateTransactionResponse {
+    /// Whether the transaction would be accepted right now
+    pub would_accept: bool,
+    /// Why the transaction would be rejected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Machine-readable code of the reason, as in error responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// The sender's balance if the transaction were accepted, or its current
+    /// balance if it would be rejected
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(value_type = String)
+    )]
+    pub resulting_balance: f64,
 }
 
 /// Get pending transactions
@@ -41,51 +181,213 @@ pub struct CreateTransactionResponse {
 )]
 pub async fn get_pending_transactions(
     State(blockchain): State<SharedBlockchain>,
-) -> Json<Vec<Transaction>> {
+) -> Result<Json<Vec<Transaction>>, BlockchainError> {
     info!("GET /transactions/pending - Retrieving pending transactions");
 
-    let blockchain = blockchain.lock().unwrap();
+    let blockchain = lock_chain(&blockchain).await?;
     let transactions = blockchain.pending_transactions.clone();
 
     info!(
         "GET /transactions/pending - Returning {} pending transactions with status 200",
         transactions.len()
     );
-    Json(transactions)
+    Ok(Json(transactions))
+}
+
+/// Get the pending transactions an address sends or receives in
+#[utoipa::path(
+    get,
+    path = "/transactions/pending/{address}",
+    tag = "Blockchain",
+    params(
+        ("address" = String, Path, description = "Sender or recipient address")
+    ),
+    responses(
+        (status = 200, description = "Pending transactions involving the address, empty if there are none", body = Vec<Transaction>)
+    )
+)]
+pub async fn get_pending_for_address(
+    State(blockchain): State<SharedBlockchain>,
+    Path(address): Path<String>,
+) -> Result<Json<Vec<Transaction>>, BlockchainError> {
+    info!(
+        "GET /transactions/pending/{} - Retrieving pending transactions",
+        address
+    );
+
+    let blockchain = lock_chain(&blockchain).await?;
+    let transactions: Vec<Transaction> = blockchain
+        .pending_for_address(&address)
+        .into_iter()
+        .cloned()
+        .collect();
+
+    info!(
+        "GET /transactions/pending/{} - Returning {} pending transactions with status 200",
+        address,
+        transactions.len()
+    );
+    Ok(Json(transactions))
+}
+
+/// Get a transaction and its status, by hash or by id
+#[utoipa::path(
+    get,
+    path = "/transactions/{hash}",
+    tag = "Blockchain",
+    params(
+        ("hash" = String, Path, description = "Transaction hash or id")
+    ),
+    responses(
+        (status = 200, description = "Transaction status", body = TransactionStatusResponse)
+    )
+)]
+pub async fn get_transaction_status(
+    State(blockchain): State<SharedBlockchain>,
+    Path(hash): Path<String>,
+) -> Result<Json<TransactionStatusResponse>, BlockchainError> {
+    info!("GET /transactions/{} - Retrieving transaction status", hash);
+
+    let blockchain = lock_chain(&blockchain).await?;
+    // Look the key up as a hash first, and as an id only if no transaction has that hash
+    let by_id = match blockchain.transaction_status(&hash) {
+        TransactionStatus::Unknown => blockchain.get_transaction_by_id(&hash),
+        _ => None,
+    };
+    let (hash, transaction) = match by_id {
+        Some((transaction, _)) => (transaction.hash.clone(), Some(transaction)),
+        None => {
+            let transaction = blockchain
+                .get_transaction(&hash)
+                .map(|(transaction, _)| transaction.clone());
+            (hash, transaction)
+        }
+    };
+    let status = blockchain.transaction_status(&hash);
+    let id = transaction
+        .as_ref()
+        .map(|transaction| blockchain.transaction_id(transaction));
+    let confirmations = match status {
+        TransactionStatus::Confirmed { confirmations, .. } => confirmations,
+        _ => 0,
+    };
+
+    info!(
+        "GET /transactions/{} - Returning status {:?} with status 200",
+        hash, status
+    );
+    Ok(Json(TransactionStatusResponse {
+        hash,
+        id,
+        status,
+        transaction,
+        confirmations,
+    }))
+}
+
+/// Get a proof that a transaction is confirmed, for light wallets.
+///
+/// The response holds the transaction, the header of its block and the Merkle
+/// branch from the transaction up to the header's Merkle root, so a client can
+/// check the inclusion, recompute the header's hash and check its proof of
+/// work without the block. Only blocks whose hash commits to their Merkle
+/// root can be proven.
+#[utoipa::path(
+    get,
+    path = "/transactions/{hash}/inclusion",
+    tag = "Blockchain",
+    params(
+        ("hash" = String, Path, description = "Transaction hash")
+    ),
+    responses(
+        (status = 200, description = "Inclusion proof", body = InclusionProof),
+        (status = 400, description = "The block's hash doesn't commit to a Merkle root", body = ErrorResponse),
+        (status = 404, description = "Transaction is not confirmed", body = ErrorResponse)
+    )
+)]
+pub async fn get_inclusion_proof(
+    State(blockchain): State<SharedBlockchain>,
+    Path(hash): Path<String>,
+) -> Result<Json<InclusionProof>, BlockchainError> {
+    info!(
+        "GET /transactions/{}/inclusion - Building inclusion proof",
+        hash
+    );
+
+    match lock_chain(&blockchain).await?.inclusion_proof(&hash) {
+        Ok(proof) => {
+            info!(
+                "GET /transactions/{}/inclusion - Found in block #{}, returning status 200",
+                hash, proof.header.index
+            );
+            Ok(Json(proof))
+        }
+        Err(err) => {
+            error!("GET /transactions/{}/inclusion - No proof: {}", hash, err);
+            Err(err)
+        }
+    }
 }
 
 /// Creates a transaction
+///
+/// The transaction only takes effect once it is mined, so it is answered with
+/// 202 Accepted and a `Location` header pointing at its status.
+///
+/// On a permissioned chain, a request carrying the gateway's `X-Gateway-Token`
+/// header may leave out the signature and public key; the transaction then
+/// needs a nonce.
 #[utoipa::path(
     post,
     path = "/transactions",
     request_body = CreateTransactionRequest,
     responses(
-        (status = 200, description = "Transaction created successfully", body = CreateTransactionResponse),
+        (status = 202, description = "Transaction accepted; poll the Location header for its status", body = CreateTransactionResponse,
+            headers(("Location" = String, description = "Status URL of the transaction"))),
         (status = 400, description = "Invalid transaction", body = ErrorResponse),
+        (status = 503, description = "Mempool is full", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
 pub async fn create_transaction(
     State(blockchain): State<SharedBlockchain>,
-    Json(request): Json<CreateTransactionRequest>,
-) -> Result<Json<CreateTransactionResponse>, BlockchainError> {
+    gateway: Option<Extension<GatewayToken>>,
+    headers: HeaderMap,
+    ValidatedJson(request): ValidatedJson<CreateTransactionRequest>,
+) -> Result<CreateTransactionResponse, BlockchainError> {
     info!(
         "POST /transactions - Creating transaction from {} to {} for amount {}",
         request.sender, request.recipient, request.amount
     );
+    let from_gateway = gateway.is_some_and(|Extension(gateway)| gateway.authenticates(&headers));
 
     // Create transaction with the provided data
     let sender = Address(request.sender.clone());
     let recipient = Address(request.recipient.clone());
 
-    // Create the transaction
-    let mut transaction = Transaction::new(sender, recipient, request.amount);
+    // Create the transaction, paying every output if several are given
+    let mut transaction = if request.outputs.is_empty() {
+        Transaction::new_with_fee(sender, recipient, request.amount, request.fee)
+    } else {
+        Transaction::new_with_outputs(sender, request.outputs, request.fee)
+    };
+    if let Some(nonce) = request.nonce {
+        transaction = transaction.with_nonce(nonce);
+    }
+    if let Some(memo) = request.memo {
+        transaction = transaction.with_memo(memo);
+    }
+    if let Some(locked_until) = request.locked_until {
+        transaction = transaction.with_locked_until(locked_until);
+    }
 
     // Special handling for system transactions
     if request.sender == "system" {
         info!("POST /transactions - Processing system transaction");
         // System transactions don't need signature validation or balance checks
         transaction.signature = Some(TransactionSignature("system".to_string()));
+    } else if from_gateway && request.signature.is_empty() && request.public_key.is_none() {
+        info!("POST /transactions - Processing unsigned transaction from the trusted gateway");
     } else {
         info!(
             "POST /transactions - Processing regular transaction, validating signature and balance"
@@ -96,9 +398,8 @@ pub async fn create_transaction(
         let public_key = match request.public_key {
             Some(pk) => pk,
             None => {
-                let err_msg = "Non-system transactions require a public key";
-                error!("POST /transactions - Validation failed: {}", err_msg);
-                return Err(BlockchainError::InvalidTransaction(err_msg.to_string()));
+                error!("POST /transactions - Validation failed: Non-system transactions require a public key");
+                return Err(TransactionError::MissingPublicKey.into());
             }
         };
 
@@ -107,40 +408,265 @@ pub async fn create_transaction(
         transaction.public_key = Some(PublicKeyHex(public_key));
 
         // Validate the transaction
-        if !transaction.is_valid() {
-            let err_msg = "Transaction validation failed";
-            error!("POST /transactions - Validation failed: {}", err_msg);
-            return Err(BlockchainError::InvalidTransaction(err_msg.to_string()));
+        if let Err(err) = transaction.validate() {
+            error!("POST /transactions - Validation failed: {}", err);
+            return Err(err.into());
         }
+    }
 
-        // Check if sender has sufficient balance
-        let chain = blockchain.lock().unwrap();
-        let balance = chain.get_balance(&request.sender);
-        if balance < request.amount {
-            let err_msg = format!(
-                "Insufficient balance: {} has only {} coins",
-                request.sender, balance
-            );
-            error!("POST /transactions - {}", err_msg);
-            return Err(BlockchainError::InvalidTransaction(err_msg));
+    // Add the transaction to the blockchain, which also checks the sender's balance
+    let mut chain = blockchain.lock().unwrap();
+    let created = if from_gateway {
+        chain.create_gateway_transaction(transaction.clone())
+    } else {
+        chain.create_transaction(transaction.clone())
+    };
+    match created {
+        Ok(_) => {
+            let response = accepted(&chain, transaction);
+            info!("POST /transactions - {} with status 202", response.message);
+            Ok(response)
+        }
+        Err(err) => {
+            error!("POST /transactions - Failed to create transaction: {}", err);
+            Err(err)
         }
-        // Release the lock before proceeding
-        drop(chain);
     }
+}
+
+/// Describes where an accepted transaction ended up
+fn creation_message(blockchain: &Blockchain, transaction: &Transaction) -> String {
+    if blockchain.is_orphan(&transaction.hash) {
+        "Transaction held until the sender's funds are confirmed".to_string()
+    } else {
+        "Transaction created successfully".to_string()
+    }
+}
+
+/// Response for a transaction that was just accepted
+fn accepted(blockchain: &Blockchain, transaction: Transaction) -> CreateTransactionResponse {
+    CreateTransactionResponse {
+        message: creation_message(blockchain, &transaction),
+        mempool_position: blockchain.mempool_position(&transaction.hash),
+        transaction,
+    }
+}
+
+impl IntoResponse for CreateTransactionResponse {
+    /// 202 Accepted, with the status URL of the transaction in `Location`
+    fn into_response(self) -> Response {
+        let location = format!("/transactions/{}", self.transaction.hash);
+        (
+            StatusCode::ACCEPTED,
+            [(header::LOCATION, location)],
+            Json(self),
+        )
+            .into_response()
+    }
+}
+
+/// Submits a fully-formed signed transaction
+///
+/// Unlike `POST /transactions`, the transaction is accepted exactly as signed by
+/// the client: no field is recomputed, so the hash and timestamp must match.
+#[utoipa::path(
+    post,
+    path = "/transactions/raw",
+    tag = "Blockchain",
+    request_body = Transaction,
+    responses(
+        (status = 202, description = "Transaction accepted; poll the Location header for its status", body = CreateTransactionResponse,
+            headers(("Location" = String, description = "Status URL of the transaction"))),
+        (status = 400, description = "Invalid transaction", body = ErrorResponse),
+        (status = 503, description = "Mempool is full", body = ErrorResponse)
+    )
+)]
+pub async fn submit_raw_transaction(
+    State(blockchain): State<SharedBlockchain>,
+    Json(transaction): Json<Transaction>,
+) -> Result<CreateTransactionResponse, BlockchainError> {
+    info!(
+        "POST /transactions/raw - Submitting transaction {} from {} to {} for amount {}",
+        transaction.hash, transaction.sender, transaction.recipient, transaction.amount
+    );
 
-    // Add the transaction to the blockchain
     let mut chain = blockchain.lock().unwrap();
     match chain.create_transaction(transaction.clone()) {
         Ok(_) => {
-            info!("POST /transactions - Transaction created successfully with status 200");
-            Ok(Json(CreateTransactionResponse {
-                message: "Transaction created successfully".to_string(),
-                transaction,
-            }))
+            let response = accepted(&chain, transaction);
+            info!(
+                "POST /transactions/raw - {} with status 202",
+                response.message
+            );
+            Ok(response)
         }
         Err(err) => {
-            error!("POST /transactions - Failed to create transaction: {}", err);
+            error!("POST /transactions/raw - Transaction rejected: {}", err);
             Err(err)
         }
     }
 }
+
+/// Revalidate a pending transaction and announce it again.
+///
+/// A transaction that is no longer valid is dropped from the pending pool and
+/// reported with status `unknown`.
+#[utoipa::path(
+    post,
+    path = "/transactions/{hash}/rebroadcast",
+    tag = "Blockchain",
+    params(
+        ("hash" = String, Path, description = "Hash of a pending transaction")
+    ),
+    responses(
+        (status = 200, description = "Transaction status after revalidation", body = RebroadcastResponse),
+        (status = 404, description = "No pending transaction with this hash", body = ErrorResponse)
+    )
+)]
+pub async fn rebroadcast_transaction(
+    State(blockchain): State<SharedBlockchain>,
+    Path(hash): Path<String>,
+) -> Result<Json<RebroadcastResponse>, BlockchainError> {
+    info!(
+        "POST /transactions/{}/rebroadcast - Revalidating transaction",
+        hash
+    );
+
+    let mut chain = blockchain.lock().unwrap();
+    let message = match chain.rebroadcast_transaction(&hash) {
+        Ok(_) => "Transaction rebroadcast".to_string(),
+        Err(BlockchainError::NotFound(what)) => {
+            error!("POST /transactions/{}/rebroadcast - Not pending", hash);
+            return Err(BlockchainError::NotFound(what));
+        }
+        Err(err) => format!("Transaction dropped: {}", err),
+    };
+
+    info!(
+        "POST /transactions/{}/rebroadcast - {} with status 200",
+        hash, message
+    );
+    Ok(Json(RebroadcastResponse {
+        status: chain.transaction_status(&hash),
+        hash,
+        message,
+    }))
+}
+
+/// Check whether a signed transaction would be accepted, without submitting it
+#[utoipa::path(
+    post,
+    path = "/transactions/simulate",
+    tag = "Blockchain",
+    request_body = Transaction,
+    responses(
+        (status = 200, description = "Simulation result", body = SimulateTransactionResponse)
+    )
+)]
+pub async fn simulate_transaction(
+    State(blockchain): State<SharedBlockchain>,
+    Json(transaction): Json<Transaction>,
+) -> Result<Json<SimulateTransactionResponse>, BlockchainError> {
+    info!(
+        "POST /transactions/simulate - Simulating transaction {} from {} to {} for amount {}",
+        transaction.hash, transaction.sender, transaction.recipient, transaction.amount
+    );
+
+    let blockchain = lock_chain(&blockchain).await?;
+    let response = match blockchain.simulate_transaction(&transaction) {
+        Ok(resulting_balance) => SimulateTransactionResponse {
+            would_accept: true,
+            reason: None,
+            code: None,
+            resulting_balance,
+        },
+        Err(err) => SimulateTransactionResponse {
+            would_accept: false,
+            reason: Some(err.to_string()),
+            code: Some(err.code().to_string()),
+            resulting_balance: blockchain.get_balance(&transaction.sender.0),
+        },
+    };
+
+    info!(
+        "POST /transactions/simulate - Would accept: {}, returning status 200",
+        response.would_accept
+    );
+    Ok(Json(response))
+}
+
+/// Explain a signed transaction without submitting it.
+///
+/// Unlike `/transactions/simulate`, every check is reported on its own: the
+/// recomputed hash, the address the public key derives to, whether the
+/// signature verifies and whether the sender can pay, along with the verdict.
+#[utoipa::path(
+    post,
+    path = "/transactions/decode",
+    tag = "Blockchain",
+    request_body = Transaction,
+    responses(
+        (status = 200, description = "Breakdown of the transaction", body = DecodeTransactionResponse)
+    )
+)]
+pub async fn decode_transaction(
+    State(blockchain): State<SharedBlockchain>,
+    Json(transaction): Json<Transaction>,
+) -> Result<Json<DecodeTransactionResponse>, BlockchainError> {
+    info!(
+        "POST /transactions/decode - Decoding transaction {} from {}",
+        transaction.hash, transaction.sender
+    );
+
+    let computed_hash = transaction.calculate_hash();
+    let scheme = transaction.sig_scheme;
+    let (derived_address, signature_valid, signature_error) =
+        match (&transaction.public_key, &transaction.signature) {
+            (None, _) => (
+                None,
+                false,
+                Some(TransactionError::MissingPublicKey.to_string()),
+            ),
+            (Some(public_key), signature) => {
+                let derived = public_key.to_address_like(scheme, &transaction.sender);
+                let verified = signature
+                    .as_ref()
+                    .ok_or(TransactionError::MissingSignature)
+                    .and_then(|signature| {
+                        public_key
+                            .verify_signature(scheme, computed_hash.as_bytes(), signature)
+                            .map_err(TransactionError::from)
+                    });
+                let error = derived
+                    .as_ref()
+                    .err()
+                    .map(ToString::to_string)
+                    .or_else(|| verified.as_ref().err().map(ToString::to_string));
+                (derived.ok(), verified.unwrap_or(false), error)
+            }
+        };
+
+    let blockchain = lock_chain(&blockchain).await?;
+    let spendable_balance = blockchain.get_spendable_balance(&transaction.sender.0);
+    let verdict = blockchain.simulate_transaction(&transaction);
+    let response = DecodeTransactionResponse {
+        hash_matches: computed_hash == transaction.hash,
+        address_matches: derived_address.as_ref() == Some(&transaction.sender),
+        derived_address: derived_address.map(|address| address.0),
+        signature_valid,
+        signature_error,
+        sufficient_balance: spendable_balance >= transaction.amount + transaction.fee,
+        spendable_balance,
+        would_accept: verdict.is_ok(),
+        reason: verdict.as_ref().err().map(ToString::to_string),
+        code: verdict.as_ref().err().map(|err| err.code().to_string()),
+        hash: transaction.hash,
+        computed_hash,
+    };
+
+    info!(
+        "POST /transactions/decode - Would accept: {}, returning status 200",
+        response.would_accept
+    );
+    Ok(Json(response))
+}
@@ -0,0 +1,70 @@
+use axum::Json;
+use log::info;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::blockchain::crypto::{PublicKeyHex, SignatureScheme, TransactionSignature};
+use crate::blockchain::Address;
+
+/// Request to verify a signature over an arbitrary message
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifySignatureRequest {
+    /// Hex encoded public key of the signer
+    pub public_key: String,
+    /// The message that was signed
+    pub message: String,
+    /// Hex encoded signature of the message
+    pub signature: String,
+    /// Optional address the public key must hash to
+    pub address: Option<String>,
+    /// Scheme the message was signed with (defaults to ed25519)
+    #[serde(default)]
+    pub sig_scheme: SignatureScheme,
+}
+
+/// Response for signature verification
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifySignatureResponse {
+    /// Whether the signature is valid (and the key matches the address, if given)
+    pub valid: bool,
+}
+
+/// Verify a signature
+#[utoipa::path(
+    post,
+    path = "/wallet/verify",
+    tag = "Blockchain",
+    request_body = VerifySignatureRequest,
+    responses(
+        (status = 200, description = "Signature verification result", body = VerifySignatureResponse)
+    )
+)]
+pub async fn verify_signature(
+    Json(payload): Json<VerifySignatureRequest>,
+) -> Json<VerifySignatureResponse> {
+    info!("POST /wallet/verify - Verifying signature");
+
+    let public_key = PublicKeyHex(payload.public_key);
+    let signature = TransactionSignature(payload.signature);
+
+    // Malformed keys or signatures simply don't verify
+    let signature_valid = public_key
+        .verify_signature(payload.sig_scheme, payload.message.as_bytes(), &signature)
+        .unwrap_or(false);
+
+    let address_valid = match payload.address {
+        Some(address) => public_key
+            .verify_address(payload.sig_scheme, &Address(address))
+            .unwrap_or(false),
+        None => true,
+    };
+
+    let valid = signature_valid && address_valid;
+    info!(
+        "POST /wallet/verify - Signature valid: {}, returning status 200",
+        valid
+    );
+    Json(VerifySignatureResponse { valid })
+}
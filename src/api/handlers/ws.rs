@@ -0,0 +1,239 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
+    response::Response,
+};
+use log::{info, warn};
+use serde::Serialize;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use utoipa::ToSchema;
+
+use crate::blockchain::{Block, MempoolEvent, SharedBlockchain, Transaction};
+
+/// What changed the balance in a [`BalanceChangeEvent`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum BalanceChangeSource {
+    /// A transaction was accepted into the pending pool
+    Transaction,
+    /// A block was mined
+    Block,
+}
+
+/// Change to the balance of a watched address
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceChangeEvent {
+    /// The watched address
+    pub address: String,
+    /// Whether a transaction or a block caused the change
+    pub source: BalanceChangeSource,
+    /// Hash of the transaction or block
+    pub hash: String,
+    /// Net change to the balance of the address
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(value_type = String)
+    )]
+    pub delta: f64,
+    /// Balance after the change, including pending transactions as in the balance endpoint
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(value_type = String)
+    )]
+    pub balance: f64,
+}
+
+/// Stream mempool changes over a WebSocket.
+///
+/// Every message is a JSON `MempoolEvent`: the full transaction when one is
+/// accepted, and the hashes of the transactions removed when a block is mined.
+#[utoipa::path(
+    get,
+    path = "/ws/mempool",
+    tag = "Blockchain",
+    responses(
+        (status = 101, description = "Switching to the WebSocket protocol; messages are MempoolEvent JSON objects")
+    )
+)]
+pub async fn mempool_ws(
+    State(blockchain): State<SharedBlockchain>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    info!("GET /ws/mempool - Client subscribing to mempool events");
+
+    // Subscribe before upgrading so no event is missed in between
+    let events = blockchain.lock().unwrap().subscribe_mempool();
+    ws.on_upgrade(move |socket| stream_mempool(socket, events))
+}
+
+/// Forwards mempool events to the client until either side goes away
+async fn stream_mempool(mut socket: WebSocket, mut events: Receiver<MempoolEvent>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("GET /ws/mempool - Client lagging, skipped {} events", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                let text = match serde_json::to_string(&event) {
+                    Ok(text) => text,
+                    Err(err) => {
+                        warn!("GET /ws/mempool - Failed to encode event: {}", err);
+                        continue;
+                    }
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => {
+                match incoming {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+
+    info!("GET /ws/mempool - Client disconnected");
+}
+
+/// Stream balance changes of one address over a WebSocket.
+///
+/// Every message is a JSON `BalanceChangeEvent`, sent when a transaction
+/// involving the address is accepted or a block involving it is mined.
+#[utoipa::path(
+    get,
+    path = "/ws/address/{address}",
+    tag = "Blockchain",
+    params(
+        ("address" = String, Path, description = "Address to watch")
+    ),
+    responses(
+        (status = 101, description = "Switching to the WebSocket protocol; messages are BalanceChangeEvent JSON objects")
+    )
+)]
+pub async fn address_ws(
+    State(blockchain): State<SharedBlockchain>,
+    Path(address): Path<String>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    info!(
+        "GET /ws/address/{} - Client subscribing to balance changes",
+        address
+    );
+
+    // Subscribe before upgrading so no event is missed in between
+    let (transactions, blocks) = {
+        let chain = blockchain.lock().unwrap();
+        (chain.subscribe_mempool(), chain.subscribe_blocks())
+    };
+    ws.on_upgrade(move |socket| {
+        stream_balance_changes(socket, blockchain, address, transactions, blocks)
+    })
+}
+
+/// Forwards the balance changes of `address` to the client until either side
+/// goes away
+async fn stream_balance_changes(
+    mut socket: WebSocket,
+    blockchain: SharedBlockchain,
+    address: String,
+    mut transactions: Receiver<MempoolEvent>,
+    mut blocks: Receiver<Block>,
+) {
+    loop {
+        let change = tokio::select! {
+            event = transactions.recv() => match event {
+                Ok(MempoolEvent::Added { transaction }) => {
+                    transaction_change(&transaction, &address)
+                }
+                Ok(_) => None,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("GET /ws/address/{} - Client lagging, skipped {} events", address, skipped);
+                    None
+                }
+                Err(RecvError::Closed) => break,
+            },
+            block = blocks.recv() => match block {
+                Ok(block) => block_change(&block, &address),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("GET /ws/address/{} - Client lagging, skipped {} blocks", address, skipped);
+                    None
+                }
+                Err(RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => None,
+            },
+        };
+        let Some((source, hash, delta)) = change else {
+            continue;
+        };
+
+        let event = BalanceChangeEvent {
+            balance: blockchain.lock().unwrap().get_balance(&address),
+            address: address.clone(),
+            source,
+            hash,
+            delta,
+        };
+        let text = match serde_json::to_string(&event) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!(
+                    "GET /ws/address/{} - Failed to encode event: {}",
+                    address, err
+                );
+                continue;
+            }
+        };
+        if socket.send(Message::Text(text)).await.is_err() {
+            break;
+        }
+    }
+
+    info!("GET /ws/address/{} - Client disconnected", address);
+}
+
+/// Source, hash and delta of a transaction's change to `address`, if it involves it
+fn transaction_change(
+    transaction: &Transaction,
+    address: &str,
+) -> Option<(BalanceChangeSource, String, f64)> {
+    transaction.involves(address).then(|| {
+        (
+            BalanceChangeSource::Transaction,
+            transaction.hash.clone(),
+            transaction.balance_change(address),
+        )
+    })
+}
+
+/// Source, hash and delta of a block's change to `address`, if any of its
+/// transactions involve it
+fn block_change(block: &Block, address: &str) -> Option<(BalanceChangeSource, String, f64)> {
+    let involved: Vec<&Transaction> = block
+        .transactions
+        .iter()
+        .filter(|tx| tx.involves(address))
+        .collect();
+    (!involved.is_empty()).then(|| {
+        (
+            BalanceChangeSource::Block,
+            block.hash.clone(),
+            involved.iter().map(|tx| tx.balance_change(address)).sum(),
+        )
+    })
+}
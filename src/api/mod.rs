@@ -1,5 +1,11 @@
+pub mod auth;
+pub mod concurrency;
+pub mod config;
+pub mod contention;
 pub mod docs;
 pub mod handlers;
 pub mod router;
+pub mod validation;
 
-pub use router::create_router;
+pub use config::ApiConfig;
+pub use router::{create_router, create_router_with_config};
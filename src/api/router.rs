@@ -1,33 +1,172 @@
+use std::sync::Arc;
+
 use axum::{
-    routing::{get, post},
-    Router,
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post},
+    Extension, Router,
 };
+use tokio::sync::Semaphore;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::timeout::TimeoutLayer;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
+use super::auth;
+use super::concurrency;
+use super::config::ApiConfig;
+use super::contention;
 use super::docs::ApiDoc;
 use super::handlers;
 use crate::blockchain::SharedBlockchain;
+use crate::webhook::Webhook;
 
-/// Creates the API router
+/// Creates the API router with the default configuration
 pub fn create_router(blockchain: SharedBlockchain) -> Router {
+    create_router_with_config(blockchain, ApiConfig::default())
+}
+
+/// Creates the API router with the given configuration
+pub fn create_router_with_config(blockchain: SharedBlockchain, config: ApiConfig) -> Router {
     // Configure CORS middleware
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
         .allow_headers(Any);
 
-    Router::new()
-        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
-        .route("/blocks", get(handlers::get_blocks))
+    // Routes that grow the chain or the pending pool from this node
+    let mut local = Router::new()
         .route("/blocks/mine", post(handlers::mine_block))
+        .route("/mine", post(handlers::start_mining_job))
+        .route("/mine/:job_id", delete(handlers::cancel_mining_job))
         .route("/transactions", post(handlers::create_transaction))
+        .route("/transactions/raw", post(handlers::submit_raw_transaction))
+        .route(
+            "/transactions/:hash/rebroadcast",
+            post(handlers::rebroadcast_transaction),
+        )
+        .route(
+            "/mempool/import",
+            post(handlers::import_mempool)
+                .layer(DefaultBodyLimit::max(config.max_import_body_bytes)),
+        );
+
+    // A read-only node only follows chains imported from elsewhere
+    if config.read_only {
+        local = local.route_layer(middleware::from_fn(auth::reject_read_only));
+    }
+
+    // Routes that change the chain or the pending pool
+    let mut mutating = local.route(
+        "/chain/import",
+        post(handlers::import_chain).layer(DefaultBodyLimit::max(config.max_import_body_bytes)),
+    );
+
+    // Require a bearer token on them if one is configured; reads stay public
+    if let Some(token) = config.auth_token {
+        mutating = mutating.route_layer(middleware::from_fn_with_state(
+            Arc::<str>::from(token),
+            auth::require_bearer_token,
+        ));
+    }
+
+    // Let a permissioned chain's gateway create unsigned transactions
+    if let Some(token) = config.gateway_token {
+        mutating = mutating.layer(Extension(auth::GatewayToken(Arc::from(token))));
+    }
+
+    // Let the mining endpoint notify the webhook, if one is configured
+    if let Some(webhook) = config.webhook {
+        mutating = mutating.layer(Extension(Arc::new(Webhook::new(webhook))));
+    }
+
+    // Routes anyone may call that only read the chain and the pending pool
+    let mut reads = Router::new()
+        .route("/blocks", get(handlers::get_blocks))
+        .route("/blocks/latest", get(handlers::get_latest_blocks))
+        .route("/blocks/range", get(handlers::get_block_range))
+        .route("/headers", get(handlers::get_headers))
+        .route("/blocks/validate", post(handlers::validate_block))
         .route(
             "/transactions/pending",
             get(handlers::get_pending_transactions),
         )
+        .route(
+            "/transactions/pending/:address",
+            get(handlers::get_pending_for_address),
+        )
+        .route("/transactions/:hash", get(handlers::get_transaction_status))
+        .route(
+            "/transactions/:hash/inclusion",
+            get(handlers::get_inclusion_proof),
+        )
+        .route(
+            "/transactions/simulate",
+            post(handlers::simulate_transaction),
+        )
+        .route("/transactions/decode", post(handlers::decode_transaction))
+        .route("/mempool/export.ndjson", get(handlers::export_mempool))
         .route("/chain/validate", get(handlers::validate_chain))
+        .route("/chain/export", get(handlers::export_chain))
+        .route("/chain/stats", get(handlers::get_chain_stats))
+        .route("/chain/blocktimes", get(handlers::get_block_times))
+        .route("/chain/hashrate", get(handlers::get_hashrate))
+        .route("/chain/genesis", get(handlers::get_genesis))
+        .route("/chain/fork-info", get(handlers::get_fork_info))
+        .route("/chain/tip", get(handlers::get_chain_tip))
+        .route("/chain/work", get(handlers::get_chain_work))
+        .route("/version", get(handlers::get_version))
+        .route(
+            "/address/:address/validate",
+            get(handlers::validate_address),
+        )
+        .route("/address/:address/nonce", get(handlers::get_nonce))
+        .route(
+            "/address/:address/last-spent",
+            get(handlers::get_last_spent),
+        )
+        .route("/address/:address/graph", get(handlers::get_address_graph))
+        .route(
+            "/address/:address/statement.csv",
+            get(handlers::get_address_statement),
+        )
+        .route("/addresses/top", get(handlers::get_top_addresses))
+        .route("/balance/:address", get(handlers::get_balance))
+        .route("/balances", post(handlers::get_balances))
+        .route("/wallet/verify", post(handlers::verify_signature))
+        .route("/search/:query", get(handlers::search))
+        .route("/mine/:job_id", get(handlers::get_mining_job));
+
+    // Answer reads with 503 instead of queueing them behind a long-held chain lock
+    if let Some(window) = config.read_lock_timeout {
+        reads = reads.route_layer(middleware::from_fn_with_state(
+            window,
+            contention::limit_lock_wait,
+        ));
+    }
+
+    let router = Router::new()
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
+        .merge(reads)
+        .merge(mutating)
+        // Requests that take too long are answered with 408 Request Timeout
+        .layer(TimeoutLayer::new(config.request_timeout))
+        // Streaming routes stay open for as long as the client listens
+        .route("/ws/mempool", get(handlers::mempool_ws))
+        .route("/ws/address/:address", get(handlers::address_ws))
         .with_state(blockchain)
-        .layer(cors)
+        .layer(Extension(Arc::new(handlers::MiningJobs::default())))
+        // Bodies over the limit are rejected with 413 Payload Too Large
+        .layer(DefaultBodyLimit::max(config.max_body_bytes));
+
+    // Shed requests beyond the concurrency limit with 503 instead of queueing them
+    let router = match config.max_concurrent_requests {
+        Some(limit) => router.layer(middleware::from_fn_with_state(
+            Arc::new(Semaphore::new(limit)),
+            concurrency::limit_concurrency,
+        )),
+        None => router,
+    };
+
+    router.layer(cors)
 }
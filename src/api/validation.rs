@@ -0,0 +1,214 @@
+use std::sync::OnceLock;
+
+use axum::{
+    async_trait,
+    extract::{FromRequest, Request},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use log::error;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use utoipa::openapi::{Components, RefOr, Schema};
+use utoipa::{OpenApi, ToSchema};
+
+use super::docs::ApiDoc;
+use super::handlers::common::ErrorResponse;
+
+/// JSON request body checked against the constraints of its OpenAPI schema,
+/// such as `minimum` or `max_length`, before it is deserialized.
+///
+/// Out-of-constraint bodies are rejected with 400 and an [`ErrorResponse`]
+/// naming the offending field; everything else behaves like [`Json`].
+#[derive(Debug)]
+pub struct ValidatedJson<T>(pub T);
+
+/// A request field that breaks its schema's constraints
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaViolation {
+    /// Path of the field, e.g. `outputs[1].amount`
+    pub field: String,
+    /// The constraint it breaks
+    pub message: String,
+}
+
+impl IntoResponse for SchemaViolation {
+    fn into_response(self) -> Response {
+        let error = format!("Invalid request: {} {}", self.field, self.message);
+        error!("Rejecting request body with status 400: {}", error);
+        let body = ErrorResponse {
+            error,
+            code: "invalid_request".to_string(),
+        };
+        (StatusCode::BAD_REQUEST, Json(body)).into_response()
+    }
+}
+
+#[async_trait]
+impl<S, T> FromRequest<S> for ValidatedJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + ToSchema<'static>,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<Value>::from_request(request, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        let (_, schema) = T::schema();
+        check(&schema, &value, "").map_err(IntoResponse::into_response)?;
+
+        let bytes = serde_json::to_vec(&value).unwrap_or_default();
+        let Json(body) = Json::<T>::from_bytes(&bytes).map_err(IntoResponse::into_response)?;
+        Ok(Self(body))
+    }
+}
+
+/// Schemas referenced by the request types, resolved once
+fn components() -> &'static Components {
+    static COMPONENTS: OnceLock<Components> = OnceLock::new();
+    COMPONENTS.get_or_init(|| ApiDoc::openapi().components.unwrap_or_default())
+}
+
+/// Checks a JSON value against a schema's numeric and length constraints,
+/// following references and descending into objects and arrays
+pub fn check(schema: &RefOr<Schema>, value: &Value, path: &str) -> Result<(), SchemaViolation> {
+    let violation = |message: String| SchemaViolation {
+        field: if path.is_empty() {
+            "body".to_string()
+        } else {
+            path.to_string()
+        },
+        message,
+    };
+
+    match schema {
+        RefOr::Ref(reference) => {
+            let name = reference
+                .ref_location
+                .rsplit('/')
+                .next()
+                .unwrap_or_default();
+            match components().schemas.get(name) {
+                Some(schema) => check(schema, value, path),
+                None => Ok(()),
+            }
+        }
+        RefOr::T(Schema::Object(object)) => {
+            // Amounts written as decimal strings are held to the same bounds
+            let number = value
+                .as_f64()
+                .or_else(|| value.as_str().and_then(|text| text.parse().ok()));
+            if let Some(number) = number {
+                if let Some(minimum) = object.minimum {
+                    if number < minimum {
+                        return Err(violation(format!("must be at least {}", minimum)));
+                    }
+                }
+                if let Some(minimum) = object.exclusive_minimum {
+                    if number <= minimum {
+                        return Err(violation(format!("must be greater than {}", minimum)));
+                    }
+                }
+                if let Some(maximum) = object.maximum {
+                    if number > maximum {
+                        return Err(violation(format!("must be at most {}", maximum)));
+                    }
+                }
+            }
+            if let Some(text) = value.as_str() {
+                let length = text.chars().count();
+                if let Some(max_length) = object.max_length {
+                    if length > max_length {
+                        return Err(violation(format!(
+                            "must be at most {} characters long",
+                            max_length
+                        )));
+                    }
+                }
+                if let Some(min_length) = object.min_length {
+                    if length < min_length {
+                        return Err(violation(format!(
+                            "must be at least {} characters long",
+                            min_length
+                        )));
+                    }
+                }
+            }
+            if let Some(fields) = value.as_object() {
+                for (name, property) in &object.properties {
+                    if let Some(field) = fields.get(name) {
+                        let path = if path.is_empty() {
+                            name.clone()
+                        } else {
+                            format!("{}.{}", path, name)
+                        };
+                        check(property, field, &path)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+        RefOr::T(Schema::Array(array)) => match value.as_array() {
+            Some(items) => {
+                if let Some(max_items) = array.max_items {
+                    if items.len() > max_items {
+                        return Err(violation(format!("must have at most {} items", max_items)));
+                    }
+                }
+                items.iter().enumerate().try_for_each(|(i, item)| {
+                    check(&array.items, item, &format!("{}[{}]", path, i))
+                })
+            }
+            None => Ok(()),
+        },
+        RefOr::T(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::api::handlers::CreateTransactionRequest;
+
+    fn check_request(value: Value) -> Result<(), SchemaViolation> {
+        check(&CreateTransactionRequest::schema().1, &value, "")
+    }
+
+    #[test]
+    fn test_constraints_are_enforced() {
+        let valid =
+            json!({ "sender": "system", "recipient": "bob", "amount": 10.0, "signature": "" });
+        assert_eq!(check_request(valid), Ok(()));
+
+        let negative = json!({ "sender": "system", "recipient": "bob", "amount": -1.0 });
+        assert_eq!(check_request(negative).unwrap_err().field, "amount");
+
+        // Amounts written as strings, as with the `string-amounts` feature
+        let negative = json!({ "sender": "system", "recipient": "bob", "amount": "-1" });
+        assert_eq!(check_request(negative).unwrap_err().field, "amount");
+
+        let long = json!({ "sender": "a".repeat(200), "recipient": "bob", "amount": 1.0 });
+        assert_eq!(check_request(long).unwrap_err().field, "sender");
+    }
+
+    #[test]
+    fn test_nested_fields_are_reported_by_path() {
+        let outputs = json!({
+            "sender": "alice",
+            "outputs": [
+                { "address": "bob", "amount": 1.0 },
+                { "address": "carol", "amount": 0.0 }
+            ]
+        });
+
+        let violation = check_request(outputs).unwrap_err();
+        assert_eq!(violation.field, "outputs[1].amount");
+        assert_eq!(violation.message, "must be greater than 0");
+    }
+}
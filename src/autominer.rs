@@ -0,0 +1,178 @@
+//! Background mining by the node itself.
+//!
+//! The autominer mines a block, credited to the node's configured miner
+//! address, whenever the pending pool holds transactions that can go into a
+//! block, and optionally on a fixed timer as well. Proof of work runs off the
+//! chain lock through [`mine_shared`], so the API keeps answering while a
+//! block is being mined.
+
+use std::time::Duration;
+
+use log::{error, info, warn};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::blockchain::{mine_shared, BlockchainError, MempoolEvent, SharedBlockchain};
+
+/// Settings of the autominer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutominerConfig {
+    /// Address the mined blocks reward
+    pub miner_address: String,
+    /// Also mine a block, even an empty one, this often
+    pub interval: Option<Duration>,
+}
+
+/// Returns true if the next block would include at least one pending transaction
+fn has_minable_transactions(blockchain: &SharedBlockchain) -> bool {
+    !blockchain
+        .lock()
+        .unwrap()
+        .select_transactions_for_block()
+        .0
+        .is_empty()
+}
+
+/// Mines one block on a blocking thread
+async fn mine_block(blockchain: &SharedBlockchain, miner_address: &str) -> bool {
+    let blockchain = blockchain.clone();
+    let miner_address = miner_address.to_string();
+    let result = tokio::task::spawn_blocking(move || mine_shared(&blockchain, &miner_address))
+        .await
+        .unwrap_or_else(|e| {
+            Err(BlockchainError::ValidationFailed(format!(
+                "Mining task failed: {}",
+                e
+            )))
+        });
+
+    match result {
+        Ok(block) => {
+            info!(
+                "Automined block #{} with {} transactions",
+                block.index,
+                block.transactions.len()
+            );
+            true
+        }
+        Err(err) => {
+            error!("Automining failed: {}", err);
+            false
+        }
+    }
+}
+
+/// Starts the autominer. It runs until the returned task is aborted.
+pub fn spawn_autominer(blockchain: &SharedBlockchain, config: AutominerConfig) -> JoinHandle<()> {
+    let mut events = blockchain.lock().unwrap().subscribe_mempool();
+    let blockchain = blockchain.clone();
+    info!("Automining blocks for {}", config.miner_address);
+
+    tokio::spawn(async move {
+        let mut timer = config.interval.map(|interval| {
+            let mut timer =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            timer
+        });
+
+        // Transactions submitted before the autominer started are mined too
+        let mut pending = has_minable_transactions(&blockchain);
+        loop {
+            while pending {
+                if !mine_block(&blockchain, &config.miner_address).await {
+                    break;
+                }
+                pending = has_minable_transactions(&blockchain);
+            }
+
+            let tick = async {
+                match &mut timer {
+                    Some(timer) => {
+                        timer.tick().await;
+                    }
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(MempoolEvent::Added { .. }) | Ok(MempoolEvent::Rebroadcast { .. }) => {
+                        pending = has_minable_transactions(&blockchain);
+                    }
+                    Ok(MempoolEvent::Removed { .. }) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Autominer lagging, skipped {} mempool events", skipped);
+                        pending = has_minable_transactions(&blockchain);
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = tick => {
+                    mine_block(&blockchain, &config.miner_address).await;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::test_utils::MINER;
+    use crate::blockchain::{create_shared_blockchain, Address, Transaction};
+
+    async fn wait_for_length(blockchain: &SharedBlockchain, length: usize) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while blockchain.lock().unwrap().chain_length() < length {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("no block was automined");
+    }
+
+    #[tokio::test]
+    async fn test_pending_transaction_is_automined() {
+        let blockchain = create_shared_blockchain(1, 50.0);
+        let handle = spawn_autominer(
+            &blockchain,
+            AutominerConfig {
+                miner_address: MINER.to_string(),
+                interval: None,
+            },
+        );
+
+        let transaction = Transaction::new(
+            Address("system".to_string()),
+            Address("recipient".to_string()),
+            10.0,
+        );
+        blockchain
+            .lock()
+            .unwrap()
+            .create_transaction(transaction)
+            .unwrap();
+
+        wait_for_length(&blockchain, 2).await;
+        let blockchain = blockchain.lock().unwrap();
+        assert!(blockchain.pending_transactions.is_empty());
+        assert_eq!(blockchain.get_balance("recipient"), 10.0);
+        assert_eq!(blockchain.get_balance(MINER), 50.0);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_timer_mines_empty_blocks() {
+        let blockchain = create_shared_blockchain(1, 50.0);
+        let handle = spawn_autominer(
+            &blockchain,
+            AutominerConfig {
+                miner_address: MINER.to_string(),
+                interval: Some(Duration::from_millis(20)),
+            },
+        );
+
+        wait_for_length(&blockchain, 3).await;
+        assert!(blockchain.lock().unwrap().get_balance(MINER) >= 100.0);
+        handle.abort();
+    }
+}
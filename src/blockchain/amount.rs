@@ -0,0 +1,129 @@
+//! Serde helpers that carry amounts and balances as decimal strings.
+//!
+//! JSON clients that parse numbers into IEEE doubles, such as JavaScript,
+//! silently round integers above 2^53. With the `string-amounts` feature,
+//! amount, fee and balance fields use this module and are written as plain
+//! decimal strings instead: no exponent, no thousands separators, a `.` before
+//! any fractional digits and a leading `-` for negative values, e.g.
+//! `"9007199254740993"` or `"10.5"`. Both strings and numbers are accepted when
+//! reading, so clients can migrate one side at a time.
+//!
+//! Binary formats such as bincode keep the plain `f64` encoding.
+
+use std::fmt;
+
+use serde::de::{self, Visitor};
+use serde::{Deserializer, Serializer};
+use utoipa::openapi::{Object, ObjectBuilder, SchemaType};
+
+/// Writes an amount as a decimal string in human-readable formats
+pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.collect_str(value)
+    } else {
+        serializer.serialize_f64(*value)
+    }
+}
+
+/// Reads an amount written as either a decimal string or a number
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(AmountVisitor)
+    } else {
+        deserializer.deserialize_f64(AmountVisitor)
+    }
+}
+
+/// Schema of an amount written as a decimal string that must be at least 0.
+/// The bound is checked against the parsed amount at the API boundary, see
+/// [`crate::api::validation`].
+pub fn non_negative_schema() -> Object {
+    string_schema().minimum(Some(0.0)).build()
+}
+
+/// Schema of an amount written as a decimal string that must be greater than
+/// 0, see [`non_negative_schema`]
+pub fn positive_schema() -> Object {
+    string_schema().exclusive_minimum(Some(0.0)).build()
+}
+
+fn string_schema() -> ObjectBuilder {
+    ObjectBuilder::new()
+        .schema_type(SchemaType::String)
+        .description(Some("Amount as a decimal string, e.g. \"10.5\""))
+}
+
+struct AmountVisitor;
+
+impl<'de> Visitor<'de> for AmountVisitor {
+    type Value = f64;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an amount as a decimal string or a number")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<f64, E> {
+        let amount: f64 = value
+            .parse()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))?;
+        if amount.is_finite() {
+            Ok(amount)
+        } else {
+            Err(E::invalid_value(de::Unexpected::Str(value), &self))
+        }
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<f64, E> {
+        Ok(value)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<f64, E> {
+        Ok(value as f64)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<f64, E> {
+        Ok(value as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper {
+        #[serde(with = "super")]
+        amount: f64,
+    }
+
+    #[test]
+    fn test_large_amount_round_trips_as_string() {
+        // 2^53 + 2, the first even integer a double can't reach by counting from 2^53
+        let wrapper = Wrapper {
+            amount: 9_007_199_254_740_994.0,
+        };
+
+        let json = serde_json::to_string(&wrapper).unwrap();
+        assert_eq!(json, r#"{"amount":"9007199254740994"}"#);
+        assert_eq!(serde_json::from_str::<Wrapper>(&json).unwrap(), wrapper);
+
+        let bytes = bincode::serialize(&wrapper).unwrap();
+        assert_eq!(bincode::deserialize::<Wrapper>(&bytes).unwrap(), wrapper);
+    }
+
+    #[test]
+    fn test_accepts_numbers_and_fractions() {
+        let from_number: Wrapper = serde_json::from_str(r#"{"amount":10}"#).unwrap();
+        assert_eq!(from_number.amount, 10.0);
+
+        let from_string: Wrapper = serde_json::from_str(r#"{"amount":"10.5"}"#).unwrap();
+        assert_eq!(from_string.amount, 10.5);
+        assert_eq!(
+            serde_json::to_string(&from_string).unwrap(),
+            r#"{"amount":"10.5"}"#
+        );
+
+        assert!(serde_json::from_str::<Wrapper>(r#"{"amount":"1e400"}"#).is_err());
+        assert!(serde_json::from_str::<Wrapper>(r#"{"amount":"ten"}"#).is_err());
+    }
+}
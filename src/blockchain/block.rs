@@ -1,12 +1,97 @@
+use std::time::Duration;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use utoipa::ToSchema;
 
+use super::canonical::CanonicalWriter;
+use super::consensus::BlockSignature;
+use super::merkle::{merkle_root, EMPTY_MERKLE_ROOT};
 use super::transaction::Transaction;
 
+/// Unix timestamp of the genesis block (2024-01-01T00:00:00Z).
+/// It is fixed so that every node derives the exact same genesis hash.
+pub const GENESIS_TIMESTAMP: i64 = 1_704_067_200;
+
+/// Highest possible difficulty: a SHA-256 hash has 64 hex characters,
+/// so no hash can have more leading zeros than that
+pub const MAX_DIFFICULTY: usize = 64;
+
+/// How far ahead of the local clock a block or transaction may be stamped
+/// unless another limit is configured
+pub const DEFAULT_MAX_FUTURE_DRIFT: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// Written in place of the transaction count of a block hashed over its
+/// Merkle root, so the two layouts can't be mistaken for each other
+const MERKLE_COMMITMENT_MARKER: u32 = u32::MAX;
+
+/// How a block hash commits to the block's transactions. Each block records
+/// its own scheme, so chains from before Merkle roots keep validating.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionCommitment {
+    /// Every transaction hash, in ascending order. Blocks that don't record a
+    /// scheme were hashed this way.
+    #[default]
+    Legacy,
+    /// The Merkle root of the transactions, in the order they are stored
+    MerkleRoot,
+}
+
+/// What a block hash commits to in place of the transactions
+enum Commitment<'a> {
+    /// Every transaction hash, in any order
+    Legacy(Vec<&'a str>),
+    /// The Merkle root of the transactions
+    MerkleRoot(&'a str),
+}
+
+/// Checks that `hash` starts with `difficulty` zeros. A declared difficulty
+/// comes from the block, so it is never used to size an allocation.
+fn has_leading_zeros(hash: &str, difficulty: usize) -> bool {
+    hash.len() >= difficulty && hash.bytes().take(difficulty).all(|byte| byte == b'0')
+}
+
+/// Hashes the canonical encoding of a block, see [`crate::blockchain::canonical`]
+fn block_hash(
+    index: u64,
+    timestamp: &DateTime<Utc>,
+    commitment: Commitment,
+    previous_hash: &str,
+    nonce: u64,
+) -> String {
+    let mut preimage = CanonicalWriter::new();
+    preimage.u64(index).timestamp(timestamp);
+    match commitment {
+        Commitment::Legacy(mut hashes) => {
+            hashes.sort_unstable();
+
+            preimage.u32(hashes.len() as u32);
+            for hash in hashes {
+                preimage.string(hash);
+            }
+        }
+        Commitment::MerkleRoot(root) => {
+            preimage.u32(MERKLE_COMMITMENT_MARKER).string(root);
+        }
+    }
+    preimage.string(previous_hash).u64(nonce);
+
+    hex::encode(Sha256::digest(preimage.finish()))
+}
+
+/// Returns true if `timestamp` is at most `max_future_drift` ahead of now
+pub fn is_within_future_drift(timestamp: DateTime<Utc>, max_future_drift: Duration) -> bool {
+    let drift = chrono::Duration::from_std(max_future_drift).unwrap_or(chrono::Duration::MAX);
+    Utc::now()
+        .checked_add_signed(drift)
+        .is_none_or(|limit| timestamp <= limit)
+}
+
 /// Represents a block in the blockchain
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct Block {
     /// Index of the block in the chain
     pub index: u64,
@@ -22,6 +107,98 @@ pub struct Block {
     pub hash: String,
     /// Difficulty level used for mining this block
     pub difficulty: usize,
+    /// How the hash commits to the transactions
+    #[serde(default)]
+    pub tx_commitment: TransactionCommitment,
+    /// Signature of the authority that sealed the block on a proof-of-authority
+    /// chain; not covered by the hash
+    #[serde(default)]
+    pub block_signature: Option<BlockSignature>,
+}
+
+/// A block without its transactions, enough to check proof of work and linkage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockHeader {
+    /// Index of the block in the chain
+    pub index: u64,
+    /// Timestamp when the block was created
+    pub timestamp: DateTime<Utc>,
+    /// Hash of the previous block
+    pub previous_hash: String,
+    /// Merkle root of the block's transactions
+    pub merkle_root: String,
+    /// Nonce used for mining (proof of work)
+    pub nonce: u64,
+    /// Hash of the block
+    pub hash: String,
+    /// Difficulty level used for mining the block
+    pub difficulty: usize,
+    /// How the hash commits to the transactions
+    #[serde(default)]
+    pub tx_commitment: TransactionCommitment,
+}
+
+impl BlockHeader {
+    /// Recomputes the block hash from the header fields.
+    ///
+    /// Returns `None` for a block committing to its transactions the legacy
+    /// way, as its hash covers every transaction hash rather than the Merkle
+    /// root, unless the block has no transactions at all.
+    pub fn calculate_hash(&self) -> Option<String> {
+        let commitment = match self.tx_commitment {
+            TransactionCommitment::MerkleRoot => Commitment::MerkleRoot(&self.merkle_root),
+            TransactionCommitment::Legacy if self.merkle_root == EMPTY_MERKLE_ROOT => {
+                Commitment::Legacy(Vec::new())
+            }
+            TransactionCommitment::Legacy => return None,
+        };
+        Some(block_hash(
+            self.index,
+            &self.timestamp,
+            commitment,
+            &self.previous_hash,
+            self.nonce,
+        ))
+    }
+
+    /// Checks that the stored hash is the hash of the header fields
+    pub fn verify_hash(&self) -> bool {
+        self.calculate_hash().as_deref() == Some(self.hash.as_str())
+    }
+
+    /// Checks that the hash meets the header's difficulty
+    pub fn verify_proof_of_work(&self) -> bool {
+        has_leading_zeros(&self.hash, self.difficulty)
+    }
+
+    /// Checks that this header follows `previous`: consecutive index, matching
+    /// previous hash, proof of work and a timestamp after the previous one but
+    /// at most `max_future_drift` ahead of now
+    pub fn is_valid_next_header(&self, previous: &BlockHeader, max_future_drift: Duration) -> bool {
+        // Check block sequence
+        if previous.index.checked_add(1) != Some(self.index) {
+            return false;
+        }
+
+        // Check previous hash reference
+        if self.previous_hash != previous.hash {
+            return false;
+        }
+
+        // Check proof of work
+        if !self.verify_proof_of_work() {
+            return false;
+        }
+
+        // Validate timestamp (block must be after previous block)
+        if self.timestamp <= previous.timestamp {
+            return false;
+        }
+
+        // Prevent timestamps too far in the future
+        is_within_future_drift(self.timestamp, max_future_drift)
+    }
 }
 
 impl Block {
@@ -31,15 +208,40 @@ impl Block {
         transactions: Vec<Transaction>,
         previous_hash: String,
         difficulty: usize,
+    ) -> Self {
+        Self::new_with_timestamp(
+            index,
+            transactions,
+            previous_hash,
+            difficulty,
+            Utc::now(),
+            0,
+            TransactionCommitment::Legacy,
+        )
+    }
+
+    /// Creates a block with the given timestamp, nonce and commitment scheme,
+    /// e.g. to replay a historical block so that it hashes exactly as when it
+    /// was recorded
+    pub fn new_with_timestamp(
+        index: u64,
+        transactions: Vec<Transaction>,
+        previous_hash: String,
+        difficulty: usize,
+        timestamp: DateTime<Utc>,
+        nonce: u64,
+        tx_commitment: TransactionCommitment,
     ) -> Self {
         let mut block = Self {
             index,
-            timestamp: Utc::now(),
+            timestamp,
             transactions,
             previous_hash,
-            nonce: 0,
+            nonce,
             hash: String::new(),
             difficulty,
+            tx_commitment,
+            block_signature: None,
         };
 
         block.hash = block.calculate_hash();
@@ -48,71 +250,144 @@ impl Block {
 
     /// Creates the genesis block (first block in the chain)
     pub fn genesis(difficulty: usize) -> Self {
-        let mut block = Self {
-            index: 0,
-            timestamp: Utc::now(),
-            transactions: vec![],
-            previous_hash: "0".repeat(64),
-            nonce: 0,
-            hash: String::new(),
+        Self::new_with_timestamp(
+            0,
+            vec![],
+            "0".repeat(64),
             difficulty,
-        };
+            DateTime::from_timestamp(GENESIS_TIMESTAMP, 0).expect("genesis timestamp is in range"),
+            0,
+            TransactionCommitment::Legacy,
+        )
+    }
 
-        block.hash = block.calculate_hash();
-        block
+    /// Sets how the hash commits to the transactions, and rehashes the block
+    pub fn with_commitment(mut self, tx_commitment: TransactionCommitment) -> Self {
+        self.tx_commitment = tx_commitment;
+        self.hash = self.calculate_hash();
+        self
     }
 
-    /// Calculates the hash of the block
-    pub fn calculate_hash(&self) -> String {
-        let mut hasher = Sha256::new();
+    /// Work the block represents: the expected number of hashes needed to meet
+    /// its difficulty, i.e. 2^(4 * difficulty) as each difficulty step is a hex
+    /// digit. Saturates at `u128::MAX` from difficulty 32 up.
+    pub fn work(&self) -> u128 {
+        self.difficulty
+            .checked_mul(4)
+            .and_then(|bits| u32::try_from(bits).ok())
+            .and_then(|bits| 1u128.checked_shl(bits))
+            .unwrap_or(u128::MAX)
+    }
 
-        // Add block data to hasher in a more efficient way
-        hasher.update(self.index.to_be_bytes());
-        hasher.update(self.timestamp.timestamp().to_be_bytes());
+    /// Sum of the fees paid by the block's transactions
+    pub fn total_fees(&self) -> f64 {
+        self.transactions.iter().map(|tx| tx.fee).sum()
+    }
 
-        // Process transactions more efficiently
-        for tx in &self.transactions {
-            hasher.update(tx.hash.as_bytes());
+    /// Returns the header of the block
+    pub fn header(&self) -> BlockHeader {
+        BlockHeader {
+            index: self.index,
+            timestamp: self.timestamp,
+            previous_hash: self.previous_hash.clone(),
+            merkle_root: merkle_root(&self.transactions),
+            nonce: self.nonce,
+            hash: self.hash.clone(),
+            difficulty: self.difficulty,
+            tx_commitment: self.tx_commitment,
         }
+    }
 
-        hasher.update(self.previous_hash.as_bytes());
-        hasher.update(self.nonce.to_be_bytes());
-
-        hex::encode(hasher.finalize())
+    /// Calculates the hash of the block over its canonical encoding,
+    /// see [`crate::blockchain::canonical`] for the byte layout.
+    ///
+    /// Under the legacy scheme, transaction hashes are committed to in sorted
+    /// order, so the same set of transactions gives the same hash whatever
+    /// order it is stored in. A Merkle root commits to the order as well.
+    pub fn calculate_hash(&self) -> String {
+        let root;
+        let commitment = match self.tx_commitment {
+            TransactionCommitment::Legacy => Commitment::Legacy(
+                self.transactions
+                    .iter()
+                    .map(|tx| tx.hash.as_str())
+                    .collect(),
+            ),
+            TransactionCommitment::MerkleRoot => {
+                root = merkle_root(&self.transactions);
+                Commitment::MerkleRoot(&root)
+            }
+        };
+        block_hash(
+            self.index,
+            &self.timestamp,
+            commitment,
+            &self.previous_hash,
+            self.nonce,
+        )
     }
 
     /// Mines the block using the block's difficulty setting
-    /// The difficulty determines how many leading zeros the hash must have
+    /// The difficulty determines how many leading zeros the hash must have.
+    /// If the whole nonce space is exhausted, the timestamp is moved forward by
+    /// one second and the search starts over instead of overflowing the nonce.
+    /// Callers must keep the difficulty at or below [`MAX_DIFFICULTY`].
     pub fn mine(&mut self) {
+        self.mine_until(|| false);
+    }
+
+    /// Mines the block like [`Block::mine`], but gives up as soon as `stop`
+    /// returns true. Returns whether a valid proof of work was found.
+    pub fn mine_until(&mut self, stop: impl Fn() -> bool) -> bool {
         let target = "0".repeat(self.difficulty);
 
         while !self.hash.starts_with(&target) {
-            self.nonce += 1;
+            if stop() {
+                return false;
+            }
+            self.nonce = match self.nonce.checked_add(1) {
+                Some(nonce) => nonce,
+                None => {
+                    self.timestamp += chrono::Duration::seconds(1);
+                    0
+                }
+            };
             self.hash = self.calculate_hash();
         }
+        true
     }
 
     /// Verifies that the block meets the proof of work requirement
     pub fn verify_proof_of_work(&self, difficulty: usize) -> bool {
-        if difficulty == 0 {
-            return true;
-        }
-
-        let target = "0".repeat(difficulty);
-        self.hash.starts_with(&target)
+        has_leading_zeros(&self.hash, difficulty)
     }
 
-    /// Validates the block
+    /// Validates the block on its own: the stored hash matches the contents,
+    /// meets the stored difficulty, and every transaction is valid.
+    ///
+    /// The genesis block (index 0) isn't mined, so its hash may not meet the
+    /// difficulty it records for the chain.
     pub fn is_valid(&self) -> bool {
+        self.is_valid_in(false)
+    }
+
+    /// Same as [`Block::is_valid`], validating transactions as a permissioned
+    /// chain does when `permissioned` is set, see [`Transaction::validate_in`]
+    pub fn is_valid_in(&self, permissioned: bool) -> bool {
         // Check if the hash is correct
         let calculated_hash = self.calculate_hash();
         if calculated_hash != self.hash {
             return false;
         }
 
+        // Check that the hash backs up the difficulty the block claims
+        if self.index != 0 && !self.verify_proof_of_work(self.difficulty) {
+            return false;
+        }
+
         // Check if all transactions are valid
         for transaction in &self.transactions {
-            if !transaction.is_valid() {
+            if transaction.validate_in(permissioned).is_err() {
                 return false;
             }
         }
@@ -121,14 +396,23 @@ impl Block {
     }
 
     // Add a method to validate the block against a previous block
-    pub fn is_valid_next_block(&self, previous_block: &Block) -> bool {
-        // Check block sequence
-        if self.index != previous_block.index + 1 {
-            return false;
-        }
+    pub fn is_valid_next_block(&self, previous_block: &Block, max_future_drift: Duration) -> bool {
+        self.is_valid_next_block_in(previous_block, max_future_drift, false)
+    }
 
-        // Check previous hash reference
-        if self.previous_hash != previous_block.hash {
+    /// Same as [`Block::is_valid_next_block`], validating transactions as a
+    /// permissioned chain does when `permissioned` is set
+    pub fn is_valid_next_block_in(
+        &self,
+        previous_block: &Block,
+        max_future_drift: Duration,
+        permissioned: bool,
+    ) -> bool {
+        // Check index, previous hash, proof of work and timestamp
+        if !self
+            .header()
+            .is_valid_next_header(&previous_block.header(), max_future_drift)
+        {
             return false;
         }
 
@@ -137,25 +421,9 @@ impl Block {
             return false;
         }
 
-        // Check proof of work
-        if !self.verify_proof_of_work(self.difficulty) {
-            return false;
-        }
-
-        // Validate timestamp (block must be after previous block)
-        if self.timestamp <= previous_block.timestamp {
-            return false;
-        }
-
-        // Prevent timestamps too far in the future (e.g., 2 hours)
-        let future_limit = Utc::now() + chrono::Duration::hours(2);
-        if self.timestamp > future_limit {
-            return false;
-        }
-
         // Validate all transactions in the block
         for transaction in &self.transactions {
-            if !transaction.is_valid() {
+            if transaction.validate_in(permissioned).is_err() {
                 return false;
             }
         }
@@ -169,6 +437,32 @@ mod tests {
     use super::*;
     use crate::blockchain::Address;
 
+    #[test]
+    fn test_total_fees() {
+        let transactions = vec![
+            Transaction::new_with_fee(
+                Address("alice".to_string()),
+                Address("bob".to_string()),
+                10.0,
+                0.5,
+            ),
+            Transaction::new_with_fee(
+                Address("alice".to_string()),
+                Address("carol".to_string()),
+                5.0,
+                0.25,
+            ),
+            Transaction::new(
+                Address("system".to_string()),
+                Address("miner".to_string()),
+                50.75,
+            ),
+        ];
+        let block = Block::new(1, transactions, "0".repeat(64), 1);
+
+        assert_eq!(block.total_fees(), 0.75);
+    }
+
     #[test]
     fn test_genesis_block() {
         let genesis = Block::genesis(4);
@@ -179,11 +473,9 @@ mod tests {
         assert!(!genesis.hash.is_empty());
         assert_eq!(genesis.difficulty, 4); // Check default difficulty
 
-        // Additional check for timestamp being reasonable
-        let now = Utc::now().timestamp();
-        let block_time = genesis.timestamp.timestamp();
-        assert!(block_time <= now);
-        assert!(block_time > now - 10); // Genesis block should be very recent
+        // The genesis block uses a fixed timestamp so every node agrees on its hash
+        assert_eq!(genesis.timestamp.timestamp(), GENESIS_TIMESTAMP);
+        assert_eq!(genesis.hash, Block::genesis(4).hash);
     }
 
     #[test]
@@ -242,36 +534,59 @@ mod tests {
 
         // Should be valid
         assert!(block.is_valid());
-        assert!(block.is_valid_next_block(&genesis));
+        assert!(block.is_valid_next_block(&genesis, DEFAULT_MAX_FUTURE_DRIFT));
 
         // Test with invalid index
         let mut invalid_block = block.clone();
         invalid_block.index = 5;
-        assert!(!invalid_block.is_valid_next_block(&genesis));
+        assert!(!invalid_block.is_valid_next_block(&genesis, DEFAULT_MAX_FUTURE_DRIFT));
 
         // Test with invalid previous hash
         let mut invalid_block = block.clone();
         invalid_block.previous_hash = "invalid_hash".to_string();
-        assert!(!invalid_block.is_valid_next_block(&genesis));
+        assert!(!invalid_block.is_valid_next_block(&genesis, DEFAULT_MAX_FUTURE_DRIFT));
 
         // Test with tampered hash
         let mut invalid_block = block.clone();
         invalid_block.hash = "tampered_hash".to_string();
         assert!(!invalid_block.is_valid());
-        assert!(!invalid_block.is_valid_next_block(&genesis));
+        assert!(!invalid_block.is_valid_next_block(&genesis, DEFAULT_MAX_FUTURE_DRIFT));
 
         // Test with tampered nonce
         let mut invalid_block = block.clone();
         invalid_block.nonce += 1;
         invalid_block.hash = invalid_block.calculate_hash();
-        assert!(invalid_block.is_valid()); // The hash is still valid for the block itself
+        assert_eq!(invalid_block.hash, invalid_block.calculate_hash()); // The hash matches the contents
         assert!(!invalid_block.verify_proof_of_work(2)); // But it no longer meets the proof of work requirement
+        assert!(!invalid_block.is_valid());
 
         // Test with invalid timestamp (before previous block)
         let mut invalid_block = block.clone();
         invalid_block.timestamp = genesis.timestamp - chrono::Duration::seconds(1);
         invalid_block.hash = invalid_block.calculate_hash();
-        assert!(!invalid_block.is_valid_next_block(&genesis));
+        assert!(!invalid_block.is_valid_next_block(&genesis, DEFAULT_MAX_FUTURE_DRIFT));
+    }
+
+    #[test]
+    fn test_stored_difficulty_must_match_hash() {
+        let mut block = Block::new(1, vec![], "0".repeat(64), 1);
+        block.mine();
+        let leading_zeros = block.hash.chars().take_while(|&c| c == '0').count();
+        assert!(block.is_valid());
+
+        // Claim one more leading zero than the hash has
+        block.difficulty = leading_zeros + 1;
+        block.hash = block.calculate_hash();
+        while block.verify_proof_of_work(block.difficulty) {
+            block.nonce += 1;
+            block.hash = block.calculate_hash();
+        }
+        assert!(!block.is_valid());
+
+        // The genesis block isn't mined, whatever difficulty it records
+        let genesis = Block::genesis(4);
+        assert!(!genesis.verify_proof_of_work(4));
+        assert!(genesis.is_valid());
     }
 
     #[test]
@@ -297,6 +612,138 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_canonical_hash_is_stable() {
+        let timestamp = DateTime::from_timestamp(GENESIS_TIMESTAMP, 0).unwrap();
+        let mut tx = Transaction::new(
+            Address("system".to_string()),
+            Address("recipient".to_string()),
+            10.0,
+        );
+        tx.timestamp = timestamp;
+        tx.hash = tx.calculate_hash();
+
+        let block = Block::new_with_timestamp(
+            1,
+            vec![tx],
+            "0".repeat(64),
+            1,
+            timestamp,
+            42,
+            TransactionCommitment::Legacy,
+        );
+
+        assert_eq!(
+            block.hash,
+            "7ccfd9da528f6a181b489b8de9487563dd276821d5867efda1128cb3e808a146"
+        );
+    }
+
+    #[test]
+    fn test_transaction_order_does_not_change_hash() {
+        let timestamp = DateTime::from_timestamp(GENESIS_TIMESTAMP, 0).unwrap();
+        let transactions: Vec<Transaction> = (1..=3)
+            .map(|i| {
+                Transaction::new(
+                    Address("system".to_string()),
+                    Address(format!("recipient{}", i)),
+                    10.0,
+                )
+            })
+            .collect();
+        let mut reversed = transactions.clone();
+        reversed.reverse();
+
+        let legacy = TransactionCommitment::Legacy;
+        let block =
+            Block::new_with_timestamp(1, transactions, "0".repeat(64), 1, timestamp, 0, legacy);
+        let shuffled =
+            Block::new_with_timestamp(1, reversed, "0".repeat(64), 1, timestamp, 0, legacy);
+
+        assert_ne!(block.transactions, shuffled.transactions);
+        assert_eq!(block.hash, shuffled.hash);
+    }
+
+    #[test]
+    fn test_merkle_commitment_covers_transaction_order() {
+        let timestamp = DateTime::from_timestamp(GENESIS_TIMESTAMP, 0).unwrap();
+        let transactions: Vec<Transaction> = (1..=3)
+            .map(|i| {
+                Transaction::new(
+                    Address("system".to_string()),
+                    Address(format!("recipient{}", i)),
+                    10.0,
+                )
+            })
+            .collect();
+        let mut reversed = transactions.clone();
+        reversed.reverse();
+
+        let legacy = Block::new_with_timestamp(
+            1,
+            transactions,
+            "0".repeat(64),
+            0,
+            timestamp,
+            0,
+            TransactionCommitment::Legacy,
+        );
+        let merkle = legacy
+            .clone()
+            .with_commitment(TransactionCommitment::MerkleRoot);
+        let shuffled = Block::new_with_timestamp(
+            1,
+            reversed,
+            "0".repeat(64),
+            0,
+            timestamp,
+            0,
+            TransactionCommitment::MerkleRoot,
+        );
+
+        assert_ne!(merkle.hash, legacy.hash);
+        assert_ne!(merkle.hash, shuffled.hash);
+        assert!(legacy.is_valid() && merkle.is_valid());
+
+        // Blocks persisted before the scheme was recorded are legacy blocks
+        let mut json = serde_json::to_value(&legacy).unwrap();
+        json.as_object_mut().unwrap().remove("txCommitment");
+        let restored: Block = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.tx_commitment, TransactionCommitment::Legacy);
+        assert_eq!(restored.calculate_hash(), legacy.hash);
+    }
+
+    #[test]
+    fn test_header_matches_block() {
+        let tx = Transaction::new(
+            Address("system".to_string()),
+            Address("recipient".to_string()),
+            10.0,
+        );
+        let mut block = Block::new(1, vec![tx.clone()], "0".repeat(64), 1);
+        block.mine();
+
+        let header = block.header();
+        assert_eq!(header.hash, block.hash);
+        assert_eq!(header.hash, block.calculate_hash());
+        assert_eq!(header.index, block.index);
+        assert_eq!(header.previous_hash, block.previous_hash);
+        assert_eq!(header.merkle_root, tx.hash);
+        assert!(header.verify_proof_of_work());
+        // A legacy block's hash can't be rebuilt without its transactions
+        assert_eq!(header.calculate_hash(), None);
+
+        let mut block = block.with_commitment(TransactionCommitment::MerkleRoot);
+        block.mine();
+        let mut header = block.header();
+        assert_eq!(header.calculate_hash(), Some(block.hash.clone()));
+        assert!(header.verify_hash());
+
+        header.merkle_root = "ab".repeat(32);
+        assert!(!header.verify_hash());
+        assert!(header.verify_proof_of_work());
+    }
+
     #[test]
     fn test_future_timestamp_validation() {
         let genesis = Block::genesis(2);
@@ -317,6 +764,124 @@ mod tests {
         invalid_block.timestamp = Utc::now() + chrono::Duration::hours(3);
         invalid_block.hash = invalid_block.calculate_hash();
 
-        assert!(!invalid_block.is_valid_next_block(&genesis));
+        assert!(!invalid_block.is_valid_next_block(&genesis, DEFAULT_MAX_FUTURE_DRIFT));
+    }
+
+    #[test]
+    fn test_block_serializes_camel_case() {
+        let mut block = Block::new(
+            1,
+            vec![Transaction::new(
+                Address("system".to_string()),
+                Address("recipient".to_string()),
+                50.0,
+            )],
+            "0".repeat(64),
+            1,
+        );
+        block.mine();
+
+        let json = serde_json::to_value(&block).unwrap();
+        assert_eq!(json["previousHash"], "0".repeat(64));
+        assert!(json.get("previous_hash").is_none());
+        assert!(json["transactions"][0].get("publicKey").is_some());
+
+        // Renaming fields must not affect the hash, which only covers field values
+        let decoded: Block = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.calculate_hash(), block.hash);
+        assert!(decoded.transactions[0].is_valid());
+    }
+
+    #[test]
+    fn test_mining_rolls_timestamp_when_nonce_space_is_exhausted() {
+        let mut block = Block::new(1, vec![], "0".repeat(64), 2);
+        block.nonce = u64::MAX;
+        block.hash = block.calculate_hash();
+
+        // Make sure the last nonce doesn't already satisfy the difficulty
+        while block.verify_proof_of_work(2) {
+            block.timestamp += chrono::Duration::seconds(10);
+            block.hash = block.calculate_hash();
+        }
+        let original_timestamp = block.timestamp;
+
+        block.mine();
+
+        assert!(block.verify_proof_of_work(2));
+        assert_eq!(
+            block.timestamp,
+            original_timestamp + chrono::Duration::seconds(1)
+        );
+        assert!(block.nonce < u64::MAX);
+        assert_eq!(block.hash, block.calculate_hash());
+    }
+
+    #[test]
+    fn test_next_block_after_max_index_is_invalid() {
+        let mut previous = Block::genesis(1);
+        previous.index = u64::MAX;
+
+        let mut block = Block::new(0, vec![], previous.hash.clone(), 1);
+        block.mine();
+
+        // Wrapping around to index 0 must not be accepted
+        assert!(!block.is_valid_next_block(&previous, DEFAULT_MAX_FUTURE_DRIFT));
+    }
+
+    #[test]
+    fn test_replayed_block_has_identical_hash() {
+        let tx = Transaction::new(
+            Address("system".to_string()),
+            Address("recipient".to_string()),
+            10.0,
+        );
+        let mut original = Block::new(1, vec![tx], "0".repeat(64), 2);
+        original.mine();
+
+        let json = serde_json::to_string(&original).unwrap();
+        let decoded: Block = serde_json::from_str(&json).unwrap();
+        let replayed = Block::new_with_timestamp(
+            decoded.index,
+            decoded.transactions,
+            decoded.previous_hash,
+            decoded.difficulty,
+            decoded.timestamp,
+            decoded.nonce,
+            decoded.tx_commitment,
+        );
+
+        assert_eq!(replayed.hash, original.hash);
+        assert_eq!(replayed, original);
+    }
+
+    #[test]
+    fn test_replayed_merkle_block_has_identical_hash() {
+        let transactions: Vec<Transaction> = (1..=3)
+            .map(|i| {
+                Transaction::new(
+                    Address("system".to_string()),
+                    Address(format!("recipient{}", i)),
+                    10.0,
+                )
+            })
+            .collect();
+        let mut original = Block::new(1, transactions, "0".repeat(64), 2)
+            .with_commitment(TransactionCommitment::MerkleRoot);
+        original.mine();
+
+        let json = serde_json::to_string(&original).unwrap();
+        let decoded: Block = serde_json::from_str(&json).unwrap();
+        let replayed = Block::new_with_timestamp(
+            decoded.index,
+            decoded.transactions,
+            decoded.previous_hash,
+            decoded.difficulty,
+            decoded.timestamp,
+            decoded.nonce,
+            decoded.tx_commitment,
+        );
+
+        assert_eq!(replayed.hash, original.hash);
+        assert_eq!(replayed, original);
     }
 }
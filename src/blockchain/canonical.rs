@@ -0,0 +1,166 @@
+//! Canonical byte layout used as the preimage for transaction and block hashes.
+//!
+//! Every field is written in a fixed, language-independent encoding so that a
+//! client can recompute a hash without matching Rust's number formatting:
+//!
+//! - integers are fixed-width big-endian
+//! - amounts and fees are fixed-point integers with [`AMOUNT_DECIMALS`]
+//!   decimals (`10.5` is `1_050_000_000`), written as a big-endian `i64`
+//! - timestamps are Unix seconds, written as a big-endian `i64`
+//! - strings are a big-endian `u32` byte length followed by their UTF-8 bytes
+//!
+//! A transaction hash is the SHA-256 of, in order: sender (string),
+//! recipient (string), amount (fixed-point), fee (fixed-point), timestamp,
+//! and the signature scheme as a single byte (0 = ed25519, 1 = secp256k1).
+//! A multi-output transaction, or one with a nonce, then appends the number
+//! of outputs (`u32`, possibly 0) and each output's address (string) and
+//! amount (fixed-point), followed by the nonce (`u64`) if there is one, and
+//! the memo (string) if there is one. A memo requires a nonce, so the two
+//! can't be mistaken for each other. A time-locked transaction, which also
+//! requires a nonce, ends with the string `locked_until` and the unlock
+//! timestamp.
+//!
+//! A block hash is the SHA-256 of, in order: index (`u64`), timestamp, the
+//! commitment to the transactions, the previous block hash (string) and the
+//! nonce (`u64`). Each block records which commitment it uses:
+//!
+//! - legacy: the number of transactions (`u32`) and each transaction hash
+//!   (string) in ascending byte order. Sorting the hashes means the hash
+//!   doesn't depend on the order the transactions were received in.
+//! - Merkle root: `u32::MAX` in place of the count, then the
+//!   [Merkle root](super::merkle::merkle_root) of the transactions in stored
+//!   order (string).
+//!
+//! Blocks assembled by
+//! [`Blockchain::prepare_block`](super::Blockchain::prepare_block) store their
+//! transactions in ascending hash order, with the mining reward last.
+
+use chrono::{DateTime, Utc};
+
+use super::crypto::SignatureScheme;
+
+/// Number of decimals kept when encoding amounts and fees
+pub const AMOUNT_DECIMALS: u32 = 8;
+
+/// Builds a canonical hash preimage field by field
+#[derive(Debug, Default)]
+pub struct CanonicalWriter {
+    bytes: Vec<u8>,
+}
+
+impl CanonicalWriter {
+    /// Starts an empty preimage
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a length-prefixed UTF-8 string
+    pub fn string(&mut self, value: &str) -> &mut Self {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+        self
+    }
+
+    /// Appends an amount as a fixed-point integer
+    pub fn amount(&mut self, value: f64) -> &mut Self {
+        self.i64(to_fixed_point(value))
+    }
+
+    /// Appends a timestamp as Unix seconds
+    pub fn timestamp(&mut self, value: &DateTime<Utc>) -> &mut Self {
+        self.i64(value.timestamp())
+    }
+
+    /// Appends a signature scheme as a single byte
+    pub fn scheme(&mut self, value: SignatureScheme) -> &mut Self {
+        self.bytes.push(match value {
+            SignatureScheme::Ed25519 => 0,
+            SignatureScheme::Secp256k1 => 1,
+        });
+        self
+    }
+
+    /// Appends a big-endian `u32`
+    pub fn u32(&mut self, value: u32) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Appends a big-endian `u64`
+    pub fn u64(&mut self, value: u64) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Appends a big-endian `i64`
+    pub fn i64(&mut self, value: i64) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Returns the preimage
+    pub fn finish(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Converts an amount to a fixed-point integer with [`AMOUNT_DECIMALS`]
+/// decimals, rounding to the nearest unit. Values out of range saturate.
+pub fn to_fixed_point(value: f64) -> i64 {
+    (value * 10f64.powi(AMOUNT_DECIMALS as i32)).round() as i64
+}
+
+/// Converts a fixed-point integer with [`AMOUNT_DECIMALS`] decimals back to
+/// the amount it encodes
+pub fn from_fixed_point(units: i64) -> f64 {
+    units as f64 / 10f64.powi(AMOUNT_DECIMALS as i32)
+}
+
+/// Returns true if the amount survives the round trip through
+/// [`to_fixed_point`], so the canonical encoding commits to it exactly.
+/// Non-finite amounts, amounts out of range and amounts with more than
+/// [`AMOUNT_DECIMALS`] decimals don't.
+pub fn is_exact_amount(value: f64) -> bool {
+    value.is_finite() && from_fixed_point(to_fixed_point(value)) == value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_point_amounts() {
+        assert_eq!(to_fixed_point(10.0), 1_000_000_000);
+        assert_eq!(to_fixed_point(10.5), 1_050_000_000);
+        assert_eq!(to_fixed_point(0.1 + 0.2), 30_000_000);
+        assert_eq!(to_fixed_point(-1.0), -100_000_000);
+    }
+
+    #[test]
+    fn test_exact_amounts() {
+        for amount in [0.0, 10.0, 10.5, 0.3, 0.00000001, -1.0, 21_000_000.12345678] {
+            assert!(is_exact_amount(amount), "{}", amount);
+        }
+        for amount in [
+            f64::NAN,
+            f64::INFINITY,
+            0.1 + 0.2,
+            0.000000001,
+            10.123456789,
+            1e300,
+        ] {
+            assert!(!is_exact_amount(amount), "{}", amount);
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut writer = CanonicalWriter::new();
+        writer.string("ab").u32(7).amount(1.0);
+
+        assert_eq!(
+            writer.finish(),
+            [0, 0, 0, 2, b'a', b'b', 0, 0, 0, 7, 0, 0, 0, 0, 5, 245, 225, 0]
+        );
+    }
+}
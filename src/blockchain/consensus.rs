@@ -0,0 +1,89 @@
+//! How blocks are sealed: by proof of work, or, on a permissioned network,
+//! by the signature of one of a fixed set of authorities.
+
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::block::Block;
+use super::crypto::{Address, PublicKeyHex, SignatureScheme, TransactionSignature};
+
+/// Signature of a block by the authority that sealed it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockSignature {
+    /// Public key of the authority
+    pub public_key: PublicKeyHex,
+    /// Ed25519 signature of the block hash
+    pub signature: TransactionSignature,
+}
+
+impl BlockSignature {
+    /// Signs a block hash with the given key
+    pub fn sign(hash: &str, key: &SigningKey) -> Self {
+        Self {
+            public_key: PublicKeyHex(hex::encode(key.verifying_key().to_bytes())),
+            signature: TransactionSignature(hex::encode(key.sign(hash.as_bytes()).to_bytes())),
+        }
+    }
+
+    /// Returns true if this is a valid signature of the block hash
+    pub fn verifies(&self, hash: &str) -> bool {
+        self.public_key
+            .verify_signature(SignatureScheme::Ed25519, hash.as_bytes(), &self.signature)
+            .unwrap_or(false)
+    }
+}
+
+/// How a chain decides who may append blocks
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ConsensusMode {
+    /// Anyone may append a block whose hash meets the difficulty
+    #[default]
+    ProofOfWork,
+    /// Only the listed authorities may append blocks, which they sign instead
+    /// of mining; blocks are not held to any difficulty
+    ProofOfAuthority { authorities: Vec<Address> },
+}
+
+impl ConsensusMode {
+    /// Returns true if blocks are sealed by an authority rather than mined
+    pub fn is_proof_of_authority(&self) -> bool {
+        matches!(self, Self::ProofOfAuthority { .. })
+    }
+
+    /// Checks that the block is sealed the way this mode requires, explaining
+    /// why it isn't. Proof of work is checked along with the rest of the block,
+    /// so only authority signatures are checked here. The genesis block isn't
+    /// sealed.
+    pub fn check_seal(&self, block: &Block) -> Result<(), String> {
+        let Self::ProofOfAuthority { authorities } = self else {
+            return Ok(());
+        };
+        if block.index == 0 {
+            return Ok(());
+        }
+
+        let Some(seal) = &block.block_signature else {
+            return Err(format!(
+                "block {} is not signed by an authority",
+                block.index
+            ));
+        };
+        if !seal.verifies(&block.hash) {
+            return Err(format!("block {} has an invalid signature", block.index));
+        }
+        let is_authority = authorities.iter().any(|authority| {
+            seal.public_key
+                .verify_address(SignatureScheme::Ed25519, authority)
+                .unwrap_or(false)
+        });
+        if !is_authority {
+            return Err(format!(
+                "block {} is signed by {}, which is not an authority",
+                block.index, seal.public_key.0
+            ));
+        }
+        Ok(())
+    }
+}
@@ -1,24 +1,289 @@
 // NOTE: This file contains only the types needed for the blockchain to verify transactions.
 // The actual wallet functionality (key generation, signing) will be implemented in a separate application.
 
-use ed25519_dalek::{Signature, VerifyingKey as PublicKey};
+use ed25519_dalek::{Signature, VerifyingKey as PublicKey, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::fmt;
+use std::str::FromStr;
 use thiserror::Error;
 use utoipa::ToSchema;
 
 /// Errors that can occur when working with cryptographic functionality
-#[derive(Debug, Error)]
+#[derive(Debug, Error, PartialEq)]
 pub enum CryptoError {
     #[error("Invalid key format: {0}")]
     InvalidKeyFormat(String),
+
+    #[error("Signature scheme {0} is not supported by this build")]
+    UnsupportedScheme(SignatureScheme),
+}
+
+/// Length in bytes of a compressed secp256k1 public key
+pub const SECP256K1_PUBLIC_KEY_LENGTH: usize = 33;
+
+/// Signature scheme a transaction is signed with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SignatureScheme {
+    /// Ed25519 signatures over 32-byte public keys
+    #[default]
+    Ed25519,
+    /// ECDSA secp256k1 signatures over 33-byte compressed public keys
+    /// (requires the `secp256k1` feature)
+    Secp256k1,
+}
+
+impl SignatureScheme {
+    /// Returns the name of the scheme as used on the wire
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ed25519 => "ed25519",
+            Self::Secp256k1 => "secp256k1",
+        }
+    }
+}
+
+impl fmt::Display for SignatureScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Hash function used for block hashes, transaction hashes and addresses
+pub const HASH_ALGORITHM: &str = "sha256";
+
+/// How addresses are derived: the first 20 bytes of the SHA-256 hash of an
+/// ed25519 public key, as bare hex or behind a network prefix
+pub const ADDRESS_SCHEME: &str = "ed25519-sha256-160";
+
+/// Number of bytes in an address (truncated public key hash)
+pub const ADDRESS_BYTES: usize = 20;
+
+/// Number of hex characters in an address (20 bytes of the public key hash)
+pub const ADDRESS_HEX_LENGTH: usize = ADDRESS_BYTES * 2;
+
+/// Number of checksum bytes at the end of a versioned address
+pub const ADDRESS_CHECKSUM_BYTES: usize = 4;
+
+/// Number of hex characters after the prefix of a versioned address: the
+/// version byte, the public key hash and the checksum
+pub const VERSIONED_ADDRESS_HEX_LENGTH: usize = (1 + ADDRESS_BYTES + ADDRESS_CHECKSUM_BYTES) * 2;
+
+/// Network a versioned address belongs to, so that an address of one can't
+/// be used on the other by mistake
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    /// Every network, in version byte order
+    pub const ALL: [Network; 2] = [Network::Mainnet, Network::Testnet];
+
+    /// Human-readable prefix of the network's addresses, followed by `1`
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Self::Mainnet => "fch",
+            Self::Testnet => "tfch",
+        }
+    }
+
+    /// Version byte encoded in the network's addresses
+    pub fn version(self) -> u8 {
+        match self {
+            Self::Mainnet => 0x00,
+            Self::Testnet => 0x6f,
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mainnet => write!(f, "mainnet"),
+            Self::Testnet => write!(f, "testnet"),
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|network| network.to_string() == s)
+            .ok_or_else(|| format!("unknown network '{}'", s))
+    }
+}
+
+/// Checksum of a versioned address: the first bytes of the SHA-256 of its
+/// prefix, version byte and public key hash
+fn address_checksum(network: Network, hash: &[u8]) -> [u8; ADDRESS_CHECKSUM_BYTES] {
+    let mut hasher = Sha256::new();
+    hasher.update(network.prefix().as_bytes());
+    hasher.update([network.version()]);
+    hasher.update(hash);
+    let digest = hasher.finalize();
+
+    let mut checksum = [0u8; ADDRESS_CHECKSUM_BYTES];
+    checksum.copy_from_slice(&digest[..ADDRESS_CHECKSUM_BYTES]);
+    checksum
+}
+
+/// Decodes a hex string into exactly `N` bytes.
+///
+/// The length is checked before decoding, so oversized or truncated input is
+/// rejected up front with a clear error instead of after allocating.
+pub fn decode_fixed<const N: usize>(s: &str) -> Result<[u8; N], CryptoError> {
+    if s.len() != N * 2 {
+        return Err(CryptoError::InvalidKeyFormat(format!(
+            "Expected {} hex characters ({} bytes), got {}",
+            N * 2,
+            N,
+            s.len()
+        )));
+    }
+
+    let mut bytes = [0u8; N];
+    hex::decode_to_slice(s, &mut bytes)
+        .map_err(|e| CryptoError::InvalidKeyFormat(e.to_string()))?;
+    Ok(bytes)
+}
+
+/// Reasons an address can be rejected
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AddressError {
+    #[error("Address is empty")]
+    Empty,
+
+    #[error("Address must be {expected} characters long, got {actual}")]
+    InvalidLength { expected: usize, actual: usize },
+
+    #[error("Address contains non-hex character '{0}'")]
+    InvalidHex(char),
+
+    #[error("Address version byte {version:#04x} doesn't match its {prefix} prefix")]
+    VersionMismatch { prefix: String, version: u8 },
+
+    #[error("Address checksum doesn't match")]
+    BadChecksum,
+
+    #[error("Address is for {actual}, expected {expected}")]
+    WrongNetwork { expected: Network, actual: Network },
 }
 
-/// Represents a blockchain address (hash of a public key)
+/// Represents a blockchain address (hash of a public key).
+///
+/// A legacy address is the hex of the public key hash. A versioned address,
+/// e.g. `fch1...`, starts with its network's prefix and `1`, followed by the
+/// hex of the network's version byte, the public key hash and a checksum.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct Address(pub String);
 
+impl Address {
+    /// Checks that the address is well formed, reporting why it isn't.
+    /// Versioned addresses of any network are accepted.
+    pub fn validate(&self) -> Result<(), AddressError> {
+        self.decode().map(|_| ())
+    }
+
+    /// Same as [`Address::validate`], also rejecting versioned addresses of
+    /// another network. Legacy addresses carry no network and are accepted.
+    pub fn validate_for(&self, network: Network) -> Result<(), AddressError> {
+        match self.decode()? {
+            (Some(actual), _) if actual != network => Err(AddressError::WrongNetwork {
+                expected: network,
+                actual,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns true if the address is well formed
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Network of a versioned address; `None` for legacy addresses
+    pub fn network(&self) -> Option<Network> {
+        self.decode().ok().and_then(|(network, _)| network)
+    }
+
+    /// Decodes the address into the public key hash it stands for
+    pub fn to_bytes(&self) -> Result<[u8; ADDRESS_BYTES], CryptoError> {
+        self.decode()
+            .map(|(_, hash)| hash)
+            .map_err(|e| CryptoError::InvalidKeyFormat(e.to_string()))
+    }
+
+    /// Encodes a public key hash as a versioned address of the network
+    pub fn versioned(network: Network, hash: &[u8; ADDRESS_BYTES]) -> Self {
+        let mut payload = Vec::with_capacity(1 + ADDRESS_BYTES + ADDRESS_CHECKSUM_BYTES);
+        payload.push(network.version());
+        payload.extend_from_slice(hash);
+        payload.extend_from_slice(&address_checksum(network, hash));
+        Address(format!("{}1{}", network.prefix(), hex::encode(payload)))
+    }
+
+    /// Splits the address into its network, if it is versioned, and the
+    /// public key hash, checking the version byte and checksum on the way
+    fn decode(&self) -> Result<(Option<Network>, [u8; ADDRESS_BYTES]), AddressError> {
+        if self.0.is_empty() {
+            return Err(AddressError::Empty);
+        }
+
+        let versioned = Network::ALL.into_iter().find_map(|network| {
+            self.0
+                .strip_prefix(network.prefix())
+                .and_then(|rest| rest.strip_prefix('1'))
+                .map(|payload| (network, payload))
+        });
+        let Some((network, payload)) = versioned else {
+            return decode_address_hex::<ADDRESS_BYTES>(&self.0).map(|hash| (None, hash));
+        };
+
+        let payload =
+            decode_address_hex::<{ 1 + ADDRESS_BYTES + ADDRESS_CHECKSUM_BYTES }>(payload)?;
+        if payload[0] != network.version() {
+            return Err(AddressError::VersionMismatch {
+                prefix: network.prefix().to_string(),
+                version: payload[0],
+            });
+        }
+        let mut hash = [0u8; ADDRESS_BYTES];
+        hash.copy_from_slice(&payload[1..=ADDRESS_BYTES]);
+        if payload[1 + ADDRESS_BYTES..] != address_checksum(network, &hash) {
+            return Err(AddressError::BadChecksum);
+        }
+        Ok((Some(network), hash))
+    }
+}
+
+/// Decodes the hex part of an address into exactly `N` bytes
+fn decode_address_hex<const N: usize>(s: &str) -> Result<[u8; N], AddressError> {
+    if let Some(c) = s.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(AddressError::InvalidHex(c));
+    }
+    if s.len() != N * 2 {
+        return Err(AddressError::InvalidLength {
+            expected: N * 2,
+            actual: s.len(),
+        });
+    }
+
+    let mut bytes = [0u8; N];
+    hex::decode_to_slice(s, &mut bytes).map_err(|_| AddressError::InvalidLength {
+        expected: N * 2,
+        actual: s.len(),
+    })?;
+    Ok(bytes)
+}
+
 impl fmt::Display for Address {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -26,75 +291,354 @@ impl fmt::Display for Address {
 }
 
 /// Represents a public key in the blockchain
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct PublicKeyHex(pub String);
 
 impl PublicKeyHex {
     /// Converts the hex string to ed25519 public key
     pub fn to_ed25519_public_key(&self) -> Result<PublicKey, CryptoError> {
-        let public_bytes =
-            hex::decode(&self.0).map_err(|e| CryptoError::InvalidKeyFormat(e.to_string()))?;
-
-        let public_key_bytes: [u8; 32] = public_bytes
-            .try_into()
-            .map_err(|_| CryptoError::InvalidKeyFormat("Invalid public key length".to_string()))?;
+        let public_key_bytes = decode_fixed::<PUBLIC_KEY_LENGTH>(&self.0)?;
 
         PublicKey::from_bytes(&public_key_bytes)
             .map_err(|e| CryptoError::InvalidKeyFormat(e.to_string()))
     }
 
-    /// Derives an address from this public key (hash of the public key)
+    /// Derives a versioned address of the default network from this ed25519
+    /// public key
     pub fn to_address(&self) -> Result<Address, CryptoError> {
-        let public_bytes =
-            hex::decode(&self.0).map_err(|e| CryptoError::InvalidKeyFormat(e.to_string()))?;
+        self.to_address_for(SignatureScheme::Ed25519)
+    }
+
+    /// Derives a versioned address of the default network from this public
+    /// key of the given scheme
+    pub fn to_address_for(&self, scheme: SignatureScheme) -> Result<Address, CryptoError> {
+        self.to_versioned_address(scheme, Network::default())
+    }
+
+    /// Derives a legacy address, the bare hex of the key hash, from this
+    /// public key of the given scheme
+    pub fn to_legacy_address(&self, scheme: SignatureScheme) -> Result<Address, CryptoError> {
+        Ok(Address(hex::encode(self.key_hash(scheme)?)))
+    }
+
+    /// Derives a versioned address of the network from this public key of the given scheme
+    pub fn to_versioned_address(
+        &self,
+        scheme: SignatureScheme,
+        network: Network,
+    ) -> Result<Address, CryptoError> {
+        Ok(Address::versioned(network, &self.key_hash(scheme)?))
+    }
+
+    /// Derives an address from this public key of the given scheme in the
+    /// format of `address`: versioned for the same network, or legacy
+    pub fn to_address_like(
+        &self,
+        scheme: SignatureScheme,
+        address: &Address,
+    ) -> Result<Address, CryptoError> {
+        match address.network() {
+            Some(network) => self.to_versioned_address(scheme, network),
+            None => self.to_legacy_address(scheme),
+        }
+    }
+
+    /// Hashes this public key of the given scheme into the bytes an address stands for
+    fn key_hash(&self, scheme: SignatureScheme) -> Result<[u8; ADDRESS_BYTES], CryptoError> {
+        let public_bytes = match scheme {
+            SignatureScheme::Ed25519 => decode_fixed::<PUBLIC_KEY_LENGTH>(&self.0)?.to_vec(),
+            SignatureScheme::Secp256k1 => {
+                decode_fixed::<SECP256K1_PUBLIC_KEY_LENGTH>(&self.0)?.to_vec()
+            }
+        };
 
         // Hash the public key using SHA-256
         let mut hasher = Sha256::new();
-        hasher.update(&public_bytes);
+        hasher.update(public_bytes);
         let hash = hasher.finalize();
 
         // Take the first 20 bytes of the hash (similar to Bitcoin's RIPEMD160 after SHA256)
         // In a real implementation, you might want to use RIPEMD160 after SHA256
-        let address = hex::encode(&hash[..20]);
+        let mut key_hash = [0u8; ADDRESS_BYTES];
+        key_hash.copy_from_slice(&hash[..ADDRESS_BYTES]);
 
-        Ok(Address(address))
+        Ok(key_hash)
     }
 
-    /// Verifies a signature against a message using this public key
+    /// Verifies a signature against a message using this public key and the given scheme
     pub fn verify_signature(
         &self,
+        scheme: SignatureScheme,
         message: &[u8],
         signature: &TransactionSignature,
     ) -> Result<bool, CryptoError> {
-        let ed25519_public_key = self.to_ed25519_public_key()?;
-        let ed25519_signature = signature.to_ed25519_signature()?;
+        match scheme {
+            SignatureScheme::Ed25519 => {
+                let ed25519_public_key = self.to_ed25519_public_key()?;
+                let ed25519_signature = signature.to_ed25519_signature()?;
 
-        match ed25519_public_key.verify_strict(message, &ed25519_signature) {
-            Ok(_) => Ok(true),
-            Err(_) => Ok(false),
+                match ed25519_public_key.verify_strict(message, &ed25519_signature) {
+                    Ok(_) => Ok(true),
+                    Err(_) => Ok(false),
+                }
+            }
+            SignatureScheme::Secp256k1 => self.verify_secp256k1_signature(message, signature),
         }
     }
 
-    /// Verifies that an address was derived from this public key
-    pub fn verify_address(&self, address: &Address) -> Result<bool, CryptoError> {
-        let derived_address = self.to_address()?;
-        Ok(derived_address.0 == address.0)
+    #[cfg(feature = "secp256k1")]
+    fn verify_secp256k1_signature(
+        &self,
+        message: &[u8],
+        signature: &TransactionSignature,
+    ) -> Result<bool, CryptoError> {
+        use k256::ecdsa::signature::Verifier;
+
+        let public_key_bytes = decode_fixed::<SECP256K1_PUBLIC_KEY_LENGTH>(&self.0)?;
+        let public_key = k256::ecdsa::VerifyingKey::from_sec1_bytes(&public_key_bytes)
+            .map_err(|e| CryptoError::InvalidKeyFormat(e.to_string()))?;
+        let signature_bytes = decode_fixed::<SIGNATURE_LENGTH>(&signature.0)?;
+        let signature = k256::ecdsa::Signature::from_slice(&signature_bytes)
+            .map_err(|e| CryptoError::InvalidKeyFormat(e.to_string()))?;
+
+        Ok(public_key.verify(message, &signature).is_ok())
+    }
+
+    #[cfg(not(feature = "secp256k1"))]
+    fn verify_secp256k1_signature(
+        &self,
+        _message: &[u8],
+        _signature: &TransactionSignature,
+    ) -> Result<bool, CryptoError> {
+        Err(CryptoError::UnsupportedScheme(SignatureScheme::Secp256k1))
+    }
+
+    /// Verifies that an address, legacy or versioned, was derived from this
+    /// public key of the given scheme
+    pub fn verify_address(
+        &self,
+        scheme: SignatureScheme,
+        address: &Address,
+    ) -> Result<bool, CryptoError> {
+        let derived_address = self.to_address_like(scheme, address)?;
+        Ok(constant_time_eq(
+            derived_address.0.as_bytes(),
+            address.0.as_bytes(),
+        ))
     }
 }
 
 /// Represents a digital signature for a transaction
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct TransactionSignature(pub String);
 
 impl TransactionSignature {
     /// Converts the signature to ed25519 signature
     pub fn to_ed25519_signature(&self) -> Result<Signature, CryptoError> {
-        let bytes =
-            hex::decode(&self.0).map_err(|e| CryptoError::InvalidKeyFormat(e.to_string()))?;
+        let bytes = decode_fixed::<SIGNATURE_LENGTH>(&self.0)?;
+        Ok(Signature::from_bytes(&bytes))
+    }
+}
 
-        let signature = Signature::try_from(bytes.as_slice())
-            .map_err(|e| CryptoError::InvalidKeyFormat(e.to_string()))?;
+/// Compares two byte strings in time that depends only on their lengths, not
+/// on where they first differ. Use it wherever an attacker can submit guesses,
+/// e.g. for tokens, addresses and hashes.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        // Equal lengths
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"Secret"));
+        assert!(constant_time_eq(b"", b""));
+
+        // Differing lengths, including a prefix of the other
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"secret2", b"secret"));
+        assert!(!constant_time_eq(b"", b"secret"));
+    }
+
+    #[test]
+    fn test_valid_address() {
+        let address = Address("0123456789abcdef0123456789ABCDEF01234567".to_string());
+        assert_eq!(address.validate(), Ok(()));
+        assert!(address.is_valid());
+    }
+
+    #[test]
+    fn test_derived_address_is_valid() {
+        let public_key = PublicKeyHex(hex::encode([7u8; 32]));
+        assert!(public_key.to_address().unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_versioned_address_is_bound_to_its_network() {
+        let public_key = PublicKeyHex(hex::encode([7u8; 32]));
+        let mainnet = public_key
+            .to_versioned_address(SignatureScheme::Ed25519, Network::Mainnet)
+            .unwrap();
+        let testnet = public_key
+            .to_versioned_address(SignatureScheme::Ed25519, Network::Testnet)
+            .unwrap();
+
+        assert!(mainnet.0.starts_with("fch1"));
+        assert!(testnet.0.starts_with("tfch1"));
+        assert!(mainnet.is_valid() && testnet.is_valid());
+        assert_eq!(mainnet.validate_for(Network::Mainnet), Ok(()));
+        assert_eq!(
+            mainnet.validate_for(Network::Testnet),
+            Err(AddressError::WrongNetwork {
+                expected: Network::Testnet,
+                actual: Network::Mainnet
+            })
+        );
+        assert_eq!(testnet.validate_for(Network::Testnet), Ok(()));
+        assert_eq!(
+            testnet.validate_for(Network::Mainnet),
+            Err(AddressError::WrongNetwork {
+                expected: Network::Mainnet,
+                actual: Network::Testnet
+            })
+        );
+
+        // Both encode the same key hash as the legacy address
+        assert_eq!(public_key.to_address().unwrap(), mainnet);
+        let legacy = public_key
+            .to_legacy_address(SignatureScheme::Ed25519)
+            .unwrap();
+        assert_eq!(mainnet.to_bytes().unwrap(), legacy.to_bytes().unwrap());
+        assert_eq!(testnet.to_bytes().unwrap(), legacy.to_bytes().unwrap());
+        assert_eq!(legacy.validate_for(Network::Testnet), Ok(()));
+        assert!(public_key
+            .verify_address(SignatureScheme::Ed25519, &mainnet)
+            .unwrap());
+        assert!(!PublicKeyHex(hex::encode([8u8; 32]))
+            .verify_address(SignatureScheme::Ed25519, &mainnet)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_versioned_address_errors() {
+        let address = Address::versioned(Network::Mainnet, &[1u8; ADDRESS_BYTES]);
+
+        let mut tampered = address.0.clone();
+        tampered.replace_range(tampered.len() - 1.., "0");
+        if tampered == address.0 {
+            tampered.replace_range(tampered.len() - 1.., "1");
+        }
+        assert_eq!(Address(tampered).validate(), Err(AddressError::BadChecksum));
+
+        // A testnet payload behind the mainnet prefix
+        let testnet = Address::versioned(Network::Testnet, &[1u8; ADDRESS_BYTES]);
+        let relabelled = testnet.0.replacen("tfch1", "fch1", 1);
+        assert_eq!(
+            Address(relabelled).validate(),
+            Err(AddressError::VersionMismatch {
+                prefix: "fch".to_string(),
+                version: 0x6f
+            })
+        );
+
+        assert_eq!(
+            Address("fch1abcd".to_string()).validate(),
+            Err(AddressError::InvalidLength {
+                expected: VERSIONED_ADDRESS_HEX_LENGTH,
+                actual: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_address_error_variants() {
+        assert_eq!(Address(String::new()).validate(), Err(AddressError::Empty));
+        assert_eq!(
+            Address("abc123".to_string()).validate(),
+            Err(AddressError::InvalidLength {
+                expected: ADDRESS_HEX_LENGTH,
+                actual: 6
+            })
+        );
+        assert_eq!(
+            Address("system".to_string()).validate(),
+            Err(AddressError::InvalidHex('s'))
+        );
+        assert_eq!(
+            Address(format!("{}xyz", "0".repeat(37))).validate(),
+            Err(AddressError::InvalidHex('x'))
+        );
+        assert!(!Address("system".to_string()).is_valid());
+    }
+
+    #[test]
+    fn test_decode_fixed() {
+        assert_eq!(decode_fixed::<2>("0aff").unwrap(), [0x0a, 0xff]);
+        assert!(matches!(
+            decode_fixed::<2>("0af"),
+            Err(CryptoError::InvalidKeyFormat(_))
+        ));
+        assert!(matches!(
+            decode_fixed::<2>("0aff00"),
+            Err(CryptoError::InvalidKeyFormat(_))
+        ));
+        assert!(matches!(
+            decode_fixed::<2>("zzzz"),
+            Err(CryptoError::InvalidKeyFormat(_))
+        ));
+        // Multi-byte characters must not be mistaken for hex digits
+        assert!(matches!(
+            decode_fixed::<2>("éé"),
+            Err(CryptoError::InvalidKeyFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_malformed_hex_is_rejected_by_every_entry_point() {
+        let inputs = [
+            String::new(),
+            "abc".to_string(),
+            "zz".repeat(32),
+            "0".repeat(63),
+            "0".repeat(129),
+            "g".repeat(128),
+        ];
+
+        for input in &inputs {
+            let public_key = PublicKeyHex(input.clone());
+            assert!(public_key.to_ed25519_public_key().is_err(), "{}", input);
+            assert!(public_key.to_address().is_err(), "{}", input);
+            assert!(public_key
+                .verify_address(SignatureScheme::Ed25519, &Address("0".repeat(40)))
+                .is_err());
+
+            let signature = TransactionSignature(input.clone());
+            assert!(signature.to_ed25519_signature().is_err(), "{}", input);
+
+            assert!(Address(input.clone()).to_bytes().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_well_formed_hex_decodes() {
+        let public_key = PublicKeyHex(hex::encode([7u8; 32]));
+        let address = public_key
+            .to_legacy_address(SignatureScheme::Ed25519)
+            .unwrap();
+        assert_eq!(hex::encode(address.to_bytes().unwrap()), address.0);
+        let versioned = public_key.to_address().unwrap();
+        assert_eq!(versioned.to_bytes().unwrap(), address.to_bytes().unwrap());
 
-        Ok(signature)
+        let signature = TransactionSignature("ab".repeat(64));
+        assert!(signature.to_ed25519_signature().is_ok());
     }
 }
@@ -0,0 +1,31 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+use super::block::Block;
+use super::transaction::Transaction;
+
+/// Number of events buffered for slow subscribers before they start missing some
+pub const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Change to the pool of pending transactions
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MempoolEvent {
+    /// A transaction entered the pending pool
+    Added { transaction: Transaction },
+    /// Transactions left the pending pool, e.g. because they were mined
+    Removed { hashes: Vec<String> },
+    /// A pending transaction was revalidated and is announced again
+    Rebroadcast { transaction: Transaction },
+}
+
+/// Creates the channel mempool events are broadcast on
+pub fn mempool_channel() -> broadcast::Sender<MempoolEvent> {
+    broadcast::channel(EVENT_CHANNEL_CAPACITY).0
+}
+
+/// Creates the channel newly appended blocks are broadcast on
+pub fn block_channel() -> broadcast::Sender<Block> {
+    broadcast::channel(EVENT_CHANNEL_CAPACITY).0
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::DateTime;
+
+use super::block::{Block, TransactionCommitment, GENESIS_TIMESTAMP};
+use super::chain::BlockchainError;
+use super::crypto::Address;
+use super::transaction::Transaction;
+
+/// Initial balances credited in the genesis block, by address.
+///
+/// A sorted map keeps the order of the genesis transactions, and therefore
+/// the genesis hash, the same on every node.
+pub type GenesisAllocations = BTreeMap<String, f64>;
+
+/// Reads genesis allocations from a JSON file mapping addresses to amounts,
+/// e.g. `{"1111…": 1000.0}`
+pub fn read_allocations(path: impl AsRef<Path>) -> Result<GenesisAllocations, BlockchainError> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        BlockchainError::InvalidConfig(format!(
+            "Failed to read genesis allocations {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    serde_json::from_str(&contents).map_err(|e| {
+        BlockchainError::InvalidConfig(format!(
+            "Invalid genesis allocations {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Allocations a genesis block pays out, the inverse of [`genesis_block`]
+pub fn allocations_of(genesis: &Block) -> GenesisAllocations {
+    genesis
+        .transactions
+        .iter()
+        .map(|tx| (tx.recipient.0.clone(), tx.amount))
+        .collect()
+}
+
+/// Creates a genesis block paying out the given allocations as system transactions
+pub fn genesis_block(
+    difficulty: usize,
+    allocations: &GenesisAllocations,
+) -> Result<Block, BlockchainError> {
+    let timestamp =
+        DateTime::from_timestamp(GENESIS_TIMESTAMP, 0).expect("genesis timestamp is in range");
+
+    let mut transactions = Vec::with_capacity(allocations.len());
+    for (address, &amount) in allocations {
+        if !Address(address.clone()).is_valid() || !amount.is_finite() || amount <= 0.0 {
+            return Err(BlockchainError::InvalidConfig(format!(
+                "invalid genesis allocation of {} to {}",
+                amount, address
+            )));
+        }
+
+        // Fix the timestamp so that every node derives the same transaction hash
+        let mut transaction = Transaction::new(
+            Address("system".to_string()),
+            Address(address.clone()),
+            amount,
+        );
+        transaction.timestamp = timestamp;
+        transaction.hash = transaction.calculate_hash();
+        transactions.push(transaction);
+    }
+
+    Ok(Block::new_with_timestamp(
+        0,
+        transactions,
+        "0".repeat(64),
+        difficulty,
+        timestamp,
+        0,
+        TransactionCommitment::Legacy,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::test_utils::{MINER, OTHER_MINER};
+    use crate::blockchain::Blockchain;
+
+    #[test]
+    fn test_allocations_give_deterministic_genesis() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("genesis.json");
+        std::fs::write(
+            &path,
+            format!(r#"{{"{}": 1000.0, "{}": 250.5}}"#, OTHER_MINER, MINER),
+        )
+        .unwrap();
+
+        let first =
+            Blockchain::try_new_with_allocations(1, 50.0, &read_allocations(&path).unwrap())
+                .unwrap();
+        let second =
+            Blockchain::try_new_with_allocations(1, 50.0, &read_allocations(&path).unwrap())
+                .unwrap();
+
+        assert_eq!(first.chain[0].hash, second.chain[0].hash);
+        assert_ne!(first.chain[0].hash, Block::genesis(1).hash);
+        assert!(first.is_chain_valid().unwrap());
+        assert_eq!(first.get_balance(MINER), 250.5);
+        assert_eq!(first.get_balance(OTHER_MINER), 1000.0);
+        assert_eq!(first.total_supply(), 1250.5);
+    }
+
+    #[test]
+    fn test_rejects_invalid_allocation() {
+        let allocations = GenesisAllocations::from([(MINER.to_string(), -5.0)]);
+        assert!(matches!(
+            genesis_block(1, &allocations),
+            Err(BlockchainError::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use super::block::{BlockHeader, TransactionCommitment};
+use super::transaction::Transaction;
+
+/// Merkle root of a block without transactions
+pub const EMPTY_MERKLE_ROOT: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Side of the path a sibling node sits on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MerkleSide {
+    /// The sibling is hashed before the running node
+    Left,
+    /// The sibling is hashed after the running node
+    Right,
+}
+
+/// One level of a Merkle branch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MerkleStep {
+    /// Hex encoded sibling node
+    pub hash: String,
+    /// Which side the sibling is on
+    pub side: MerkleSide,
+}
+
+/// Everything a light client needs to check that a transaction is confirmed
+/// without downloading its block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InclusionProof {
+    /// The confirmed transaction
+    pub transaction: Transaction,
+    /// Header of the block containing it
+    pub header: BlockHeader,
+    /// Siblings from the transaction's leaf up to the header's Merkle root
+    pub branch: Vec<MerkleStep>,
+}
+
+impl InclusionProof {
+    /// Checks the proof the way a client would: the transaction matches its
+    /// hash, that hash folds into the header's Merkle root along the branch,
+    /// the header hashes to its stored hash, and that hash meets the proof of
+    /// work. Blocks committing to their transactions the legacy way can't be
+    /// proven, as their hash doesn't cover the Merkle root.
+    pub fn verify(&self) -> bool {
+        self.header.tx_commitment == TransactionCommitment::MerkleRoot
+            && self.header.verify_hash()
+            && self.transaction.hash == self.transaction.calculate_hash()
+            && verify_merkle_proof(
+                &self.transaction.hash,
+                &self.branch,
+                &self.header.merkle_root,
+            )
+            && self.header.verify_proof_of_work()
+    }
+}
+
+/// Leaf of a transaction hash: its bytes decoded from hex, or its raw bytes
+/// if it isn't valid hex
+fn leaf(hash: &str) -> Vec<u8> {
+    hex::decode(hash).unwrap_or_else(|_| hash.as_bytes().to_vec())
+}
+
+fn parent(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().to_vec()
+}
+
+fn next_level(level: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    level
+        .chunks(2)
+        .map(|pair| parent(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
+}
+
+/// Computes the Merkle root of the given transactions.
+///
+/// Leaves are the transaction hashes decoded from hex (or their raw bytes if
+/// they aren't valid hex). Each parent is the SHA-256 of its two children
+/// concatenated, and the last node of an odd level is paired with itself.
+pub fn merkle_root(transactions: &[Transaction]) -> String {
+    let mut level: Vec<Vec<u8>> = transactions.iter().map(|tx| leaf(&tx.hash)).collect();
+
+    if level.is_empty() {
+        return EMPTY_MERKLE_ROOT.to_string();
+    }
+
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+
+    hex::encode(&level[0])
+}
+
+/// Builds the branch proving that the transaction at `index` is part of
+/// [`merkle_root`] of `transactions`, or `None` if there is no such transaction
+pub fn merkle_proof(transactions: &[Transaction], mut index: usize) -> Option<Vec<MerkleStep>> {
+    if index >= transactions.len() {
+        return None;
+    }
+
+    let mut level: Vec<Vec<u8>> = transactions.iter().map(|tx| leaf(&tx.hash)).collect();
+    let mut branch = Vec::new();
+    while level.len() > 1 {
+        let (sibling, side) = if index.is_multiple_of(2) {
+            // The last node of an odd level is its own sibling
+            (
+                level.get(index + 1).unwrap_or(&level[index]),
+                MerkleSide::Right,
+            )
+        } else {
+            (&level[index - 1], MerkleSide::Left)
+        };
+        branch.push(MerkleStep {
+            hash: hex::encode(sibling),
+            side,
+        });
+        level = next_level(&level);
+        index /= 2;
+    }
+
+    Some(branch)
+}
+
+/// Checks that a transaction hash folds into `root` along `branch`
+pub fn verify_merkle_proof(hash: &str, branch: &[MerkleStep], root: &str) -> bool {
+    let mut node = leaf(hash);
+    for step in branch {
+        let Ok(sibling) = hex::decode(&step.hash) else {
+            return false;
+        };
+        node = match step.side {
+            MerkleSide::Left => parent(&sibling, &node),
+            MerkleSide::Right => parent(&node, &sibling),
+        };
+    }
+    hex::encode(node) == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::Address;
+
+    fn transaction(amount: f64) -> Transaction {
+        Transaction::new(
+            Address("system".to_string()),
+            Address("recipient".to_string()),
+            amount,
+        )
+    }
+
+    #[test]
+    fn test_merkle_root() {
+        let a = transaction(1.0);
+        let b = transaction(2.0);
+        let c = transaction(3.0);
+        let node = |left: &[u8], right: &[u8]| Sha256::digest([left, right].concat()).to_vec();
+        let leaf = |tx: &Transaction| hex::decode(&tx.hash).unwrap();
+
+        assert_eq!(merkle_root(&[]), EMPTY_MERKLE_ROOT);
+        assert_eq!(merkle_root(std::slice::from_ref(&a)), a.hash);
+
+        let ab = node(&leaf(&a), &leaf(&b));
+        let cc = node(&leaf(&c), &leaf(&c));
+        assert_eq!(
+            merkle_root(&[a.clone(), b.clone(), c.clone()]),
+            hex::encode(node(&ab, &cc))
+        );
+
+        // Reordering transactions changes the root
+        assert_ne!(merkle_root(&[b, a, c.clone()]), hex::encode(node(&ab, &cc)));
+    }
+
+    #[test]
+    fn test_merkle_proof_of_every_leaf() {
+        let transactions: Vec<Transaction> = (1..=5).map(|i| transaction(i as f64)).collect();
+        let root = merkle_root(&transactions);
+
+        for (index, tx) in transactions.iter().enumerate() {
+            let branch = merkle_proof(&transactions, index).unwrap();
+            assert_eq!(branch.len(), 3);
+            assert!(verify_merkle_proof(&tx.hash, &branch, &root));
+            let other = &transactions[(index + 1) % transactions.len()];
+            assert!(!verify_merkle_proof(&other.hash, &branch, &root));
+        }
+        assert_eq!(merkle_proof(&transactions, 5), None);
+
+        // A single transaction is its own root
+        let single = std::slice::from_ref(&transactions[0]);
+        assert_eq!(merkle_proof(single, 0), Some(Vec::new()));
+    }
+}
@@ -1,9 +1,37 @@
+pub mod amount;
 pub mod block;
+pub mod canonical;
 pub mod chain;
+pub mod consensus;
 pub mod crypto;
+pub mod events;
+pub mod genesis;
+pub mod merkle;
+pub mod persistence;
+pub mod pruning;
+pub mod reward;
+pub mod stats;
+pub mod store;
 pub mod transaction;
 
-pub use block::Block;
-pub use chain::{create_shared_blockchain, BlockchainError, SharedBlockchain};
-pub use crypto::Address;
-pub use transaction::Transaction;
+#[cfg(test)]
+pub(crate) mod test_utils;
+
+pub use block::{Block, BlockHeader, TransactionCommitment, MAX_DIFFICULTY};
+pub use chain::{
+    create_shared_blockchain, mine_shared, mine_shared_cancellable, mine_shared_split,
+    mine_shared_split_cancellable, AddressHistoryEntry, BlockValidationIssue,
+    BlockValidationResult, Blockchain, BlockchainError, CompetingTip, CounterpartyFlow,
+    ExclusionReason, MiningCancellation, NonceGap, NonceState, SharedBlockchain,
+    ValidationIssueKind, PROTOCOL_VERSION,
+};
+pub use consensus::{BlockSignature, ConsensusMode};
+pub use crypto::{Address, Network};
+pub use events::MempoolEvent;
+pub use genesis::GenesisAllocations;
+pub use merkle::{InclusionProof, MerkleSide, MerkleStep};
+pub use persistence::BlockLog;
+pub use reward::RewardRecipient;
+pub use stats::BlockTimeStats;
+pub use store::{ChainStore, MemoryStore};
+pub use transaction::{Transaction, TransactionError, TransactionOutput, TransactionStatus};
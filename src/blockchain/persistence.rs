@@ -0,0 +1,494 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::block::Block;
+use super::chain::BlockchainError;
+use super::store::ChainStore;
+
+/// Magic string identifying a block log header
+pub const LOG_FORMAT: &str = "fchain-block-log";
+
+/// Version of the block log format written by this release. Bump it when the
+/// layout of `Block` or `Transaction` changes, and upgrade entries written by
+/// older versions as they are read back, in `migrate_entry`.
+pub const LOG_VERSION: u32 = 1;
+
+/// First line of a block log, naming the format and its version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LogHeader {
+    format: String,
+    version: u32,
+}
+
+impl LogHeader {
+    fn current() -> Self {
+        Self {
+            format: LOG_FORMAT.to_string(),
+            version: LOG_VERSION,
+        }
+    }
+
+    /// Parses a header line, or returns `None` if the line is something else
+    fn parse(line: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(line)
+            .ok()
+            .filter(|header| header.format == LOG_FORMAT)
+    }
+}
+
+/// Fails unless entries of the given log version can be read, directly or
+/// through [`migrate_entry`]
+fn check_version(version: u32) -> Result<(), BlockchainError> {
+    if version == LOG_VERSION {
+        return Ok(());
+    }
+    Err(BlockchainError::ValidationFailed(format!(
+        "unsupported chain file version {}",
+        version
+    )))
+}
+
+/// Upgrades an entry written with an older log version to the current layout.
+///
+/// Only the current version exists so far, so there is nothing to migrate yet.
+fn migrate_entry(
+    version: u32,
+    entry: serde_json::Value,
+) -> Result<serde_json::Value, BlockchainError> {
+    check_version(version)?;
+    Ok(entry)
+}
+
+/// Append-only log of blocks, stored as one JSON-encoded block per line.
+///
+/// Every append is fsync'd before it returns, so a block that made it into
+/// the chain survives a crash or panic even without a clean shutdown.
+///
+/// The first line is a header naming the format version, checked before any
+/// block is decoded. Logs written before the header was introduced have no
+/// header and are read as version 1, whose layout they share.
+#[derive(Debug, Clone)]
+pub struct BlockLog {
+    path: PathBuf,
+}
+
+impl BlockLog {
+    /// Creates a handle to the block log at the given path
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Returns the path of the log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns true if the log file exists and contains data
+    pub fn exists(&self) -> bool {
+        self.path
+            .metadata()
+            .map(|meta| meta.len() > 0)
+            .unwrap_or(false)
+    }
+
+    /// Appends a block to the log and flushes it to disk
+    pub fn append(&self, block: &Block) -> Result<(), BlockchainError> {
+        let mut line = serde_json::to_string(block)
+            .map_err(|e| BlockchainError::Storage(format!("Failed to encode block: {}", e)))?;
+        line.push('\n');
+        if !self.exists() {
+            line.insert_str(0, &Self::header_line()?);
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| self.io_error("open", e))?;
+
+        file.write_all(line.as_bytes())
+            .map_err(|e| self.io_error("write", e))?;
+        file.sync_all().map_err(|e| self.io_error("sync", e))
+    }
+
+    /// Replaces the contents of the log with the given blocks
+    pub fn rewrite(&self, blocks: &[Block]) -> Result<(), BlockchainError> {
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = File::create(&tmp_path).map_err(|e| self.io_error("create", e))?;
+        file.write_all(Self::header_line()?.as_bytes())
+            .map_err(|e| self.io_error("write", e))?;
+
+        for block in blocks {
+            let line = serde_json::to_string(block)
+                .map_err(|e| BlockchainError::Storage(format!("Failed to encode block: {}", e)))?;
+            writeln!(file, "{}", line).map_err(|e| self.io_error("write", e))?;
+        }
+
+        file.sync_all().map_err(|e| self.io_error("sync", e))?;
+        std::fs::rename(&tmp_path, &self.path).map_err(|e| self.io_error("rename", e))
+    }
+
+    /// Reads all blocks from the log.
+    ///
+    /// A trailing line without a newline is the remains of an interrupted
+    /// write and is ignored; any other malformed line is an error.
+    pub fn read_all(&self) -> Result<Vec<Block>, BlockchainError> {
+        let mut blocks = Vec::new();
+        self.for_each_entry(|version, line_number, line| {
+            blocks.push(Self::decode(version, line_number, line)?);
+            Ok(true)
+        })?;
+        Ok(blocks)
+    }
+
+    /// Reads the block with the given index without loading the whole log.
+    ///
+    /// Blocks are logged in chain order starting from genesis, so the block
+    /// with index `n` is the `n`-th entry.
+    pub fn read_block(&self, index: u64) -> Result<Option<Block>, BlockchainError> {
+        let mut entry = 0;
+        let mut found = None;
+        self.for_each_entry(|version, line_number, line| {
+            if entry == index {
+                found = Some(Self::decode(version, line_number, line)?);
+                return Ok(false);
+            }
+            entry += 1;
+            Ok(true)
+        })?;
+        Ok(found.filter(|block| block.index == index))
+    }
+
+    /// Calls `visit` with the log version, line number and content of every
+    /// complete, non-empty entry until it returns `false`. Fails before
+    /// visiting anything if the header names an unsupported version.
+    fn for_each_entry(
+        &self,
+        mut visit: impl FnMut(u32, usize, &str) -> Result<bool, BlockchainError>,
+    ) -> Result<(), BlockchainError> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(self.io_error("open", e)),
+        };
+
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        let mut line_number = 0;
+        let mut version = None;
+
+        loop {
+            line.clear();
+            let read = reader
+                .read_line(&mut line)
+                .map_err(|e| self.io_error("read", e))?;
+            if read == 0 {
+                break;
+            }
+            line_number += 1;
+
+            if !line.ends_with('\n') {
+                log::warn!(
+                    "Ignoring incomplete trailing entry at line {} of {}",
+                    line_number,
+                    self.path.display()
+                );
+                break;
+            }
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let version = match version {
+                Some(version) => version,
+                None => {
+                    let header = LogHeader::parse(&line);
+                    let found = header.as_ref().map_or(LOG_VERSION, |h| h.version);
+                    check_version(found)?;
+                    version = Some(found);
+                    if header.is_some() {
+                        continue;
+                    }
+                    found
+                }
+            };
+
+            if !visit(version, line_number, &line)? {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn decode(version: u32, line_number: usize, line: &str) -> Result<Block, BlockchainError> {
+        let corrupt = |e: serde_json::Error| {
+            BlockchainError::Storage(format!(
+                "Corrupt block log entry at line {}: {}",
+                line_number, e
+            ))
+        };
+        let entry = serde_json::from_str(line).map_err(corrupt)?;
+        serde_json::from_value(migrate_entry(version, entry)?).map_err(corrupt)
+    }
+
+    fn header_line() -> Result<String, BlockchainError> {
+        let mut line = serde_json::to_string(&LogHeader::current())
+            .map_err(|e| BlockchainError::Storage(format!("Failed to encode header: {}", e)))?;
+        line.push('\n');
+        Ok(line)
+    }
+
+    fn io_error(&self, action: &str, err: std::io::Error) -> BlockchainError {
+        BlockchainError::Storage(format!(
+            "Failed to {} block log {}: {}",
+            action,
+            self.path.display(),
+            err
+        ))
+    }
+}
+
+/// The JSON file backend, keeping the blocks in the log
+impl ChainStore for BlockLog {
+    fn append_block(&self, block: &Block) -> Result<(), BlockchainError> {
+        self.append(block)
+    }
+
+    fn replace_blocks(&self, blocks: &[Block]) -> Result<(), BlockchainError> {
+        self.rewrite(blocks)
+    }
+
+    fn load_all(&self) -> Result<Vec<Block>, BlockchainError> {
+        self.read_all()
+    }
+
+    fn load_block(&self, index: u64) -> Result<Option<Block>, BlockchainError> {
+        self.read_block(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::crypto::{Address, Network};
+    use crate::blockchain::test_utils::{TestKeypair, MINER};
+    use crate::blockchain::{Blockchain, Transaction};
+    use std::io::Write;
+
+    #[test]
+    fn test_append_and_read_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = BlockLog::new(dir.path().join("blocks.log"));
+
+        let genesis = Block::genesis(1);
+        let mut block = Block::new(1, vec![], genesis.hash.clone(), 1);
+        block.mine();
+
+        log.append(&genesis).unwrap();
+        log.append(&block).unwrap();
+
+        let blocks = log.read_all().unwrap();
+        assert_eq!(blocks, vec![genesis, block]);
+    }
+
+    #[test]
+    fn test_read_single_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = BlockLog::new(dir.path().join("blocks.log"));
+
+        let genesis = Block::genesis(1);
+        let mut block = Block::new(1, vec![], genesis.hash.clone(), 1);
+        block.mine();
+        log.append(&genesis).unwrap();
+        log.append(&block).unwrap();
+
+        assert_eq!(log.read_block(1).unwrap(), Some(block));
+        assert_eq!(log.read_block(0).unwrap(), Some(genesis));
+        assert_eq!(log.read_block(2).unwrap(), None);
+    }
+
+    #[test]
+    fn test_log_starts_with_version_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = BlockLog::new(dir.path().join("blocks.log"));
+
+        log.append(&Block::genesis(1)).unwrap();
+
+        let contents = std::fs::read_to_string(log.path()).unwrap();
+        let header = contents.lines().next().unwrap();
+        assert_eq!(LogHeader::parse(header), Some(LogHeader::current()));
+    }
+
+    #[test]
+    fn test_unsupported_version_is_a_clear_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = BlockLog::new(dir.path().join("blocks.log"));
+        log.rewrite(&[Block::genesis(1)]).unwrap();
+
+        let contents = std::fs::read_to_string(log.path()).unwrap();
+        let contents =
+            contents.replacen(&format!("\"version\":{}", LOG_VERSION), "\"version\":99", 1);
+        std::fs::write(log.path(), contents).unwrap();
+
+        let err = Blockchain::recover_from_store(Box::new(log), 1, 50.0).unwrap_err();
+        assert!(matches!(
+            err,
+            BlockchainError::ValidationFailed(ref msg) if msg == "unsupported chain file version 99"
+        ));
+    }
+
+    #[test]
+    fn test_log_without_header_is_read_as_version_1() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = BlockLog::new(dir.path().join("blocks.log"));
+
+        let genesis = Block::genesis(1);
+        let line = serde_json::to_string(&genesis).unwrap();
+        std::fs::write(log.path(), format!("{}\n", line)).unwrap();
+
+        assert_eq!(log.read_all().unwrap(), vec![genesis.clone()]);
+        assert_eq!(log.read_block(0).unwrap(), Some(genesis));
+    }
+
+    #[test]
+    fn test_missing_log_reads_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = BlockLog::new(dir.path().join("missing.log"));
+
+        assert!(!log.exists());
+        assert!(log.read_all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_incomplete_trailing_entry_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = BlockLog::new(dir.path().join("blocks.log"));
+
+        let genesis = Block::genesis(1);
+        log.append(&genesis).unwrap();
+
+        // Simulate a crash in the middle of writing the next entry
+        let mut file = OpenOptions::new().append(true).open(log.path()).unwrap();
+        file.write_all(b"{\"index\":1,\"timest").unwrap();
+
+        let blocks = log.read_all().unwrap();
+        assert_eq!(blocks, vec![genesis]);
+    }
+
+    #[test]
+    fn test_recover_identical_chain_after_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.log");
+
+        let original = {
+            let mut blockchain = Blockchain::new(1, 50.0);
+            blockchain
+                .enable_store(Box::new(BlockLog::new(&path)))
+                .unwrap();
+
+            for i in 0..3 {
+                let tx = Transaction::new(
+                    Address("system".to_string()),
+                    Address(format!("recipient{}", i)),
+                    10.0,
+                );
+                blockchain.create_transaction(tx).unwrap();
+                blockchain.mine_pending_transactions(MINER).unwrap();
+            }
+
+            blockchain.chain.clone()
+            // The blockchain is dropped here without any explicit save
+        };
+
+        let recovered =
+            Blockchain::recover_from_store(Box::new(BlockLog::new(&path)), 1, 50.0).unwrap();
+
+        assert_eq!(recovered.chain, original);
+        assert!(recovered.is_chain_valid().unwrap());
+        assert_eq!(recovered.get_balance(MINER), 150.0);
+    }
+
+    #[test]
+    fn test_permissioned_node_recovers_gateway_transactions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.log");
+        let sender = TestKeypair::new(1);
+
+        let original = {
+            let mut blockchain = Blockchain::new(1, 50.0).with_permissioned(true);
+            blockchain
+                .enable_store(Box::new(BlockLog::new(&path)))
+                .unwrap();
+            blockchain
+                .mine_pending_transactions(&sender.address().0)
+                .unwrap();
+            // Vouched for by the gateway, so unsigned
+            let unsigned =
+                Transaction::new(sender.address(), Address("recipient".to_string()), 10.0)
+                    .with_nonce(0);
+            blockchain.create_gateway_transaction(unsigned).unwrap();
+            blockchain.mine_pending_transactions(MINER).unwrap();
+            blockchain.chain.clone()
+        };
+
+        // A public chain refuses the unsigned transaction
+        let result = Blockchain::recover_from_store(Box::new(BlockLog::new(&path)), 1, 50.0);
+        assert!(matches!(result, Err(BlockchainError::InvalidBlock(_))));
+
+        let mut recovered = Blockchain::new(1, 50.0).with_permissioned(true);
+        recovered
+            .recover_store(Box::new(BlockLog::new(&path)))
+            .unwrap();
+        assert_eq!(recovered.chain, original);
+        assert_eq!(recovered.get_balance("recipient"), 10.0);
+    }
+
+    #[test]
+    fn test_testnet_node_recovers_testnet_addresses() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.log");
+        let miner = Address::versioned(Network::Testnet, &[0x11; 20]);
+
+        let original = {
+            let mut blockchain = Blockchain::new(1, 50.0).with_network(Network::Testnet);
+            blockchain
+                .enable_store(Box::new(BlockLog::new(&path)))
+                .unwrap();
+            blockchain.mine_pending_transactions(&miner.0).unwrap();
+            blockchain.chain.clone()
+        };
+
+        // A mainnet chain refuses the testnet miner
+        let result = Blockchain::recover_from_store(Box::new(BlockLog::new(&path)), 1, 50.0);
+        assert!(matches!(result, Err(BlockchainError::InvalidBlock(_))));
+
+        let mut recovered = Blockchain::new(1, 50.0).with_network(Network::Testnet);
+        recovered
+            .recover_store(Box::new(BlockLog::new(&path)))
+            .unwrap();
+        assert_eq!(recovered.chain, original);
+        assert_eq!(recovered.get_balance(&miner.0), 50.0);
+    }
+
+    #[test]
+    fn test_recover_rejects_tampered_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = BlockLog::new(dir.path().join("blocks.log"));
+
+        let mut blockchain = Blockchain::new(1, 50.0);
+        blockchain.enable_store(Box::new(log.clone())).unwrap();
+        blockchain.mine_pending_transactions(MINER).unwrap();
+
+        let mut blocks = log.read_all().unwrap();
+        blocks[1].transactions[0].amount = 1_000_000.0;
+        log.rewrite(&blocks).unwrap();
+
+        let result = Blockchain::recover_from_store(Box::new(log), 1, 50.0);
+        assert!(matches!(result, Err(BlockchainError::InvalidBlock(_))));
+    }
+}
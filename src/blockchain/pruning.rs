@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use super::block::Block;
+
+/// What a pruned blockchain remembers about the blocks it dropped from memory.
+///
+/// The blocks themselves stay in the chain store and can be loaded on demand;
+/// this keeps just enough to validate the retained tail, answer balance
+/// queries and reject replayed transactions without reading them back.
+#[derive(Debug, Clone)]
+pub struct PrunedHistory {
+    /// The genesis block, kept so candidate chains can still be matched against it
+    pub genesis: Block,
+    /// Index of the newest pruned block
+    pub tip_index: u64,
+    /// Hash of the newest pruned block, which the first retained block links to
+    pub tip_hash: String,
+    /// Confirmed balance of every address that appears in a pruned block
+    pub balances: HashMap<String, f64>,
+    /// Coins issued by the pruned blocks, net of fees
+    pub supply: f64,
+    /// Index of the block containing each pruned transaction, by hash
+    pub transactions: HashMap<String, u64>,
+    /// Highest nonce each address used in a pruned block
+    pub nonces: HashMap<String, u64>,
+    /// What pruned time-locked transactions pay each recipient, with the
+    /// unlock time; dropped once the lock has expired
+    pub locked: Vec<(String, f64, DateTime<Utc>)>,
+    /// Combined work of the pruned blocks, see [`Block::work`]
+    pub work: u128,
+}
+
+impl PrunedHistory {
+    /// Starts a history from the genesis block, which is always pruned first
+    pub fn new(genesis: Block) -> Self {
+        let mut history = Self {
+            tip_index: genesis.index,
+            tip_hash: genesis.hash.clone(),
+            genesis: genesis.clone(),
+            balances: HashMap::new(),
+            supply: 0.0,
+            transactions: HashMap::new(),
+            nonces: HashMap::new(),
+            locked: Vec::new(),
+            work: genesis.work(),
+        };
+        history.record_transactions(&genesis);
+        history
+    }
+
+    /// Folds the next pruned block into the history
+    pub fn absorb(&mut self, block: &Block) {
+        self.tip_index = block.index;
+        self.tip_hash = block.hash.clone();
+        self.work = self.work.saturating_add(block.work());
+        self.record_transactions(block);
+        let now = Utc::now();
+        self.locked
+            .retain(|(_, _, locked_until)| now < *locked_until);
+    }
+
+    /// Confirmed balance of an address across the pruned blocks
+    pub fn balance(&self, address: &str) -> f64 {
+        self.balances.get(address).copied().unwrap_or(0.0)
+    }
+
+    /// What pruned transactions pay an address under a lock that hasn't
+    /// expired at `now`
+    pub fn locked_at(&self, address: &str, now: DateTime<Utc>) -> f64 {
+        self.locked
+            .iter()
+            .filter(|(recipient, _, locked_until)| recipient == address && now < *locked_until)
+            .map(|(_, amount, _)| amount)
+            .sum()
+    }
+
+    fn record_transactions(&mut self, block: &Block) {
+        for tx in &block.transactions {
+            for (recipient, amount) in tx.credits() {
+                *self.balances.entry(recipient.0.clone()).or_default() += amount;
+            }
+            *self.balances.entry(tx.sender.0.clone()).or_default() -= tx.amount + tx.fee;
+            self.supply += if tx.sender.0 == "system" {
+                tx.amount
+            } else {
+                -tx.fee
+            };
+            if let Some(locked_until) = tx.locked_until {
+                for (recipient, amount) in tx.credits() {
+                    self.locked
+                        .push((recipient.0.clone(), amount, locked_until));
+                }
+            }
+            self.transactions.insert(tx.hash.clone(), block.index);
+            if let Some(nonce) = tx.nonce {
+                let highest = self.nonces.entry(tx.sender.0.clone()).or_insert(nonce);
+                *highest = (*highest).max(nonce);
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Splitting a block's reward across several addresses, e.g. the payout
+//! addresses of a mining pool.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::canonical::{from_fixed_point, to_fixed_point};
+use super::crypto::Address;
+use super::transaction::TransactionError;
+
+/// An address paid a share of the block reward in proportion to its weight
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RewardRecipient {
+    /// Address credited
+    pub address: Address,
+    /// Share of the reward relative to the other recipients' weights
+    #[schema(minimum = 1)]
+    pub weight: u64,
+}
+
+impl RewardRecipient {
+    /// Creates a recipient with the given weight
+    pub fn new(address: impl Into<String>, weight: u64) -> Self {
+        Self {
+            address: Address(address.into()),
+            weight,
+        }
+    }
+}
+
+/// Divides `total` among the recipients in proportion to their weights.
+///
+/// Shares are rounded down to the smallest amount unit and whatever rounding
+/// leaves over goes to the first recipient, so the shares add up to `total`.
+/// Recipients whose share rounds down to nothing are left out. Every share is
+/// a whole number of units, see [`super::canonical::is_exact_amount`].
+pub fn split_reward(
+    total: f64,
+    recipients: &[RewardRecipient],
+) -> Result<Vec<(Address, f64)>, TransactionError> {
+    let Some((first, rest)) = recipients.split_first() else {
+        return Err(TransactionError::InvalidRewardSplit(
+            "no reward recipients".to_string(),
+        ));
+    };
+    if let Some(recipient) = recipients.iter().find(|recipient| recipient.weight == 0) {
+        return Err(TransactionError::InvalidRewardSplit(format!(
+            "{} has a weight of 0",
+            recipient.address
+        )));
+    }
+    if let Some((index, recipient)) = recipients.iter().enumerate().find(|(index, recipient)| {
+        recipients[..*index]
+            .iter()
+            .any(|r| r.address == recipient.address)
+    }) {
+        return Err(TransactionError::InvalidRewardSplit(format!(
+            "{} is listed more than once (recipient {})",
+            recipient.address, index
+        )));
+    }
+
+    let units = i128::from(to_fixed_point(total));
+    let total_weight: i128 = recipients.iter().map(|r| i128::from(r.weight)).sum();
+
+    let shares: Vec<(Address, i128)> = rest
+        .iter()
+        .map(|recipient| {
+            let share = units * i128::from(recipient.weight) / total_weight;
+            (recipient.address.clone(), share)
+        })
+        .filter(|(_, share)| *share > 0)
+        .collect();
+    let first_share = units - shares.iter().map(|(_, share)| share).sum::<i128>();
+
+    Ok(std::iter::once((first.address.clone(), first_share))
+        .chain(shares)
+        .map(|(address, share)| (address, from_fixed_point(share as i64)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::test_utils::{MINER, OTHER_MINER};
+
+    #[test]
+    fn test_split_follows_the_weights_and_adds_up() {
+        let recipients = [
+            RewardRecipient::new(MINER, 1),
+            RewardRecipient::new(OTHER_MINER, 2),
+            RewardRecipient::new("3333333333333333333333333333333333333333", 3),
+        ];
+
+        let shares = split_reward(100.0, &recipients).unwrap();
+
+        let units: Vec<i64> = shares
+            .iter()
+            .map(|(_, amount)| to_fixed_point(*amount))
+            .collect();
+        // 100 / 6 doesn't divide evenly; the first recipient gets the remainder
+        assert_eq!(units, vec![1_666_666_667, 3_333_333_333, 5_000_000_000]);
+        assert_eq!(units.iter().sum::<i64>(), to_fixed_point(100.0));
+        assert_eq!(shares[1].0 .0, OTHER_MINER);
+    }
+
+    #[test]
+    fn test_split_rejects_bad_recipients() {
+        assert!(split_reward(50.0, &[]).is_err());
+        assert!(split_reward(50.0, &[RewardRecipient::new(MINER, 0)]).is_err());
+        assert!(split_reward(
+            50.0,
+            &[
+                RewardRecipient::new(MINER, 1),
+                RewardRecipient::new(MINER, 2)
+            ]
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Summary statistics over a series of block intervals (in seconds)
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTimeStats {
+    /// Interval between each block and its predecessor, oldest first
+    pub intervals: Vec<i64>,
+    /// Shortest interval
+    pub min: Option<i64>,
+    /// Longest interval
+    pub max: Option<i64>,
+    /// Average interval
+    pub mean: Option<f64>,
+    /// Median interval
+    pub median: Option<f64>,
+}
+
+impl BlockTimeStats {
+    /// Computes the statistics for the given intervals.
+    /// All aggregates are `None` when there are no intervals.
+    pub fn from_intervals(intervals: Vec<i64>) -> Self {
+        if intervals.is_empty() {
+            return Self {
+                intervals,
+                min: None,
+                max: None,
+                mean: None,
+                median: None,
+            };
+        }
+
+        let mut sorted = intervals.clone();
+        sorted.sort_unstable();
+
+        let len = sorted.len();
+        let median = if len.is_multiple_of(2) {
+            (sorted[len / 2 - 1] + sorted[len / 2]) as f64 / 2.0
+        } else {
+            sorted[len / 2] as f64
+        };
+        let mean = sorted.iter().sum::<i64>() as f64 / len as f64;
+
+        Self {
+            min: sorted.first().copied(),
+            max: sorted.last().copied(),
+            mean: Some(mean),
+            median: Some(median),
+            intervals,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_for_odd_number_of_intervals() {
+        let stats = BlockTimeStats::from_intervals(vec![10, 30, 20]);
+
+        assert_eq!(stats.intervals, vec![10, 30, 20]);
+        assert_eq!(stats.min, Some(10));
+        assert_eq!(stats.max, Some(30));
+        assert_eq!(stats.mean, Some(20.0));
+        assert_eq!(stats.median, Some(20.0));
+    }
+
+    #[test]
+    fn test_stats_for_even_number_of_intervals() {
+        let stats = BlockTimeStats::from_intervals(vec![5, 1, 10, 4]);
+
+        assert_eq!(stats.median, Some(4.5));
+        assert_eq!(stats.mean, Some(5.0));
+    }
+
+    #[test]
+    fn test_stats_for_no_intervals() {
+        let stats = BlockTimeStats::from_intervals(vec![]);
+
+        assert!(stats.intervals.is_empty());
+        assert_eq!(stats.min, None);
+        assert_eq!(stats.mean, None);
+        assert_eq!(stats.median, None);
+    }
+}
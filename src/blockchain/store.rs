@@ -0,0 +1,147 @@
+//! Storage backends a [`Blockchain`](super::Blockchain) persists its blocks to.
+//!
+//! A chain without a store keeps everything in memory and loses it on exit.
+//! With one, every appended block is written to the store before it becomes
+//! part of the chain, and the chain can be rebuilt from the store on startup.
+//! [`BlockLog`](super::BlockLog) keeps the blocks in a JSON lines file;
+//! [`MemoryStore`] keeps them in memory, trading durability for speed.
+
+use std::fmt::Debug;
+use std::sync::Mutex;
+
+use super::block::Block;
+use super::chain::BlockchainError;
+
+/// Where a chain's blocks are persisted
+pub trait ChainStore: Debug + Send + Sync {
+    /// Appends a block after the last stored one
+    fn append_block(&self, block: &Block) -> Result<(), BlockchainError>;
+
+    /// Replaces every stored block with the given ones
+    fn replace_blocks(&self, blocks: &[Block]) -> Result<(), BlockchainError>;
+
+    /// Loads every stored block, in chain order
+    fn load_all(&self) -> Result<Vec<Block>, BlockchainError>;
+
+    /// Loads the block with the given index, if it is stored
+    fn load_block(&self, index: u64) -> Result<Option<Block>, BlockchainError> {
+        Ok(self
+            .load_all()?
+            .into_iter()
+            .find(|block| block.index == index))
+    }
+}
+
+/// Store that keeps the blocks in memory, e.g. for tests or throwaway nodes
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    blocks: Mutex<Vec<Block>>,
+}
+
+impl MemoryStore {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ChainStore for MemoryStore {
+    fn append_block(&self, block: &Block) -> Result<(), BlockchainError> {
+        self.blocks.lock().unwrap().push(block.clone());
+        Ok(())
+    }
+
+    fn replace_blocks(&self, blocks: &[Block]) -> Result<(), BlockchainError> {
+        *self.blocks.lock().unwrap() = blocks.to_vec();
+        Ok(())
+    }
+
+    fn load_all(&self) -> Result<Vec<Block>, BlockchainError> {
+        Ok(self.blocks.lock().unwrap().clone())
+    }
+
+    fn load_block(&self, index: u64) -> Result<Option<Block>, BlockchainError> {
+        let blocks = self.blocks.lock().unwrap();
+        Ok(usize::try_from(index)
+            .ok()
+            .and_then(|i| blocks.get(i))
+            .filter(|block| block.index == index)
+            .cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::test_utils::{TestKeypair, MINER};
+    use crate::blockchain::{BlockLog, Blockchain};
+
+    /// Runs the same mining, transfers and pruning against a store and
+    /// returns the resulting chain with the blocks stored
+    fn exercise(store: Box<dyn ChainStore>) -> (Blockchain, Vec<Block>) {
+        let sender = TestKeypair::new(1);
+        let mut blockchain = Blockchain::new(1, 50.0);
+        blockchain.enable_store(store).unwrap();
+        let mut blockchain = blockchain.with_pruning(2).unwrap();
+
+        blockchain
+            .mine_pending_transactions(&sender.address().0)
+            .unwrap();
+        for nonce in 0..3 {
+            let transfer = sender.transfer_with_nonce("recipient", 5.0, 0.5, nonce);
+            blockchain.create_transaction(transfer).unwrap();
+            blockchain.mine_pending_transactions(MINER).unwrap();
+        }
+
+        let blocks = blockchain.store.as_ref().unwrap().load_all().unwrap();
+        (blockchain, blocks)
+    }
+
+    #[test]
+    fn test_memory_and_file_backends_agree() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let (in_memory, memory_blocks) = exercise(Box::new(MemoryStore::new()));
+        let (on_disk, file_blocks) =
+            exercise(Box::new(BlockLog::new(dir.path().join("blocks.log"))));
+
+        // Block hashes differ with the timestamps, so compare the content
+        let summary = |blocks: &[Block]| -> Vec<(u64, usize)> {
+            blocks
+                .iter()
+                .map(|block| (block.index, block.transactions.len()))
+                .collect()
+        };
+        assert_eq!(memory_blocks.len(), 5);
+        assert_eq!(summary(&memory_blocks), summary(&file_blocks));
+
+        for address in ["recipient", MINER, &TestKeypair::new(1).address().0] {
+            assert_eq!(in_memory.get_balance(address), on_disk.get_balance(address));
+        }
+        assert_eq!(in_memory.chain_length(), on_disk.chain_length());
+        // Pruned blocks are read back from either store
+        let amounts = |blockchain: &Blockchain| -> Vec<f64> {
+            let block = blockchain.get_block_by_index(1).unwrap();
+            block.transactions.iter().map(|tx| tx.amount).collect()
+        };
+        assert_eq!(amounts(&in_memory), amounts(&on_disk));
+    }
+
+    #[test]
+    fn test_recover_from_memory_store() {
+        let mut blockchain = Blockchain::new(1, 50.0);
+        blockchain
+            .enable_store(Box::new(MemoryStore::new()))
+            .unwrap();
+        blockchain.mine_pending_transactions(MINER).unwrap();
+        let store = MemoryStore::new();
+        store
+            .replace_blocks(&blockchain.store.as_ref().unwrap().load_all().unwrap())
+            .unwrap();
+
+        let recovered = Blockchain::recover_from_store(Box::new(store), 1, 50.0).unwrap();
+
+        assert_eq!(recovered.chain, blockchain.chain);
+        assert_eq!(recovered.get_balance(MINER), 50.0);
+    }
+}
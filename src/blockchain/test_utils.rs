@@ -0,0 +1,87 @@
+use ed25519_dalek::{Signer, SigningKey};
+
+use super::crypto::{Address, PublicKeyHex, TransactionSignature};
+use super::transaction::{Transaction, TransactionOutput};
+
+/// Well-formed address used as the reward address when mining in tests
+pub const MINER: &str = "1111111111111111111111111111111111111111";
+
+/// A second well-formed reward address, e.g. for a competing miner
+pub const OTHER_MINER: &str = "2222222222222222222222222222222222222222";
+
+/// Deterministic key pair for signing transactions in tests
+pub struct TestKeypair {
+    signing_key: SigningKey,
+}
+
+impl TestKeypair {
+    /// Creates a key pair derived from a single seed byte
+    pub fn new(seed: u8) -> Self {
+        Self {
+            signing_key: SigningKey::from_bytes(&[seed; 32]),
+        }
+    }
+
+    /// Returns the signing key, e.g. to seal proof-of-authority blocks
+    pub fn signing_key(&self) -> SigningKey {
+        self.signing_key.clone()
+    }
+
+    /// Returns the hex encoded public key
+    pub fn public_key(&self) -> PublicKeyHex {
+        PublicKeyHex(hex::encode(self.signing_key.verifying_key().to_bytes()))
+    }
+
+    /// Returns the address derived from the public key
+    pub fn address(&self) -> Address {
+        self.public_key().to_address().unwrap()
+    }
+
+    /// Signs an arbitrary message
+    pub fn sign_message(&self, message: &[u8]) -> TransactionSignature {
+        TransactionSignature(hex::encode(self.signing_key.sign(message).to_bytes()))
+    }
+
+    /// Signs the transaction and attaches the public key
+    pub fn sign(&self, transaction: &mut Transaction) {
+        transaction.signature = Some(self.sign_message(transaction.hash.as_bytes()));
+        transaction.public_key = Some(self.public_key());
+    }
+
+    /// Creates a signed transaction from this key pair's address
+    pub fn transfer(&self, recipient: &str, amount: f64, fee: f64) -> Transaction {
+        let mut transaction =
+            Transaction::new_with_fee(self.address(), Address(recipient.to_string()), amount, fee);
+        self.sign(&mut transaction);
+        transaction
+    }
+
+    /// Creates a signed transaction carrying the given nonce
+    pub fn transfer_with_nonce(
+        &self,
+        recipient: &str,
+        amount: f64,
+        fee: f64,
+        nonce: u64,
+    ) -> Transaction {
+        let mut transaction =
+            Transaction::new_with_fee(self.address(), Address(recipient.to_string()), amount, fee)
+                .with_nonce(nonce);
+        self.sign(&mut transaction);
+        transaction
+    }
+
+    /// Creates a signed multi-output transaction from this key pair's address
+    pub fn pay_many(&self, outputs: &[(&str, f64)], fee: f64) -> Transaction {
+        let outputs = outputs
+            .iter()
+            .map(|(address, amount)| TransactionOutput {
+                address: Address(address.to_string()),
+                amount: *amount,
+            })
+            .collect();
+        let mut transaction = Transaction::new_with_outputs(self.address(), outputs, fee);
+        self.sign(&mut transaction);
+        transaction
+    }
+}
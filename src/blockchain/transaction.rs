@@ -1,18 +1,210 @@
+use std::time::Duration;
+
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use sha2::{Digest, Sha256};
+use thiserror::Error;
 use utoipa::ToSchema;
 
-use super::crypto::{Address, PublicKeyHex, TransactionSignature};
+use super::block::is_within_future_drift;
+use super::canonical::{is_exact_amount, to_fixed_point, CanonicalWriter};
+use super::crypto::{
+    constant_time_eq, Address, AddressError, CryptoError, Network, PublicKeyHex, SignatureScheme,
+    TransactionSignature,
+};
+
+/// Reasons a transaction can be rejected, either by [`Transaction::validate`]
+/// or when it is submitted to a [`Blockchain`](super::Blockchain).
+///
+/// Every variant has a stable machine-readable [`code`](TransactionError::code)
+/// that the API returns alongside the message.
+#[derive(Debug, Error, PartialEq)]
+pub enum TransactionError {
+    #[error("amount must be positive, got {0}")]
+    InvalidAmount(f64),
+
+    #[error("fee must be a non-negative number, got {0}")]
+    InvalidFee(f64),
+
+    #[error("sender and recipient are required")]
+    MissingAddress,
+
+    #[error("hash mismatch: expected {expected}, got {actual}")]
+    HashMismatch { expected: String, actual: String },
+
+    #[error("recipient and amount must match the outputs")]
+    OutputMismatch,
+
+    #[error("system transactions can't carry a fee")]
+    SystemFee,
+
+    #[error("missing signature")]
+    MissingSignature,
+
+    #[error("missing public key")]
+    MissingPublicKey,
+
+    #[error("address is not derived from the public key")]
+    AddressMismatch,
+
+    #[error("signature verification failed")]
+    InvalidSignature,
+
+    #[error("timestamp {0} is too far in the future")]
+    FutureTimestamp(DateTime<Utc>),
+
+    #[error(transparent)]
+    Crypto(#[from] CryptoError),
+
+    #[error("invalid miner address")]
+    InvalidMinerAddress,
+
+    #[error("address {address} is for {actual}, this node is on {expected}")]
+    WrongNetwork {
+        address: Address,
+        expected: Network,
+        actual: Network,
+    },
+
+    #[error("invalid reward split: {0}")]
+    InvalidRewardSplit(String),
+
+    #[error("Amount {amount} is not a multiple of the minimum unit {granularity}")]
+    NotGranular { amount: f64, granularity: f64 },
+
+    #[error("Insufficient balance: {address} has only {balance} coins")]
+    InsufficientBalance { address: Address, balance: f64 },
+
+    #[error("Transaction {0} already exists")]
+    Duplicate(String),
+
+    #[error("Nonce {nonce} was already used by {sender}")]
+    NonceUsed { nonce: u64, sender: String },
+
+    #[error("Nonce {nonce} is already held for {sender} in the orphan pool")]
+    NonceHeldAsOrphan { nonce: u64, sender: String },
+
+    #[error("Replacing pending transaction {hash} requires a fee above {fee}")]
+    ReplacementFeeTooLow { hash: String, fee: f64 },
+
+    #[error("created more than {ttl_secs} seconds ago, so it has already expired")]
+    Expired { ttl_secs: u64 },
+
+    #[error("a memo requires a nonce")]
+    MemoWithoutNonce,
+
+    #[error("memo is {0} bytes, more than the maximum of {MAX_MEMO_BYTES}")]
+    MemoTooLong(usize),
+
+    #[error("zero-amount transactions are not accepted by this node")]
+    ZeroAmountNotAllowed,
+
+    #[error("a time lock requires a nonce")]
+    LockWithoutNonce,
+
+    #[error("unsigned transactions require a nonce")]
+    MissingNonce,
+}
+
+impl TransactionError {
+    /// Stable machine-readable identifier of the error
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidAmount(_) => "amount_out_of_range",
+            Self::InvalidFee(_) => "fee_out_of_range",
+            Self::NotGranular { .. } => "amount_not_granular",
+            Self::MissingAddress | Self::InvalidMinerAddress => "invalid_address",
+            Self::WrongNetwork { .. } => "wrong_network",
+            Self::InvalidRewardSplit(_) => "invalid_reward_split",
+            Self::HashMismatch { .. } => "hash_mismatch",
+            Self::OutputMismatch => "output_mismatch",
+            Self::SystemFee => "system_fee",
+            Self::MissingSignature => "missing_signature",
+            Self::MissingPublicKey => "missing_public_key",
+            Self::AddressMismatch => "address_mismatch",
+            Self::InvalidSignature => "bad_signature",
+            Self::FutureTimestamp(_) => "future_timestamp",
+            Self::Crypto(CryptoError::InvalidKeyFormat(_)) => "invalid_key_format",
+            Self::Crypto(CryptoError::UnsupportedScheme(_)) => "unsupported_scheme",
+            Self::InsufficientBalance { .. } => "insufficient_balance",
+            Self::Duplicate(_) => "duplicate_transaction",
+            Self::NonceUsed { .. } => "nonce_used",
+            Self::NonceHeldAsOrphan { .. } => "nonce_held_as_orphan",
+            Self::ReplacementFeeTooLow { .. } => "replacement_fee_too_low",
+            Self::Expired { .. } => "expired",
+            Self::MemoWithoutNonce => "memo_without_nonce",
+            Self::MemoTooLong(_) => "memo_too_long",
+            Self::ZeroAmountNotAllowed => "zero_amount_not_allowed",
+            Self::LockWithoutNonce => "lock_without_nonce",
+            Self::MissingNonce => "missing_nonce",
+        }
+    }
+}
+
+/// Maximum size in bytes of a transaction memo
+pub const MAX_MEMO_BYTES: usize = 256;
+
+/// One recipient of a multi-output transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionOutput {
+    /// Address credited
+    pub address: Address,
+    /// Amount credited to it
+    #[cfg_attr(not(feature = "string-amounts"), schema(exclusive_minimum = 0))]
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(schema_with = crate::blockchain::amount::positive_schema)
+    )]
+    pub amount: f64,
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+/// A transfer between addresses, or a coinbase paying out a block reward and fees.
+///
+/// Serialized to JSON with an extra `isCoinbase` flag, so clients don't have
+/// to recognize the `system` sender. Binary formats such as bincode keep the
+/// plain field layout, and the flag is ignored when deserializing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(remote = "Self", rename_all = "camelCase")]
 pub struct Transaction {
     /// Sender's address (hash of public key)
     pub sender: Address,
     /// Recipient's address
     pub recipient: Address,
     /// Amount being transferred
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(value_type = String)
+    )]
     pub amount: f64,
+    /// Fee paid to the miner that includes the transaction in a block
+    #[serde(default)]
+    #[cfg_attr(
+        feature = "string-amounts",
+        serde(with = "crate::blockchain::amount"),
+        schema(value_type = String)
+    )]
+    pub fee: f64,
+    /// Recipients of a multi-output transaction, credited atomically. When
+    /// present, `recipient` is the first output's address and `amount` is the
+    /// total of all outputs.
+    #[serde(default)]
+    pub outputs: Vec<TransactionOutput>,
+    /// Position of the transaction among its sender's, counting from 0.
+    /// Transactions created before nonces existed have none.
+    #[serde(default)]
+    pub nonce: Option<u64>,
+    /// Data anchored on-chain with the transaction, at most [`MAX_MEMO_BYTES`].
+    /// A transaction with a memo may move an amount of 0.
+    #[serde(default)]
+    #[schema(max_length = 256)]
+    pub memo: Option<String>,
+    /// Time until which the recipients can't spend what the transaction pays
+    /// them, e.g. for an escrow. They are credited with it all the same.
+    #[serde(default)]
+    pub locked_until: Option<DateTime<Utc>>,
     /// Timestamp when the transaction was created
     pub timestamp: DateTime<Utc>,
     /// Transaction hash
@@ -21,123 +213,398 @@ pub struct Transaction {
     pub signature: Option<TransactionSignature>,
     /// Full public key of the sender (required when spending)
     pub public_key: Option<PublicKeyHex>,
+    /// Scheme the transaction is signed with
+    #[serde(default)]
+    pub sig_scheme: SignatureScheme,
+}
+
+/// JSON form of a [`Transaction`], flagging coinbase transactions
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionJson<'a> {
+    #[serde(flatten, serialize_with = "serialize_fields")]
+    transaction: &'a Transaction,
+    is_coinbase: bool,
+}
+
+fn serialize_fields<S: Serializer>(
+    transaction: &&Transaction,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    Transaction::serialize(transaction, serializer)
+}
+
+impl Serialize for Transaction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            TransactionJson {
+                transaction: self,
+                is_coinbase: self.is_coinbase(),
+            }
+            .serialize(serializer)
+        } else {
+            Transaction::serialize(self, serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Transaction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Transaction::deserialize(deserializer)
+    }
+}
+
+/// Where a transaction is in its lifecycle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum TransactionStatus {
+    /// Waiting in the pending pool to be mined
+    Pending,
+    /// Included in a block of the chain
+    #[serde(rename_all = "camelCase")]
+    Confirmed {
+        /// Index of the block containing the transaction
+        block_index: u64,
+        /// Number of blocks from the containing block to the tip, inclusive
+        confirmations: u64,
+    },
+    /// Held in the orphan pool until the sender's funds confirm
+    Orphaned,
+    /// Neither pending nor confirmed
+    Unknown,
 }
 
 impl Transaction {
     /// Creates a new transaction
     pub fn new(sender: Address, recipient: Address, amount: f64) -> Self {
+        Self::new_with_fee(sender, recipient, amount, 0.0)
+    }
+
+    /// Creates a new transaction that pays a fee to the miner
+    pub fn new_with_fee(sender: Address, recipient: Address, amount: f64, fee: f64) -> Self {
         let timestamp = Utc::now();
         let mut transaction = Self {
             sender,
             recipient,
             amount,
+            fee,
+            outputs: Vec::new(),
+            nonce: None,
+            memo: None,
+            locked_until: None,
             timestamp,
             hash: String::new(),
             signature: None,
             public_key: None,
+            sig_scheme: SignatureScheme::default(),
         };
 
         transaction.hash = transaction.calculate_hash();
         transaction
     }
 
-    /// Calculates the hash of the transaction
+    /// Creates a transaction paying several recipients at once. The sender is
+    /// debited the total of the outputs plus the fee, or nothing at all.
+    pub fn new_with_outputs(sender: Address, outputs: Vec<TransactionOutput>, fee: f64) -> Self {
+        let recipient = outputs
+            .first()
+            .map_or_else(|| Address(String::new()), |output| output.address.clone());
+        let amount = outputs.iter().map(|output| output.amount).sum();
+
+        let mut transaction = Self::new_with_fee(sender, recipient, amount, fee);
+        transaction.outputs = outputs;
+        transaction.hash = transaction.calculate_hash();
+        transaction
+    }
+
+    /// Sets the sender's nonce, re-hashing the transaction. Call it before signing.
+    pub fn with_nonce(mut self, nonce: u64) -> Self {
+        self.nonce = Some(nonce);
+        self.hash = self.calculate_hash();
+        self
+    }
+
+    /// Attaches a memo, re-hashing the transaction. Call it before signing;
+    /// the transaction also needs a nonce.
+    pub fn with_memo(mut self, memo: impl Into<String>) -> Self {
+        self.memo = Some(memo.into());
+        self.hash = self.calculate_hash();
+        self
+    }
+
+    /// Time-locks what the transaction pays until `locked_until`, re-hashing
+    /// the transaction. Call it before signing; the transaction also needs a nonce.
+    pub fn with_locked_until(mut self, locked_until: DateTime<Utc>) -> Self {
+        self.locked_until = Some(locked_until);
+        self.hash = self.calculate_hash();
+        self
+    }
+
+    /// Returns true if the recipients can't spend what the transaction pays
+    /// them yet at `now`
+    pub fn is_locked_at(&self, now: DateTime<Utc>) -> bool {
+        self.locked_until
+            .is_some_and(|locked_until| now < locked_until)
+    }
+
+    /// Returns true for a transaction minted by the system, such as a block's
+    /// reward, rather than signed by a sender
+    pub fn is_coinbase(&self) -> bool {
+        self.sender.0 == "system"
+    }
+
+    /// Returns true for a transaction that only anchors a memo and moves no
+    /// coins besides its fee
+    pub fn is_data_only(&self) -> bool {
+        self.amount == 0.0 && self.memo.is_some()
+    }
+
+    /// Addresses credited by the transaction and the amount each receives
+    pub fn credits(&self) -> Vec<(&Address, f64)> {
+        if self.outputs.is_empty() {
+            return vec![(&self.recipient, self.amount)];
+        }
+        self.outputs
+            .iter()
+            .map(|output| (&output.address, output.amount))
+            .collect()
+    }
+
+    /// Total amount the transaction credits to an address
+    pub fn amount_received_by(&self, address: &str) -> f64 {
+        self.credits()
+            .into_iter()
+            .filter(|(recipient, _)| recipient.0 == address)
+            .map(|(_, amount)| amount)
+            .sum()
+    }
+
+    /// Returns true if the address sends or receives in this transaction
+    pub fn involves(&self, address: &str) -> bool {
+        self.sender.0 == address
+            || self
+                .credits()
+                .iter()
+                .any(|(recipient, _)| recipient.0 == address)
+    }
+
+    /// Net effect of this transaction on the balance of an address: what it
+    /// receives, minus the amount and fee if it is the sender
+    pub fn balance_change(&self, address: &str) -> f64 {
+        let sent = if self.sender.0 == address {
+            self.amount + self.fee
+        } else {
+            0.0
+        };
+        self.amount_received_by(address) - sent
+    }
+
+    /// Calculates the hash of the transaction over its canonical encoding,
+    /// see [`crate::blockchain::canonical`] for the byte layout.
+    ///
+    /// The scheme is committed to so a signature can't be reinterpreted under another one.
     pub fn calculate_hash(&self) -> String {
-        let mut hasher = Sha256::new();
-        let data = format!(
-            "{}{}{}{}",
-            self.sender,
-            self.recipient,
-            self.amount,
-            self.timestamp.timestamp()
-        );
+        let mut preimage = CanonicalWriter::new();
+        preimage
+            .string(&self.sender.0)
+            .string(&self.recipient.0)
+            .amount(self.amount)
+            .amount(self.fee)
+            .timestamp(&self.timestamp)
+            .scheme(self.sig_scheme);
+        // Single-recipient transactions without a nonce keep the layout they always had
+        if !self.outputs.is_empty()
+            || self.nonce.is_some()
+            || self.memo.is_some()
+            || self.locked_until.is_some()
+        {
+            preimage.u32(self.outputs.len() as u32);
+            for output in &self.outputs {
+                preimage.string(&output.address.0).amount(output.amount);
+            }
+        }
+        if let Some(nonce) = self.nonce {
+            preimage.u64(nonce);
+        }
+        // A memo always follows a nonce, which keeps the layout unambiguous
+        if let Some(memo) = &self.memo {
+            preimage.string(memo);
+        }
+        // Tagged, so that it can't be mistaken for a memo
+        if let Some(locked_until) = &self.locked_until {
+            preimage.string("locked_until").timestamp(locked_until);
+        }
+
+        hex::encode(Sha256::digest(preimage.finish()))
+    }
+
+    /// Stable identifier of the transaction on the chain `chain_id`, used to
+    /// look it up and to detect conflicting transactions.
+    ///
+    /// A nonce-bearing transaction is identified by its chain, sender and
+    /// nonce, so two versions of it share an id but not a hash. Transactions
+    /// without a nonce, such as coinbases, are identified by their hash.
+    pub fn id(&self, chain_id: &str) -> String {
+        let Some(nonce) = self.nonce else {
+            return self.hash.clone();
+        };
+        let mut preimage = CanonicalWriter::new();
+        preimage.string(chain_id).string(&self.sender.0).u64(nonce);
+        hex::encode(Sha256::digest(preimage.finish()))
+    }
 
-        hasher.update(data.as_bytes());
-        let result = hasher.finalize();
-        hex::encode(result)
+    /// Checks that the transaction is stamped at most `max_future_drift` ahead of now
+    pub fn check_timestamp(&self, max_future_drift: Duration) -> Result<(), TransactionError> {
+        if is_within_future_drift(self.timestamp, max_future_drift) {
+            Ok(())
+        } else {
+            Err(TransactionError::FutureTimestamp(self.timestamp))
+        }
     }
 
-    /// Validates the transaction
+    /// Checks that no address of the transaction is a versioned address of
+    /// another network than `network`. Legacy addresses carry no network.
+    pub fn check_network(&self, network: Network) -> Result<(), TransactionError> {
+        let addresses = [&self.sender, &self.recipient]
+            .into_iter()
+            .chain(self.outputs.iter().map(|output| &output.address));
+        for address in addresses {
+            if let Err(AddressError::WrongNetwork { expected, actual }) =
+                address.validate_for(network)
+            {
+                return Err(TransactionError::WrongNetwork {
+                    address: address.clone(),
+                    expected,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the transaction passes [`Transaction::validate`]
     pub fn is_valid(&self) -> bool {
-        // Check if the amount is valid
-        if self.amount <= 0.0 {
-            println!("Transaction invalid: amount <= 0");
-            return false;
+        self.validate().is_ok()
+    }
+
+    /// Validates the transaction, reporting why it was rejected
+    pub fn validate(&self) -> Result<(), TransactionError> {
+        self.validate_contents()?;
+        self.validate_signature()
+    }
+
+    /// Validates the transaction as a permissioned chain does when
+    /// `permissioned` is set: a transaction without a signature was vouched
+    /// for by a trusted gateway, so it only needs a nonce for replay protection.
+    pub fn validate_in(&self, permissioned: bool) -> Result<(), TransactionError> {
+        if !permissioned || self.signature.is_some() || self.sender.0 == "system" {
+            return self.validate();
+        }
+        self.validate_contents()?;
+        if self.nonce.is_none() {
+            return Err(TransactionError::MissingNonce);
+        }
+        Ok(())
+    }
+
+    /// Checks everything but the signature
+    fn validate_contents(&self) -> Result<(), TransactionError> {
+        // Check if the amount is valid and exactly encodable; only data-only
+        // transactions may move nothing
+        if !is_exact_amount(self.amount) || (self.amount <= 0.0 && !self.is_data_only()) {
+            return Err(TransactionError::InvalidAmount(self.amount));
+        }
+
+        if let Some(memo) = &self.memo {
+            if self.nonce.is_none() {
+                return Err(TransactionError::MemoWithoutNonce);
+            }
+            if memo.len() > MAX_MEMO_BYTES {
+                return Err(TransactionError::MemoTooLong(memo.len()));
+            }
+        }
+        if self.locked_until.is_some() && self.nonce.is_none() {
+            return Err(TransactionError::LockWithoutNonce);
+        }
+
+        // Check if the fee is valid
+        if !is_exact_amount(self.fee) || self.fee < 0.0 {
+            return Err(TransactionError::InvalidFee(self.fee));
         }
 
         // Check if the addresses are valid
         if self.sender.0.is_empty() || self.recipient.0.is_empty() {
-            println!("Transaction invalid: empty sender or recipient");
-            return false;
+            return Err(TransactionError::MissingAddress);
+        }
+
+        // Check that every output is valid and that they add up to the amount
+        if !self.outputs.is_empty() {
+            for output in &self.outputs {
+                if !is_exact_amount(output.amount) || output.amount <= 0.0 {
+                    return Err(TransactionError::InvalidAmount(output.amount));
+                }
+                if output.address.0.is_empty() {
+                    return Err(TransactionError::MissingAddress);
+                }
+            }
+            let total: f64 = self.outputs.iter().map(|output| output.amount).sum();
+            if self.recipient != self.outputs[0].address
+                || to_fixed_point(total) != to_fixed_point(self.amount)
+            {
+                return Err(TransactionError::OutputMismatch);
+            }
         }
 
         // Check if the hash is correct
         let calculated_hash = self.calculate_hash();
-        if calculated_hash != self.hash {
-            println!(
-                "Transaction invalid: hash mismatch. Expected: {}, Got: {}",
-                self.hash, calculated_hash
-            );
-            return false;
+        if !constant_time_eq(calculated_hash.as_bytes(), self.hash.as_bytes()) {
+            return Err(TransactionError::HashMismatch {
+                expected: calculated_hash,
+                actual: self.hash.clone(),
+            });
         }
+        Ok(())
+    }
 
-        // System transactions (mining rewards) don't need signatures or public keys
+    /// Checks that the sender signed the transaction, which system transactions don't need
+    fn validate_signature(&self) -> Result<(), TransactionError> {
+        // System transactions (mining rewards) don't need signatures or public keys,
+        // but they can't carry a fee since nobody pays for it
         if self.sender.0 == "system" {
-            return true;
+            if self.fee != 0.0 {
+                return Err(TransactionError::SystemFee);
+            }
+            return Ok(());
         }
 
-        // Check if the transaction is signed
-        let signature = match &self.signature {
-            Some(sig) => sig,
-            None => {
-                println!("Transaction invalid: missing signature");
-                return false;
-            }
-        };
-
-        // Check if the public key is provided
-        let public_key = match &self.public_key {
-            Some(pk) => pk,
-            None => {
-                println!("Transaction invalid: missing public key");
-                return false;
-            }
-        };
+        // Check if the transaction is signed and the public key is provided
+        let signature = self
+            .signature
+            .as_ref()
+            .ok_or(TransactionError::MissingSignature)?;
+        let public_key = self
+            .public_key
+            .as_ref()
+            .ok_or(TransactionError::MissingPublicKey)?;
 
         // Verify that the address was derived from the public key
-        let address_valid = match public_key.verify_address(&self.sender) {
-            Ok(valid) => valid,
-            Err(e) => {
-                println!("Transaction invalid: address verification error: {}", e);
-                return false;
-            }
-        };
-
-        if !address_valid {
-            println!("Transaction invalid: address not derived from provided public key");
-            return false;
+        if !public_key.verify_address(self.sig_scheme, &self.sender)? {
+            return Err(TransactionError::AddressMismatch);
         }
 
         // Verify the signature
-        let result = public_key.verify_signature(self.hash.as_bytes(), signature);
-        match result {
-            Ok(valid) => {
-                if !valid {
-                    println!("Transaction invalid: signature verification failed");
-                }
-                valid
-            }
-            Err(e) => {
-                println!("Transaction invalid: signature verification error: {}", e);
-                false
-            }
+        if !public_key.verify_signature(self.sig_scheme, self.hash.as_bytes(), signature)? {
+            return Err(TransactionError::InvalidSignature);
         }
+
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::blockchain::test_utils::TestKeypair;
 
     #[test]
     fn test_transaction_creation() {
@@ -175,6 +642,129 @@ mod tests {
         assert!(tx.is_valid());
     }
 
+    #[test]
+    fn test_fee_is_covered_by_hash() {
+        let sender = Address("sender".to_string());
+        let recipient = Address("recipient".to_string());
+        let mut tx = Transaction::new_with_fee(sender, recipient, 10.0, 0.5);
+
+        assert_eq!(tx.fee, 0.5);
+        assert_eq!(tx.hash, tx.calculate_hash());
+
+        tx.fee = 0.0;
+        assert_ne!(tx.hash, tx.calculate_hash());
+    }
+
+    #[test]
+    fn test_nonce_is_covered_by_hash() {
+        let sender = Address("sender".to_string());
+        let recipient = Address("recipient".to_string());
+        let tx = Transaction::new(sender, recipient, 10.0);
+        let first = tx.clone().with_nonce(0);
+        let second = tx.clone().with_nonce(1);
+
+        assert_ne!(first.hash, tx.hash);
+        assert_ne!(first.hash, second.hash);
+        assert_eq!(first.hash, first.calculate_hash());
+    }
+
+    #[test]
+    fn test_zero_amount_requires_memo_and_nonce() {
+        let keypair = TestKeypair::new(1);
+        let unsigned = Transaction::new_with_fee(
+            keypair.address(),
+            Address("recipient".to_string()),
+            0.0,
+            0.1,
+        );
+
+        let mut without_memo = unsigned.clone().with_nonce(0);
+        keypair.sign(&mut without_memo);
+        let mut without_nonce = unsigned.clone().with_memo("hello");
+        keypair.sign(&mut without_nonce);
+        let mut too_long = unsigned
+            .clone()
+            .with_nonce(0)
+            .with_memo("x".repeat(MAX_MEMO_BYTES + 1));
+        keypair.sign(&mut too_long);
+        let mut data_only = unsigned.with_nonce(0).with_memo("hello");
+        keypair.sign(&mut data_only);
+
+        assert!(matches!(
+            without_memo.validate(),
+            Err(TransactionError::InvalidAmount(_))
+        ));
+        assert!(matches!(
+            without_nonce.validate(),
+            Err(TransactionError::MemoWithoutNonce)
+        ));
+        assert!(matches!(
+            too_long.validate(),
+            Err(TransactionError::MemoTooLong(257))
+        ));
+        assert!(data_only.is_data_only());
+        assert!(data_only.validate().is_ok());
+        assert_ne!(
+            data_only.hash,
+            data_only.clone().with_memo("other").calculate_hash()
+        );
+    }
+
+    #[test]
+    fn test_coinbase_flag_is_serialized() {
+        let reward = Transaction::new(
+            Address("system".to_string()),
+            Address("miner".to_string()),
+            50.0,
+        );
+        let transfer = TestKeypair::new(1).transfer("recipient", 10.0, 0.0);
+
+        let reward_json = serde_json::to_value(&reward).unwrap();
+        let transfer_json = serde_json::to_value(&transfer).unwrap();
+
+        assert_eq!(reward_json["isCoinbase"], true);
+        assert_eq!(transfer_json["isCoinbase"], false);
+        assert_eq!(
+            serde_json::from_value::<Transaction>(reward_json).unwrap(),
+            reward
+        );
+        let bytes = bincode::serialize(&transfer).unwrap();
+        assert_eq!(
+            bincode::deserialize::<Transaction>(&bytes).unwrap(),
+            transfer
+        );
+    }
+
+    #[test]
+    fn test_lock_is_covered_by_hash() {
+        let tx = Transaction::new(
+            Address("sender".to_string()),
+            Address("recipient".to_string()),
+            10.0,
+        );
+        let unlocked = tx.clone().with_nonce(0);
+        let locked = unlocked
+            .clone()
+            .with_locked_until(tx.timestamp + chrono::Duration::hours(1));
+
+        assert_ne!(locked.hash, unlocked.hash);
+        assert!(locked.is_locked_at(tx.timestamp));
+        assert!(!locked.is_locked_at(tx.timestamp + chrono::Duration::hours(2)));
+        assert_eq!(
+            tx.with_locked_until(Utc::now()).validate(),
+            Err(TransactionError::LockWithoutNonce)
+        );
+    }
+
+    #[test]
+    fn test_system_transaction_cannot_carry_fee() {
+        let system_addr = Address("system".to_string());
+        let recipient = Address("miner".to_string());
+        let tx = Transaction::new_with_fee(system_addr, recipient, 50.0, 1.0);
+
+        assert!(!tx.is_valid());
+    }
+
     #[test]
     fn test_transaction_tampering() {
         // Create a system transaction (which doesn't need signatures)
@@ -189,4 +779,185 @@ mod tests {
         tx.amount = 100.0;
         assert!(!tx.is_valid());
     }
+
+    #[test]
+    fn test_validate_reports_reason() {
+        let system = || Address("system".to_string());
+        let recipient = || Address("recipient".to_string());
+        let keypair = TestKeypair::new(1);
+
+        let tx = Transaction::new(system(), recipient(), 0.0);
+        assert_eq!(tx.validate(), Err(TransactionError::InvalidAmount(0.0)));
+
+        let tx = Transaction::new_with_fee(keypair.address(), recipient(), 10.0, -1.0);
+        assert_eq!(tx.validate(), Err(TransactionError::InvalidFee(-1.0)));
+
+        // Amounts and fees the canonical encoding can't hold exactly
+        for amount in [f64::NAN, f64::INFINITY, 10.000000001, 1e300] {
+            let tx = keypair.transfer("recipient", amount, 0.0);
+            assert!(
+                matches!(tx.validate(), Err(TransactionError::InvalidAmount(_))),
+                "{}",
+                amount
+            );
+        }
+        let tx = keypair.transfer("recipient", 10.0, 0.1 + 0.2);
+        assert!(matches!(
+            tx.validate(),
+            Err(TransactionError::InvalidFee(_))
+        ));
+        let tx = keypair.transfer("recipient", 10.0, f64::NAN);
+        assert!(matches!(
+            tx.validate(),
+            Err(TransactionError::InvalidFee(_))
+        ));
+
+        let tx = Transaction::new(Address(String::new()), recipient(), 10.0);
+        assert_eq!(tx.validate(), Err(TransactionError::MissingAddress));
+
+        let mut tx = Transaction::new(system(), recipient(), 10.0);
+        tx.amount = 100.0;
+        assert!(matches!(
+            tx.validate(),
+            Err(TransactionError::HashMismatch { .. })
+        ));
+
+        let tx = Transaction::new_with_fee(system(), recipient(), 50.0, 1.0);
+        assert_eq!(tx.validate(), Err(TransactionError::SystemFee));
+
+        let tx = Transaction::new(keypair.address(), recipient(), 10.0);
+        assert_eq!(tx.validate(), Err(TransactionError::MissingSignature));
+
+        let mut tx = keypair.transfer("recipient", 10.0, 0.0);
+        tx.public_key = None;
+        assert_eq!(tx.validate(), Err(TransactionError::MissingPublicKey));
+
+        let mut tx = Transaction::new(keypair.address(), recipient(), 10.0);
+        TestKeypair::new(2).sign(&mut tx);
+        assert_eq!(tx.validate(), Err(TransactionError::AddressMismatch));
+
+        let mut tx = keypair.transfer("recipient", 10.0, 0.0);
+        tx.signature = Some(keypair.sign_message(b"something else"));
+        assert_eq!(tx.validate(), Err(TransactionError::InvalidSignature));
+
+        let mut tx = keypair.transfer("recipient", 10.0, 0.0);
+        tx.public_key = Some(PublicKeyHex("not hex".to_string()));
+        assert!(matches!(tx.validate(), Err(TransactionError::Crypto(_))));
+
+        assert_eq!(keypair.transfer("recipient", 10.0, 0.0).validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_multi_output_transaction() {
+        let keypair = TestKeypair::new(1);
+        let tx = keypair.pay_many(&[("alice", 10.0), ("bob", 5.5), ("alice", 1.0)], 0.5);
+
+        assert_eq!(tx.validate(), Ok(()));
+        assert_eq!(tx.recipient.0, "alice");
+        assert_eq!(tx.amount, 16.5);
+        assert_eq!(tx.amount_received_by("alice"), 11.0);
+        assert_eq!(tx.amount_received_by("bob"), 5.5);
+        assert!(tx.involves("bob"));
+        assert!(!tx.involves("carol"));
+        assert_eq!(tx.balance_change("bob"), 5.5);
+        assert_eq!(tx.balance_change("carol"), 0.0);
+        assert_eq!(tx.balance_change(&keypair.address().0), -17.0);
+
+        // Every output is covered by the hash and so by the signature
+        let mut tampered = tx.clone();
+        tampered.outputs[1].address = Address("carol".to_string());
+        assert!(matches!(
+            tampered.validate(),
+            Err(TransactionError::HashMismatch { .. })
+        ));
+
+        // The amount must be the total of the outputs
+        let mut inflated = tx.clone();
+        inflated.amount = 20.0;
+        inflated.hash = inflated.calculate_hash();
+        keypair.sign(&mut inflated);
+        assert_eq!(inflated.validate(), Err(TransactionError::OutputMismatch));
+
+        let negative = keypair.pay_many(&[("alice", 10.0), ("bob", -5.0)], 0.0);
+        assert_eq!(
+            negative.validate(),
+            Err(TransactionError::InvalidAmount(-5.0))
+        );
+    }
+
+    #[test]
+    fn test_canonical_hash_is_stable() {
+        let mut tx = Transaction::new(
+            Address("system".to_string()),
+            Address("recipient".to_string()),
+            10.0,
+        );
+        tx.timestamp = DateTime::from_timestamp(1_704_067_200, 0).unwrap();
+
+        assert_eq!(
+            tx.calculate_hash(),
+            "1a23274af48383f8ea93d5913ae35143206af1e5ff22c1ffeca9fb0de6078946"
+        );
+    }
+
+    #[test]
+    fn test_scheme_is_covered_by_hash() {
+        let sender = Address("sender".to_string());
+        let recipient = Address("recipient".to_string());
+        let mut tx = Transaction::new(sender, recipient, 10.0);
+        assert_eq!(tx.sig_scheme, SignatureScheme::Ed25519);
+
+        tx.sig_scheme = SignatureScheme::Secp256k1;
+        assert_ne!(tx.hash, tx.calculate_hash());
+    }
+
+    #[test]
+    fn test_signature_must_match_scheme() {
+        let keypair = TestKeypair::new(1);
+        let tx = keypair.transfer("recipient", 10.0, 0.0);
+        assert!(tx.is_valid());
+
+        // Claiming another scheme for an ed25519 signature fails, whether or not
+        // the hash is recomputed and re-signed to cover the new scheme
+        let mut relabeled = tx.clone();
+        relabeled.sig_scheme = SignatureScheme::Secp256k1;
+        assert!(!relabeled.is_valid());
+
+        relabeled.hash = relabeled.calculate_hash();
+        keypair.sign(&mut relabeled);
+        assert!(!relabeled.is_valid());
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_secp256k1_signed_transaction() {
+        use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+
+        let signing_key = SigningKey::from_slice(&[1u8; 32]).unwrap();
+        let public_key = PublicKeyHex(hex::encode(
+            signing_key
+                .verifying_key()
+                .to_encoded_point(true)
+                .as_bytes(),
+        ));
+
+        let mut tx = Transaction::new(
+            public_key
+                .to_address_for(SignatureScheme::Secp256k1)
+                .unwrap(),
+            Address("recipient".to_string()),
+            10.0,
+        );
+        tx.sig_scheme = SignatureScheme::Secp256k1;
+        tx.hash = tx.calculate_hash();
+        let signature: Signature = signing_key.sign(tx.hash.as_bytes());
+        tx.signature = Some(TransactionSignature(hex::encode(signature.to_bytes())));
+        tx.public_key = Some(public_key);
+        assert!(tx.is_valid());
+
+        // The same signature doesn't pass as ed25519
+        let mut downgraded = tx.clone();
+        downgraded.sig_scheme = SignatureScheme::Ed25519;
+        assert!(!downgraded.is_valid());
+    }
 }
@@ -0,0 +1,937 @@
+//! Node configuration, merged from a TOML file, command line flags and
+//! environment variables, in increasing order of precedence.
+//!
+//! A sample file, where every key is optional:
+//!
+//! ```toml
+//! bind_address = "0.0.0.0:3013"
+//! difficulty = 4
+//! mining_reward = 100.0
+//! target_block_time = 10
+//! max_future_drift = 7200
+//! max_reorg_depth = 100
+//! log_level = "info"
+//! legacy_block_hashes = false
+//! network = "mainnet"
+//! data_dir = "./data"
+//! peers = ["http://10.0.0.2:3013"]
+//! auth_token = "changeme"
+//! webhook_url = "https://example.com/fchain-events"
+//! webhook_secret = "changeme"
+//! read_only = false
+//! amount_granularity = 0.0001
+//! allow_zero_amount = false
+//! permissioned = false
+//! gateway_token = "changeme"
+//! authorities = ["1111111111111111111111111111111111111111"]
+//! authority_key = "<hex ed25519 secret key>"
+//! automine = true
+//! miner_address = "1111111111111111111111111111111111111111"
+//! automine_interval = 60
+//!
+//! [limits]
+//! max_body_bytes = 65536
+//! request_timeout = 30
+//! read_lock_timeout_ms = 1000
+//! max_concurrent_requests = 1024
+//! max_mempool_size = 10000
+//! ```
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ed25519_dalek::{SigningKey, SECRET_KEY_LENGTH};
+use log::LevelFilter;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::api::config::{
+    ApiConfig, DEFAULT_MAX_BODY_BYTES, DEFAULT_MAX_CONCURRENT_REQUESTS,
+    DEFAULT_MAX_IMPORT_BODY_BYTES, DEFAULT_READ_LOCK_TIMEOUT, DEFAULT_REQUEST_TIMEOUT,
+};
+use crate::autominer::AutominerConfig;
+use crate::blockchain::block::DEFAULT_MAX_FUTURE_DRIFT;
+use crate::blockchain::chain::DEFAULT_TARGET_BLOCK_TIME_SECS;
+use crate::blockchain::crypto::{decode_fixed, Network, PublicKeyHex, SignatureScheme};
+use crate::blockchain::{Address, ConsensusMode, TransactionCommitment, MAX_DIFFICULTY};
+use crate::gossip::RetryPolicy;
+use crate::logging::LogFormat;
+use crate::webhook::WebhookConfig;
+
+/// Name of the block log file inside the data directory
+pub const BLOCK_LOG_FILE: &str = "blocks.log";
+
+/// Errors raised while loading the configuration
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Failed to parse config file: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("Invalid value for {key}: {message}")]
+    Invalid { key: String, message: String },
+}
+
+impl ConfigError {
+    pub(crate) fn invalid(key: &str, message: impl Into<String>) -> Self {
+        Self::Invalid {
+            key: key.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Settings of a node
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Address the HTTP API listens on
+    pub bind_address: SocketAddr,
+    /// Number of leading zeros a block hash needs
+    pub difficulty: usize,
+    /// Coins awarded for mining a block
+    pub mining_reward: f64,
+    /// Intended time between blocks, in seconds
+    pub target_block_time: u64,
+    /// Seconds ahead of the local clock blocks and transactions may be stamped
+    pub max_future_drift: u64,
+    /// Maximum number of blocks a chain replacement may roll back; 0 leaves it unbounded
+    pub max_reorg_depth: u64,
+    /// Hash new blocks over their sorted transaction hashes instead of their
+    /// Merkle root, for networks with nodes that predate Merkle roots
+    pub legacy_block_hashes: bool,
+    /// Network the node's addresses belong to; versioned addresses of any
+    /// other network are refused
+    pub network: Network,
+    /// Most verbose level logged, e.g. `debug`; `None` leaves it to `RUST_LOG`
+    pub log_level: Option<String>,
+    /// Directory holding the block log; `None` keeps the chain in memory only
+    pub data_dir: Option<PathBuf>,
+    /// Block log file, overriding the one in `data_dir`
+    pub block_log: Option<PathBuf>,
+    /// JSON file of initial balances for a fresh chain
+    pub genesis_allocations: Option<PathBuf>,
+    /// Number of recent blocks kept in memory; `None` keeps them all
+    pub keep_blocks: Option<usize>,
+    /// Base URLs of the peers new transactions are gossiped to
+    pub peers: Vec<String>,
+    /// Bearer token required by mutating endpoints
+    pub auth_token: Option<String>,
+    /// URL notified of every block mined through the API
+    pub webhook_url: Option<String>,
+    /// Secret signing the webhook requests; required with `webhook_url`
+    pub webhook_secret: Option<String>,
+    /// Serve reads and chain imports only, never mining or accepting transactions
+    pub read_only: bool,
+    /// Smallest unit transaction amounts must be a multiple of; 0 allows any amount
+    pub amount_granularity: f64,
+    /// Accept zero-amount transactions that carry a memo
+    pub allow_zero_amount: bool,
+    /// Accept unsigned transactions from a trusted gateway instead of requiring signatures
+    pub permissioned: bool,
+    /// Token the gateway sends in the `X-Gateway-Token` header; required with `permissioned`
+    pub gateway_token: Option<String>,
+    /// Addresses allowed to seal blocks; any switches the chain from proof of
+    /// work to proof of authority
+    pub authorities: Vec<String>,
+    /// Hex ed25519 secret key this node seals blocks with under proof of authority
+    pub authority_key: Option<String>,
+    /// Mine pending transactions in the background, crediting `miner_address`
+    pub automine: bool,
+    /// Address the node's own mined blocks reward; required with `automine`
+    pub miner_address: Option<String>,
+    /// Seconds between automined blocks, mined even when the pool is empty;
+    /// `None` mines only when transactions are pending
+    pub automine_interval: Option<u64>,
+    /// Size and time limits
+    pub limits: LimitsConfig,
+}
+
+/// Size and time limits of a node
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Maximum request body size in bytes for regular endpoints
+    pub max_body_bytes: usize,
+    /// Maximum request body size in bytes for endpoints that accept whole chains
+    pub max_import_body_bytes: usize,
+    /// Seconds a request may take before it is aborted
+    pub request_timeout: u64,
+    /// Milliseconds a read waits for the chain lock before it is answered with
+    /// 503; 0 waits as long as it takes
+    pub read_lock_timeout_ms: u64,
+    /// Requests served at once before more are answered with 503; 0 serves
+    /// any number
+    pub max_concurrent_requests: usize,
+    /// Maximum number of pending transactions; 0 leaves the pool unbounded
+    pub max_mempool_size: usize,
+    /// Maximum number of transactions per block; 0 leaves it unbounded
+    pub max_block_transactions: usize,
+    /// Maximum encoded size of a block's transactions; 0 leaves it unbounded
+    pub max_block_bytes: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind_address: SocketAddr::from(([0, 0, 0, 0], 3013)),
+            difficulty: 4,
+            mining_reward: 100.0,
+            target_block_time: DEFAULT_TARGET_BLOCK_TIME_SECS,
+            max_future_drift: DEFAULT_MAX_FUTURE_DRIFT.as_secs(),
+            max_reorg_depth: 0,
+            legacy_block_hashes: false,
+            network: Network::default(),
+            log_level: None,
+            data_dir: None,
+            block_log: None,
+            genesis_allocations: None,
+            keep_blocks: None,
+            peers: Vec::new(),
+            auth_token: None,
+            webhook_url: None,
+            webhook_secret: None,
+            read_only: false,
+            amount_granularity: 0.0,
+            allow_zero_amount: false,
+            permissioned: false,
+            gateway_token: None,
+            authorities: Vec::new(),
+            authority_key: None,
+            automine: false,
+            miner_address: None,
+            automine_interval: None,
+            limits: LimitsConfig::default(),
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_import_body_bytes: DEFAULT_MAX_IMPORT_BODY_BYTES,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT.as_secs(),
+            read_lock_timeout_ms: DEFAULT_READ_LOCK_TIMEOUT.as_millis() as u64,
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            max_mempool_size: 0,
+            max_block_transactions: 0,
+            max_block_bytes: 0,
+        }
+    }
+}
+
+/// Options given on the command line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CliArgs {
+    /// `--config <path>`: TOML file to load
+    pub config: Option<PathBuf>,
+    /// `--log-format <pretty|json>`
+    pub log_format: LogFormat,
+    /// `--bind <address>`
+    pub bind_address: Option<SocketAddr>,
+    /// `--difficulty <n>`
+    pub difficulty: Option<usize>,
+    /// `--mining-reward <coins>`
+    pub mining_reward: Option<f64>,
+    /// `--target-block-time <seconds>`
+    pub target_block_time: Option<u64>,
+    /// `--data-dir <path>`
+    pub data_dir: Option<PathBuf>,
+    /// `--peer <url>`, repeatable; replaces the peers from the file
+    pub peers: Vec<String>,
+    /// `--auth-token <token>`
+    pub auth_token: Option<String>,
+    /// `--read-only`
+    pub read_only: bool,
+    /// `--automine`
+    pub automine: bool,
+    /// `--miner-address <address>`
+    pub miner_address: Option<String>,
+}
+
+impl CliArgs {
+    /// Parses the arguments following the program name. Both `--flag value`
+    /// and `--flag=value` are accepted.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut cli = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{} requires a value", flag))
+            };
+
+            match flag.as_str() {
+                "--config" => cli.config = Some(value()?.into()),
+                "--log-format" => cli.log_format = value()?.parse()?,
+                "--bind" => cli.bind_address = Some(parse_flag(&flag, &value()?)?),
+                "--difficulty" => cli.difficulty = Some(parse_flag(&flag, &value()?)?),
+                "--mining-reward" => cli.mining_reward = Some(parse_flag(&flag, &value()?)?),
+                "--target-block-time" => {
+                    cli.target_block_time = Some(parse_flag(&flag, &value()?)?)
+                }
+                "--data-dir" => cli.data_dir = Some(value()?.into()),
+                "--peer" => cli.peers.push(value()?),
+                "--auth-token" => cli.auth_token = Some(value()?),
+                "--read-only" => match inline {
+                    None => cli.read_only = true,
+                    Some(_) => return Err(format!("{} doesn't take a value", flag)),
+                },
+                "--automine" => match inline {
+                    None => cli.automine = true,
+                    Some(_) => return Err(format!("{} doesn't take a value", flag)),
+                },
+                "--miner-address" => cli.miner_address = Some(value()?),
+                _ => return Err(format!("Unknown option '{}'", flag)),
+            }
+        }
+
+        Ok(cli)
+    }
+}
+
+fn parse_flag<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value '{}' for {}", value, flag))
+}
+
+impl Config {
+    /// Parses a TOML document
+    pub fn from_toml(source: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(source)?)
+    }
+
+    /// Reads a TOML file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml(&source)
+    }
+
+    /// Builds the configuration of a node: the file named by `--config`, or
+    /// the defaults, then the command line flags, then the `FCHAIN_*`
+    /// variables read through `env`. The result is validated.
+    pub fn load(cli: &CliArgs, env: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let mut config = match &cli.config {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.apply_cli(cli);
+        config.apply_env(env)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn apply_cli(&mut self, cli: &CliArgs) {
+        if let Some(bind_address) = cli.bind_address {
+            self.bind_address = bind_address;
+        }
+        if let Some(difficulty) = cli.difficulty {
+            self.difficulty = difficulty;
+        }
+        if let Some(mining_reward) = cli.mining_reward {
+            self.mining_reward = mining_reward;
+        }
+        if let Some(target_block_time) = cli.target_block_time {
+            self.target_block_time = target_block_time;
+        }
+        if let Some(data_dir) = &cli.data_dir {
+            self.data_dir = Some(data_dir.clone());
+        }
+        if !cli.peers.is_empty() {
+            self.peers = cli.peers.clone();
+        }
+        if let Some(auth_token) = &cli.auth_token {
+            self.auth_token = Some(auth_token.clone());
+        }
+        if cli.read_only {
+            self.read_only = true;
+        }
+        if cli.automine {
+            self.automine = true;
+        }
+        if let Some(miner_address) = &cli.miner_address {
+            self.miner_address = Some(miner_address.clone());
+        }
+    }
+
+    fn apply_env(&mut self, env: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        if let Some(value) = env("FCHAIN_BIND_ADDRESS") {
+            self.bind_address = parse_env("FCHAIN_BIND_ADDRESS", &value)?;
+        }
+        if let Some(value) = env("FCHAIN_DIFFICULTY") {
+            self.difficulty = parse_env("FCHAIN_DIFFICULTY", &value)?;
+        }
+        if let Some(value) = env("FCHAIN_MINING_REWARD") {
+            self.mining_reward = parse_env("FCHAIN_MINING_REWARD", &value)?;
+        }
+        if let Some(value) = env("FCHAIN_TARGET_BLOCK_TIME") {
+            self.target_block_time = parse_env("FCHAIN_TARGET_BLOCK_TIME", &value)?;
+        }
+        if let Some(value) = env("FCHAIN_MAX_FUTURE_DRIFT") {
+            self.max_future_drift = parse_env("FCHAIN_MAX_FUTURE_DRIFT", &value)?;
+        }
+        if let Some(value) = env("FCHAIN_MAX_REORG_DEPTH") {
+            self.max_reorg_depth = parse_env("FCHAIN_MAX_REORG_DEPTH", &value)?;
+        }
+        if let Some(value) = env("FCHAIN_LEGACY_BLOCK_HASHES") {
+            self.legacy_block_hashes = parse_env("FCHAIN_LEGACY_BLOCK_HASHES", &value)?;
+        }
+        if let Some(value) = env("FCHAIN_NETWORK") {
+            self.network = parse_env("FCHAIN_NETWORK", &value)?;
+        }
+        if let Some(value) = env("FCHAIN_LOG_LEVEL") {
+            self.log_level = Some(value).filter(|level| !level.is_empty());
+        }
+        if let Some(value) = env("FCHAIN_DATA_DIR") {
+            self.data_dir = Some(value.into());
+        }
+        if let Some(value) = env("FCHAIN_BLOCK_LOG") {
+            self.block_log = Some(value.into());
+        }
+        if let Some(value) = env("FCHAIN_GENESIS_ALLOCATIONS") {
+            self.genesis_allocations = Some(value.into());
+        }
+        if let Some(value) = env("FCHAIN_KEEP_BLOCKS") {
+            self.keep_blocks = Some(parse_env("FCHAIN_KEEP_BLOCKS", &value)?);
+        }
+        if let Some(value) = env("FCHAIN_PEERS") {
+            self.peers = value
+                .split(',')
+                .map(str::trim)
+                .filter(|peer| !peer.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        // An empty token disables authentication
+        if let Some(value) = env("FCHAIN_API_TOKEN") {
+            self.auth_token = Some(value).filter(|token| !token.is_empty());
+        }
+        if let Some(value) = env("FCHAIN_READ_ONLY") {
+            self.read_only = parse_env("FCHAIN_READ_ONLY", &value)?;
+        }
+        if let Some(value) = env("FCHAIN_AMOUNT_GRANULARITY") {
+            self.amount_granularity = parse_env("FCHAIN_AMOUNT_GRANULARITY", &value)?;
+        }
+        if let Some(value) = env("FCHAIN_ALLOW_ZERO_AMOUNT") {
+            self.allow_zero_amount = parse_env("FCHAIN_ALLOW_ZERO_AMOUNT", &value)?;
+        }
+        if let Some(value) = env("FCHAIN_PERMISSIONED") {
+            self.permissioned = parse_env("FCHAIN_PERMISSIONED", &value)?;
+        }
+        if let Some(value) = env("FCHAIN_GATEWAY_TOKEN") {
+            self.gateway_token = Some(value).filter(|token| !token.is_empty());
+        }
+        if let Some(value) = env("FCHAIN_AUTHORITIES") {
+            self.authorities = value
+                .split(',')
+                .map(str::trim)
+                .filter(|authority| !authority.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        if let Some(value) = env("FCHAIN_AUTHORITY_KEY") {
+            self.authority_key = Some(value).filter(|key| !key.is_empty());
+        }
+        if let Some(value) = env("FCHAIN_AUTOMINE") {
+            self.automine = parse_env("FCHAIN_AUTOMINE", &value)?;
+        }
+        if let Some(value) = env("FCHAIN_MINER_ADDRESS") {
+            self.miner_address = Some(value).filter(|address| !address.is_empty());
+        }
+        if let Some(value) = env("FCHAIN_AUTOMINE_INTERVAL") {
+            self.automine_interval = Some(parse_env("FCHAIN_AUTOMINE_INTERVAL", &value)?);
+        }
+        if let Some(value) = env("FCHAIN_WEBHOOK_URL") {
+            self.webhook_url = Some(value).filter(|url| !url.is_empty());
+        }
+        if let Some(value) = env("FCHAIN_WEBHOOK_SECRET") {
+            self.webhook_secret = Some(value);
+        }
+        Ok(())
+    }
+
+    /// Checks that every value is usable
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.difficulty > MAX_DIFFICULTY {
+            return Err(ConfigError::invalid(
+                "difficulty",
+                format!("must be at most {}", MAX_DIFFICULTY),
+            ));
+        }
+        if !self.mining_reward.is_finite() || self.mining_reward < 0.0 {
+            return Err(ConfigError::invalid(
+                "mining_reward",
+                "must be a non-negative number",
+            ));
+        }
+        if !self.amount_granularity.is_finite() || self.amount_granularity < 0.0 {
+            return Err(ConfigError::invalid(
+                "amount_granularity",
+                "must be a non-negative number",
+            ));
+        }
+        if self.target_block_time == 0 {
+            return Err(ConfigError::invalid(
+                "target_block_time",
+                "must be at least 1 second",
+            ));
+        }
+        if let Some(level) = &self.log_level {
+            if level.parse::<LevelFilter>().is_err() {
+                return Err(ConfigError::invalid(
+                    "log_level",
+                    "must be one of off, error, warn, info, debug or trace",
+                ));
+            }
+        }
+        if let Some(keep) = self.keep_blocks {
+            if keep == 0 {
+                return Err(ConfigError::invalid("keep_blocks", "must be at least 1"));
+            }
+            if self.block_log_path().is_none() {
+                return Err(ConfigError::invalid(
+                    "keep_blocks",
+                    "requires data_dir or block_log",
+                ));
+            }
+        }
+        if let Some(peer) = self
+            .peers
+            .iter()
+            .find(|peer| !peer.starts_with("http://") && !peer.starts_with("https://"))
+        {
+            return Err(ConfigError::invalid(
+                "peers",
+                format!("'{}' is not an http(s) URL", peer),
+            ));
+        }
+        if self.auth_token.as_deref() == Some("") {
+            return Err(ConfigError::invalid("auth_token", "must not be empty"));
+        }
+        if let Some(url) = &self.webhook_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(ConfigError::invalid(
+                    "webhook_url",
+                    format!("'{}' is not an http(s) URL", url),
+                ));
+            }
+            if self
+                .webhook_secret
+                .as_deref()
+                .unwrap_or_default()
+                .is_empty()
+            {
+                return Err(ConfigError::invalid(
+                    "webhook_secret",
+                    "must be set when webhook_url is",
+                ));
+            }
+        }
+        if self.permissioned && self.gateway_token.as_deref().unwrap_or_default().is_empty() {
+            return Err(ConfigError::invalid(
+                "gateway_token",
+                "must be set when permissioned is",
+            ));
+        }
+        for authority in &self.authorities {
+            if let Err(err) = Address(authority.clone()).validate_for(self.network) {
+                return Err(ConfigError::invalid(
+                    "authorities",
+                    format!("'{}': {}", authority, err),
+                ));
+            }
+        }
+        if let Some(key) = &self.authority_key {
+            if self.authorities.is_empty() {
+                return Err(ConfigError::invalid(
+                    "authority_key",
+                    "requires authorities",
+                ));
+            }
+            let key = decode_fixed::<SECRET_KEY_LENGTH>(key)
+                .map_err(|err| ConfigError::invalid("authority_key", err.to_string()))?;
+            let public_key = PublicKeyHex(hex::encode(
+                SigningKey::from_bytes(&key).verifying_key().to_bytes(),
+            ));
+            let is_authority = self.authorities.iter().any(|authority| {
+                public_key
+                    .verify_address(SignatureScheme::Ed25519, &Address(authority.clone()))
+                    .unwrap_or(false)
+            });
+            if !is_authority {
+                return Err(ConfigError::invalid(
+                    "authority_key",
+                    "its address is not one of the authorities",
+                ));
+            }
+        }
+        if let Some(address) = &self.miner_address {
+            if let Err(err) = Address(address.clone()).validate_for(self.network) {
+                return Err(ConfigError::invalid("miner_address", err.to_string()));
+            }
+        }
+        if self.automine {
+            if self.miner_address.is_none() {
+                return Err(ConfigError::invalid(
+                    "miner_address",
+                    "must be set when automine is",
+                ));
+            }
+            if self.read_only {
+                return Err(ConfigError::invalid(
+                    "automine",
+                    "a read-only node can't mine",
+                ));
+            }
+        }
+        if self.automine_interval == Some(0) {
+            return Err(ConfigError::invalid(
+                "automine_interval",
+                "must be at least 1 second",
+            ));
+        }
+        if self.limits.max_body_bytes == 0 || self.limits.max_import_body_bytes == 0 {
+            return Err(ConfigError::invalid(
+                "limits",
+                "body size limits must be at least 1 byte",
+            ));
+        }
+        if self.limits.request_timeout == 0 {
+            return Err(ConfigError::invalid(
+                "limits.request_timeout",
+                "must be at least 1 second",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Block log file to persist the chain to, if any
+    pub fn block_log_path(&self) -> Option<PathBuf> {
+        self.block_log
+            .clone()
+            .or_else(|| self.data_dir.as_ref().map(|dir| dir.join(BLOCK_LOG_FILE)))
+    }
+
+    /// How new blocks commit to their transactions
+    pub fn tx_commitment(&self) -> TransactionCommitment {
+        if self.legacy_block_hashes {
+            TransactionCommitment::Legacy
+        } else {
+            TransactionCommitment::MerkleRoot
+        }
+    }
+
+    /// How blocks are sealed: by the authorities if any are configured,
+    /// otherwise by proof of work
+    pub fn consensus_mode(&self) -> ConsensusMode {
+        if self.authorities.is_empty() {
+            return ConsensusMode::ProofOfWork;
+        }
+        ConsensusMode::ProofOfAuthority {
+            authorities: self.authorities.iter().cloned().map(Address).collect(),
+        }
+    }
+
+    /// Key this node seals blocks with, if one is configured. Call after
+    /// [`Config::validate`], which checks that it decodes.
+    pub fn authority_signing_key(&self) -> Option<SigningKey> {
+        let key = decode_fixed::<SECRET_KEY_LENGTH>(self.authority_key.as_deref()?).ok()?;
+        Some(SigningKey::from_bytes(&key))
+    }
+
+    /// Most verbose level to log, if one is configured. Call after
+    /// [`Config::validate`], which checks that it parses.
+    pub fn log_level(&self) -> Option<LevelFilter> {
+        self.log_level.as_deref()?.parse().ok()
+    }
+
+    /// Autominer settings, if automining is enabled
+    pub fn autominer_config(&self) -> Option<AutominerConfig> {
+        let miner_address = self.miner_address.clone().filter(|_| self.automine)?;
+        Some(AutominerConfig {
+            miner_address,
+            interval: self.automine_interval.map(Duration::from_secs),
+        })
+    }
+
+    /// HTTP API settings
+    pub fn api_config(&self) -> ApiConfig {
+        ApiConfig {
+            max_body_bytes: self.limits.max_body_bytes,
+            max_import_body_bytes: self.limits.max_import_body_bytes,
+            auth_token: self.auth_token.clone(),
+            request_timeout: Duration::from_secs(self.limits.request_timeout),
+            read_lock_timeout: Some(self.limits.read_lock_timeout_ms)
+                .filter(|&millis| millis > 0)
+                .map(Duration::from_millis),
+            max_concurrent_requests: Some(self.limits.max_concurrent_requests)
+                .filter(|&limit| limit > 0),
+            webhook: self.webhook_url.clone().map(|url| WebhookConfig {
+                url,
+                secret: self.webhook_secret.clone().unwrap_or_default(),
+                retry: RetryPolicy::default(),
+            }),
+            read_only: self.read_only,
+            gateway_token: self.gateway_token.clone().filter(|_| self.permissioned),
+        }
+    }
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, ConfigError> {
+    value
+        .parse()
+        .map_err(|_| ConfigError::invalid(name, format!("'{}' is not valid", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+        bind_address = "127.0.0.1:4000"
+        difficulty = 3
+        mining_reward = 25.0
+        target_block_time = 30
+        data_dir = "/var/lib/fchain"
+        peers = ["http://10.0.0.2:3013", "http://10.0.0.3:3013"]
+        auth_token = "secret"
+
+        [limits]
+        max_body_bytes = 1024
+        request_timeout = 5
+        max_mempool_size = 100
+    "#;
+
+    fn args(args: &[&str]) -> CliArgs {
+        CliArgs::parse(args.iter().map(|arg| arg.to_string())).unwrap()
+    }
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn test_sample_toml_parses() {
+        let config = Config::from_toml(SAMPLE).unwrap();
+
+        assert_eq!(config.bind_address, "127.0.0.1:4000".parse().unwrap());
+        assert_eq!(config.difficulty, 3);
+        assert_eq!(config.mining_reward, 25.0);
+        assert_eq!(config.target_block_time, 30);
+        assert_eq!(
+            config.block_log_path(),
+            Some(PathBuf::from("/var/lib/fchain/blocks.log"))
+        );
+        assert_eq!(config.peers.len(), 2);
+        assert_eq!(config.auth_token.as_deref(), Some("secret"));
+        assert_eq!(config.limits.max_body_bytes, 1024);
+        assert_eq!(config.limits.max_mempool_size, 100);
+        // Unset values keep their defaults
+        assert_eq!(config.max_future_drift, DEFAULT_MAX_FUTURE_DRIFT.as_secs());
+        assert_eq!(
+            config.limits.max_import_body_bytes,
+            DEFAULT_MAX_IMPORT_BODY_BYTES
+        );
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        let result = Config::from_toml("dificulty = 3");
+        assert!(matches!(result, Err(ConfigError::Parse(_))));
+    }
+
+    #[test]
+    fn test_cli_overrides_file_and_env_overrides_cli() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fchain.toml");
+        std::fs::write(&path, SAMPLE).unwrap();
+        let cli = args(&[
+            "--config",
+            path.to_str().unwrap(),
+            "--difficulty=5",
+            "--peer",
+            "http://10.0.0.9:3013",
+            "--log-format",
+            "json",
+        ]);
+
+        let config = Config::load(&cli, no_env).unwrap();
+        assert_eq!(cli.log_format, LogFormat::Json);
+        assert_eq!(config.difficulty, 5);
+        assert_eq!(config.peers, vec!["http://10.0.0.9:3013".to_string()]);
+        // Values without a flag come from the file
+        assert_eq!(config.mining_reward, 25.0);
+
+        let env = |name: &str| (name == "FCHAIN_DIFFICULTY").then(|| "2".to_string());
+        let config = Config::load(&cli, env).unwrap();
+        assert_eq!(config.difficulty, 2);
+    }
+
+    #[test]
+    fn test_read_only_flag_and_env() {
+        let config = Config::load(&args(&["--read-only"]), no_env).unwrap();
+        assert!(config.read_only);
+        assert!(config.api_config().read_only);
+
+        let env = |name: &str| (name == "FCHAIN_READ_ONLY").then(|| "true".to_string());
+        assert!(Config::load(&CliArgs::default(), env).unwrap().read_only);
+        assert!(!Config::load(&CliArgs::default(), no_env).unwrap().read_only);
+
+        assert!(CliArgs::parse(["--read-only=yes".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_permissioned_requires_a_gateway_token() {
+        let permissioned = |name: &str| (name == "FCHAIN_PERMISSIONED").then(|| "true".to_string());
+        let config = Config::load(&CliArgs::default(), permissioned);
+        assert!(matches!(config, Err(ConfigError::Invalid { key, .. }) if key == "gateway_token"));
+
+        let env = |name: &str| match name {
+            "FCHAIN_PERMISSIONED" => Some("true".to_string()),
+            "FCHAIN_GATEWAY_TOKEN" => Some("secret".to_string()),
+            _ => None,
+        };
+        let config = Config::load(&CliArgs::default(), env).unwrap();
+        assert!(config.permissioned);
+        assert_eq!(config.api_config().gateway_token.as_deref(), Some("secret"));
+
+        // The token is ignored unless the chain is permissioned
+        let token = |name: &str| (name == "FCHAIN_GATEWAY_TOKEN").then(|| "secret".to_string());
+        let config = Config::load(&CliArgs::default(), token).unwrap();
+        assert_eq!(config.api_config().gateway_token, None);
+    }
+
+    #[test]
+    fn test_authorities_select_proof_of_authority() {
+        let key = hex::encode([1u8; SECRET_KEY_LENGTH]);
+        let authority = PublicKeyHex(hex::encode(
+            SigningKey::from_bytes(&[1u8; SECRET_KEY_LENGTH])
+                .verifying_key()
+                .to_bytes(),
+        ))
+        .to_address()
+        .unwrap()
+        .0;
+        assert_eq!(
+            Config::default().consensus_mode(),
+            ConsensusMode::ProofOfWork
+        );
+
+        let env = |name: &str| match name {
+            "FCHAIN_AUTHORITIES" => Some(format!("{}, {}", authority, "2".repeat(40))),
+            "FCHAIN_AUTHORITY_KEY" => Some(key.clone()),
+            _ => None,
+        };
+        let config = Config::load(&CliArgs::default(), env).unwrap();
+        assert_eq!(
+            config.consensus_mode(),
+            ConsensusMode::ProofOfAuthority {
+                authorities: vec![Address(authority.clone()), Address("2".repeat(40))]
+            }
+        );
+        assert!(config.authority_signing_key().is_some());
+
+        // The key must belong to one of the authorities
+        let outsider = |name: &str| match name {
+            "FCHAIN_AUTHORITIES" => Some("2".repeat(40)),
+            "FCHAIN_AUTHORITY_KEY" => Some(key.clone()),
+            _ => None,
+        };
+        let config = Config::load(&CliArgs::default(), outsider);
+        assert!(matches!(config, Err(ConfigError::Invalid { key, .. }) if key == "authority_key"));
+    }
+
+    #[test]
+    fn test_automine_requires_a_miner_address() {
+        let miner = "1111111111111111111111111111111111111111";
+        let config =
+            Config::load(&args(&["--automine", "--miner-address", miner]), no_env).unwrap();
+        assert_eq!(
+            config.autominer_config(),
+            Some(AutominerConfig {
+                miner_address: miner.to_string(),
+                interval: None,
+            })
+        );
+
+        let env = |name: &str| (name == "FCHAIN_AUTOMINE_INTERVAL").then(|| "60".to_string());
+        let config = Config::load(&args(&["--automine", "--miner-address", miner]), env).unwrap();
+        assert_eq!(
+            config.autominer_config().unwrap().interval,
+            Some(Duration::from_secs(60))
+        );
+
+        // A miner address alone doesn't turn automining on
+        let config = Config::load(&args(&["--miner-address", miner]), no_env).unwrap();
+        assert_eq!(config.autominer_config(), None);
+
+        let config = Config::load(&args(&["--automine"]), no_env);
+        assert!(matches!(config, Err(ConfigError::Invalid { key, .. }) if key == "miner_address"));
+
+        let config = Config::load(&args(&["--automine", "--miner-address", "miner"]), no_env);
+        assert!(matches!(config, Err(ConfigError::Invalid { key, .. }) if key == "miner_address"));
+
+        // A testnet miner address only suits a testnet node
+        let testnet_miner = Address::versioned(Network::Testnet, &[0x11; 20]).0;
+        let testnet_args = args(&["--automine", "--miner-address", &testnet_miner]);
+        let config = Config::load(&testnet_args, no_env);
+        assert!(matches!(config, Err(ConfigError::Invalid { key, .. }) if key == "miner_address"));
+        let env = |name: &str| (name == "FCHAIN_NETWORK").then(|| "testnet".to_string());
+        let config = Config::load(&testnet_args, env).unwrap();
+        assert_eq!(config.network, Network::Testnet);
+
+        let config = Config::load(
+            &args(&["--automine", "--miner-address", miner, "--read-only"]),
+            no_env,
+        );
+        assert!(matches!(config, Err(ConfigError::Invalid { key, .. }) if key == "automine"));
+    }
+
+    #[test]
+    fn test_invalid_values_fail_fast() {
+        let config = Config::load(&args(&["--difficulty", "65"]), no_env);
+        assert!(matches!(config, Err(ConfigError::Invalid { key, .. }) if key == "difficulty"));
+
+        let config = Config::load(&args(&["--peer", "10.0.0.2:3013"]), no_env);
+        assert!(matches!(config, Err(ConfigError::Invalid { key, .. }) if key == "peers"));
+
+        let env = |name: &str| {
+            (name == "FCHAIN_WEBHOOK_URL").then(|| "http://localhost:9000/hook".to_string())
+        };
+        let config = Config::load(&CliArgs::default(), env);
+        assert!(matches!(config, Err(ConfigError::Invalid { key, .. }) if key == "webhook_secret"));
+
+        let env = |name: &str| (name == "FCHAIN_AMOUNT_GRANULARITY").then(|| "-1".to_string());
+        let config = Config::load(&CliArgs::default(), env);
+        assert!(
+            matches!(config, Err(ConfigError::Invalid { key, .. }) if key == "amount_granularity")
+        );
+
+        let env = |name: &str| (name == "FCHAIN_KEEP_BLOCKS").then(|| "10".to_string());
+        let config = Config::load(&CliArgs::default(), env);
+        assert!(matches!(config, Err(ConfigError::Invalid { key, .. }) if key == "keep_blocks"));
+
+        assert!(CliArgs::parse(["--difficulty".to_string(), "hard".to_string()]).is_err());
+        assert!(CliArgs::parse(["--bogus".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,368 @@
+//! Propagation of new pending transactions to peer nodes.
+//!
+//! Every transaction entering the local pending pool is POSTed to each peer's
+//! `/transactions/raw` endpoint. Failed deliveries are retried with
+//! exponential backoff; a peer that still can't be reached after the last
+//! attempt is marked unreachable and skipped from then on.
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use thiserror::Error;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::blockchain::{MempoolEvent, SharedBlockchain, Transaction};
+
+/// How often, and how patiently, a failed operation is retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled after every further failure
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between two attempts
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay to wait after the given failed attempt, counting from 1
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Runs `operation` until it succeeds or `policy.max_attempts` attempts have
+/// failed, sleeping with exponential backoff in between. Returns the last
+/// error if every attempt failed.
+///
+/// The operation is given the number of the current attempt, counting from 1.
+pub async fn retry_with_backoff<T, E, F, Fut>(
+    policy: &RetryPolicy,
+    mut operation: F,
+) -> Result<T, E>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match operation(attempt).await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt >= policy.max_attempts => return Err(err),
+            Err(_) => {
+                tokio::time::sleep(policy.backoff(attempt)).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Reasons a transaction could not be delivered to a peer
+#[derive(Debug, Error)]
+pub enum GossipError {
+    #[error("request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("peer answered with status {0}")]
+    Status(u16),
+}
+
+/// Peers to gossip with and how hard to try reaching them
+#[derive(Debug, Clone, Default)]
+pub struct GossipConfig {
+    /// Base URLs of the peer nodes, e.g. `http://10.0.0.2:3013`
+    pub peers: Vec<String>,
+    /// Retry policy applied to every delivery
+    pub retry: RetryPolicy,
+}
+
+/// Sends transactions to the configured peers
+#[derive(Debug)]
+pub struct Gossip {
+    client: reqwest::Client,
+    peers: Mutex<Vec<String>>,
+    retry: RetryPolicy,
+    unreachable: Mutex<HashSet<String>>,
+}
+
+impl Gossip {
+    /// Creates a gossip client for the given configuration
+    pub fn new(config: GossipConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            peers: Mutex::new(normalize_peers(config.peers)),
+            retry: config.retry,
+            unreachable: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Every configured peer
+    pub fn peers(&self) -> Vec<String> {
+        self.peers.lock().unwrap().clone()
+    }
+
+    /// Replaces the peers, e.g. after a config reload. Every peer, including
+    /// one marked unreachable before, gets a fresh chance.
+    pub fn set_peers(&self, peers: Vec<String>) {
+        *self.peers.lock().unwrap() = normalize_peers(peers);
+        self.unreachable.lock().unwrap().clear();
+    }
+
+    /// Peers that haven't been marked unreachable
+    pub fn reachable_peers(&self) -> Vec<String> {
+        let unreachable = self.unreachable.lock().unwrap();
+        self.peers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|peer| !unreachable.contains(*peer))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns true if the peer failed every attempt of an earlier delivery
+    pub fn is_unreachable(&self, peer: &str) -> bool {
+        self.unreachable
+            .lock()
+            .unwrap()
+            .contains(peer.trim_end_matches('/'))
+    }
+
+    /// Sends a transaction to every reachable peer, retrying failed deliveries
+    pub async fn broadcast_transaction(&self, transaction: &Transaction) {
+        for peer in self.reachable_peers() {
+            let result = retry_with_backoff(&self.retry, |attempt| {
+                debug!(
+                    "Sending transaction {} to {} (attempt {})",
+                    transaction.hash, peer, attempt
+                );
+                self.send_transaction(&peer, transaction)
+            })
+            .await;
+
+            if let Err(err) = result {
+                warn!(
+                    "Marking peer {} unreachable after {} attempts: {}",
+                    peer, self.retry.max_attempts, err
+                );
+                self.unreachable.lock().unwrap().insert(peer);
+            }
+        }
+    }
+
+    async fn send_transaction(
+        &self,
+        peer: &str,
+        transaction: &Transaction,
+    ) -> Result<(), GossipError> {
+        let response = self
+            .client
+            .post(format!("{}/transactions/raw", peer))
+            .json(transaction)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status.is_server_error() {
+            return Err(GossipError::Status(status.as_u16()));
+        }
+        // The peer is up: a rejection, e.g. because it already has the
+        // transaction, won't change by sending it again
+        if !status.is_success() {
+            debug!(
+                "Peer {} rejected transaction {} with status {}",
+                peer, transaction.hash, status
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Strips trailing slashes so peers compare equal however they were written
+fn normalize_peers(peers: Vec<String>) -> Vec<String> {
+    peers
+        .into_iter()
+        .map(|peer| peer.trim_end_matches('/').to_string())
+        .collect()
+}
+
+/// Spawns a task that gossips every transaction entering the pending pool
+pub fn spawn_gossip(blockchain: &SharedBlockchain, gossip: Arc<Gossip>) -> JoinHandle<()> {
+    let mut events = blockchain.lock().unwrap().subscribe_mempool();
+    info!("Gossiping transactions to {} peers", gossip.peers().len());
+
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(MempoolEvent::Added { transaction })
+                | Ok(MempoolEvent::Rebroadcast { transaction }) => {
+                    gossip.broadcast_transaction(&transaction).await;
+                }
+                Ok(MempoolEvent::Removed { .. }) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Gossip lagging, skipped {} mempool events", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+
+    use super::*;
+    use crate::blockchain::{create_shared_blockchain, Address};
+
+    fn fast_retry(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        }
+    }
+
+    /// Peer that answers 503 to its first `failures` requests and records the
+    /// transactions it accepts afterwards
+    #[derive(Clone, Default)]
+    struct FlakyPeer {
+        failures: u32,
+        attempts: Arc<AtomicU32>,
+        received: Arc<Mutex<Vec<Transaction>>>,
+    }
+
+    async fn receive(
+        State(peer): State<FlakyPeer>,
+        Json(transaction): Json<Transaction>,
+    ) -> StatusCode {
+        if peer.attempts.fetch_add(1, Ordering::SeqCst) < peer.failures {
+            return StatusCode::SERVICE_UNAVAILABLE;
+        }
+        peer.received.lock().unwrap().push(transaction);
+        StatusCode::OK
+    }
+
+    async fn spawn_peer(peer: FlakyPeer) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/transactions/raw", post(receive))
+            .with_state(peer);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(4), Duration::from_millis(500));
+        assert_eq!(policy.backoff(40), Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up_after_max_attempts() {
+        let mut calls = 0;
+        let result: Result<(), u32> = retry_with_backoff(&fast_retry(3), |attempt| {
+            calls += 1;
+            async move { Err(attempt) }
+        })
+        .await;
+
+        assert_eq!(result, Err(3));
+        assert_eq!(calls, 3);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_reaches_flaky_peer() {
+        let peer = FlakyPeer {
+            failures: 2,
+            ..FlakyPeer::default()
+        };
+        let url = spawn_peer(peer.clone()).await;
+        let gossip = Arc::new(Gossip::new(GossipConfig {
+            peers: vec![url.clone()],
+            retry: fast_retry(3),
+        }));
+        let blockchain = create_shared_blockchain(1, 50.0);
+        let handle = spawn_gossip(&blockchain, gossip.clone());
+
+        let transaction = Transaction::new(
+            Address("system".to_string()),
+            Address("recipient".to_string()),
+            10.0,
+        );
+        blockchain
+            .lock()
+            .unwrap()
+            .create_transaction(transaction.clone())
+            .unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while peer.received.lock().unwrap().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("transaction was not propagated");
+
+        assert_eq!(*peer.received.lock().unwrap(), vec![transaction]);
+        assert_eq!(peer.attempts.load(Ordering::SeqCst), 3);
+        assert!(!gossip.is_unreachable(&url));
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_down_peer_is_marked_unreachable_and_skipped() {
+        let peer = FlakyPeer {
+            failures: u32::MAX,
+            ..FlakyPeer::default()
+        };
+        let url = spawn_peer(peer.clone()).await;
+        let gossip = Gossip::new(GossipConfig {
+            peers: vec![url.clone()],
+            retry: fast_retry(2),
+        });
+        let transaction = Transaction::new(
+            Address("system".to_string()),
+            Address("recipient".to_string()),
+            10.0,
+        );
+
+        gossip.broadcast_transaction(&transaction).await;
+        assert!(gossip.is_unreachable(&url));
+        assert!(gossip.reachable_peers().is_empty());
+        assert_eq!(peer.attempts.load(Ordering::SeqCst), 2);
+
+        // Later broadcasts don't try it again
+        gossip.broadcast_transaction(&transaction).await;
+        assert_eq!(peer.attempts.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,6 +1,12 @@
 // Re-export modules for testing and library usage
 pub mod api;
+pub mod autominer;
 pub mod blockchain;
+pub mod config;
+pub mod gossip;
+pub mod logging;
+pub mod reload;
+pub mod webhook;
 
 // Re-export main types for convenience
 pub use blockchain::block::Block;
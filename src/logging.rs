@@ -0,0 +1,118 @@
+use std::io::Write;
+use std::str::FromStr;
+
+use chrono::{SecondsFormat, Utc};
+use log::{LevelFilter, Record};
+use serde_json::json;
+
+/// Output format of the application logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human readable output for local development
+    #[default]
+    Pretty,
+    /// One JSON object per line for log aggregators
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pretty" | "text" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "Unknown log format '{}', expected 'pretty' or 'json'",
+                other
+            )),
+        }
+    }
+}
+
+/// Initializes the global logger with the given format.
+///
+/// Levels are taken from `RUST_LOG` and default to `info`. Returns false if a
+/// logger was already installed, so it is safe to call more than once (e.g. from tests).
+pub fn init_logging(format: LogFormat) -> bool {
+    // Without RUST_LOG the logger lets every level through and the global
+    // maximum decides, so that set_log_level can raise it later
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"));
+
+    if format == LogFormat::Json {
+        builder.format(|buf, record| writeln!(buf, "{}", json_line(record)));
+    }
+
+    let installed = builder.try_init().is_ok();
+    if installed {
+        log::set_max_level(default_level());
+    }
+    installed
+}
+
+/// Caps the level of the application logs while the node runs, e.g. after a
+/// config reload; `None` restores the level set by `RUST_LOG` or `info`.
+/// `RUST_LOG`, when set, still filters what is logged.
+pub fn set_log_level(level: Option<LevelFilter>) {
+    log::set_max_level(level.unwrap_or_else(default_level));
+}
+
+/// Maximum level before any is configured: whatever `RUST_LOG` lets through,
+/// or `info` without it
+fn default_level() -> LevelFilter {
+    if std::env::var_os("RUST_LOG").is_some() {
+        LevelFilter::Trace
+    } else {
+        LevelFilter::Info
+    }
+}
+
+/// Renders a log record as a single-line JSON object
+fn json_line(record: &Record) -> String {
+    json!({
+        "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        "level": record.level().to_string(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    #[test]
+    fn test_parse_log_format() {
+        assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert_eq!("JSON".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert_eq!("pretty".parse::<LogFormat>(), Ok(LogFormat::Pretty));
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn test_json_line_fields() {
+        let line = json_line(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("fchain::api")
+                .args(format_args!("Block {} mined", 7))
+                .build(),
+        );
+
+        assert!(!line.contains('\n'));
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["level"], "WARN");
+        assert_eq!(value["target"], "fchain::api");
+        assert_eq!(value["message"], "Block 7 mined");
+        assert!(value["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn test_init_logging_twice_does_not_panic() {
+        init_logging(LogFormat::Json);
+        assert!(!init_logging(LogFormat::Pretty));
+    }
+}
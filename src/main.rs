@@ -1,29 +1,146 @@
-use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use log::info;
 
-mod api;
-mod blockchain;
-
-use blockchain::create_shared_blockchain;
+use fchain::api;
+use fchain::autominer;
+use fchain::blockchain::{genesis, BlockLog, Blockchain};
+use fchain::config::{CliArgs, Config};
+use fchain::gossip::{self, Gossip, GossipConfig};
+use fchain::logging::{init_logging, set_log_level};
 
 #[tokio::main]
 async fn main() {
-    // Initialize logger
-    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
+    // Read the command line, e.g. `--config fchain.toml --log-format json`
+    let cli = match CliArgs::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    init_logging(cli.log_format);
+
+    // Merge the config file, the flags and the FCHAIN_* environment variables
+    let config = match Config::load(&cli, |name| std::env::var(name).ok()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    set_log_level(config.log_level());
+
+    // Create a new blockchain, or recover it from the block log if persistence is enabled
+    let new_blockchain = || match &config.genesis_allocations {
+        Some(path) => {
+            info!("Seeding genesis block from {}", path.display());
+            let allocations = genesis::read_allocations(path).expect("Failed to read allocations");
+            Blockchain::try_new_with_allocations(
+                config.difficulty,
+                config.mining_reward,
+                &allocations,
+            )
+            .expect("Failed to create genesis block")
+        }
+        None => Blockchain::new(config.difficulty, config.mining_reward),
+    };
+    // Settings are applied before recovery, since validating the recovered
+    // blocks depends on some of them, e.g. the network
+    let configure = |blockchain: Blockchain| {
+        blockchain
+            .with_target_block_time(config.target_block_time)
+            .with_max_future_drift(Duration::from_secs(config.max_future_drift))
+            .with_max_reorg_depth(config.max_reorg_depth)
+            .with_tx_commitment(config.tx_commitment())
+            .with_max_mempool_size(config.limits.max_mempool_size)
+            .with_amount_granularity(config.amount_granularity)
+            .with_allow_zero_amount(config.allow_zero_amount)
+            .with_permissioned(config.permissioned)
+            .with_network(config.network)
+            .with_consensus(config.consensus_mode())
+            .with_block_limits(
+                config.limits.max_block_transactions,
+                config.limits.max_block_bytes,
+            )
+    };
+    let blockchain = match config.block_log_path() {
+        Some(path) => {
+            let log = BlockLog::new(path);
+            if log.exists() {
+                info!("Recovering blockchain from {}", log.path().display());
+                let mut blockchain = configure(
+                    Blockchain::try_new(config.difficulty, config.mining_reward)
+                        .expect("Failed to create blockchain"),
+                );
+                blockchain
+                    .recover_store(Box::new(log))
+                    .expect("Failed to recover blockchain");
+                blockchain
+            } else {
+                info!("Persisting new blockchain to {}", log.path().display());
+                if let Some(dir) = log.path().parent() {
+                    std::fs::create_dir_all(dir).expect("Failed to create data directory");
+                }
+                let mut blockchain = configure(new_blockchain());
+                blockchain
+                    .enable_store(Box::new(log))
+                    .expect("Failed to create block log");
+                blockchain
+            }
+        }
+        None => configure(new_blockchain()),
+    };
+    let blockchain = match config.authority_signing_key() {
+        Some(key) => {
+            info!("Sealing blocks as a proof-of-authority authority");
+            blockchain.with_authority_key(key)
+        }
+        None => blockchain,
+    };
+    let blockchain = match config.keep_blocks {
+        Some(keep) => {
+            info!("Keeping the last {} blocks in memory", keep);
+            blockchain
+                .with_pruning(keep)
+                .expect("Failed to enable pruning")
+        }
+        None => blockchain,
+    };
+    let blockchain = Arc::new(Mutex::new(blockchain));
+
+    // Forward new pending transactions to the peers; a reload may add some later
+    let gossip = Arc::new(Gossip::new(GossipConfig {
+        peers: config.peers.clone(),
+        ..GossipConfig::default()
+    }));
+    gossip::spawn_gossip(&blockchain, gossip.clone());
 
-    // Create a new blockchain with difficulty 4 and mining reward 100
-    let blockchain = create_shared_blockchain(4, 100.0);
+    // Mine pending transactions in the background if automining is on
+    if let Some(autominer) = config.autominer_config() {
+        autominer::spawn_autominer(&blockchain, autominer);
+    }
 
-    // Create the API router
-    let app = api::create_router(blockchain);
+    // Create the API router, protecting mutating endpoints if a token is set
+    if config.auth_token.is_some() {
+        info!("Mutating endpoints require a bearer token");
+    }
+    if config.read_only {
+        info!("Running read-only: mining and transaction submission are disabled");
+    }
+    let app = api::create_router_with_config(blockchain.clone(), config.api_config());
 
-    // Define the address to run the server on
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3013));
+    // Apply a changed configuration on SIGHUP, e.g. `kill -HUP <pid>`
+    #[cfg(unix)]
+    fchain::reload::spawn_reload_on_sighup(cli, config.clone(), blockchain, gossip)
+        .expect("Failed to listen for SIGHUP");
 
-    info!("Starting blockchain server on {}", addr);
+    info!("Starting blockchain server on {}", config.bind_address);
 
     // Start the server
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    let listener = tokio::net::TcpListener::bind(config.bind_address)
+        .await
+        .unwrap();
     axum::serve(listener, app).await.unwrap();
 }
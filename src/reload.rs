@@ -0,0 +1,293 @@
+//! Reloading the configuration of a running node.
+//!
+//! On SIGHUP the node reads its configuration again, from the same file,
+//! flags and environment as at startup, and applies the tunables to the
+//! running chain without losing its state: block timing, the mempool and
+//! block limits, transaction rules, the gossip peers and the log level.
+//! Settings that define the chain itself, such as the difficulty, the reward
+//! or the genesis allocations, can't change under a running node, and a
+//! reload that changes any of them is refused as a whole. Any other setting,
+//! e.g. the bind address, only takes effect after a restart.
+//!
+//! The difficulty is one of them: every block, past ones included, is
+//! checked against the difficulty the chain requires at its height, so
+//! changing it would make the node reject its own chain or blocks its peers
+//! still accept. Changing it at a given height takes a difficulty schedule
+//! shared by every node. The target block time stays reloadable.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{error, info, warn};
+use tokio::task::JoinHandle;
+
+use crate::blockchain::genesis::{allocations_of, read_allocations, GenesisAllocations};
+use crate::blockchain::SharedBlockchain;
+use crate::config::{CliArgs, Config, ConfigError};
+use crate::gossip::Gossip;
+use crate::logging::set_log_level;
+
+/// Records a changed tunable in `config`, logging the change
+fn update<T: PartialEq + Clone + Debug>(key: &str, field: &mut T, value: &T) {
+    if field != value {
+        info!("Reloaded {}: {:?} -> {:?}", key, field, value);
+        *field = value.clone();
+    }
+}
+
+/// Applies the reloadable settings of `new` to the running node, configured
+/// with `current` so far, and returns the configuration now in effect.
+///
+/// Fails, leaving the node untouched, if `new` changes a setting fixed by the
+/// chain. Other settings that need a restart are kept as they are, with a
+/// warning.
+pub fn apply_reload(
+    current: &Config,
+    new: &Config,
+    blockchain: &SharedBlockchain,
+    gossip: &Gossip,
+) -> Result<Config, ConfigError> {
+    let fixed = [
+        ("difficulty", current.difficulty != new.difficulty),
+        ("mining_reward", current.mining_reward != new.mining_reward),
+        (
+            "genesis_allocations",
+            genesis_allocations_changed(new, blockchain)?,
+        ),
+        (
+            "legacy_block_hashes",
+            current.legacy_block_hashes != new.legacy_block_hashes,
+        ),
+        ("network", current.network != new.network),
+        ("authorities", current.authorities != new.authorities),
+        ("permissioned", current.permissioned != new.permissioned),
+    ];
+    if let Some((key, _)) = fixed.into_iter().find(|&(_, changed)| changed) {
+        return Err(ConfigError::invalid(
+            key,
+            "is fixed by the chain and can't be reloaded",
+        ));
+    }
+
+    let mut config = current.clone();
+    update(
+        "target_block_time",
+        &mut config.target_block_time,
+        &new.target_block_time,
+    );
+    update(
+        "max_future_drift",
+        &mut config.max_future_drift,
+        &new.max_future_drift,
+    );
+    update(
+        "max_reorg_depth",
+        &mut config.max_reorg_depth,
+        &new.max_reorg_depth,
+    );
+    update(
+        "amount_granularity",
+        &mut config.amount_granularity,
+        &new.amount_granularity,
+    );
+    update(
+        "allow_zero_amount",
+        &mut config.allow_zero_amount,
+        &new.allow_zero_amount,
+    );
+    update(
+        "limits.max_mempool_size",
+        &mut config.limits.max_mempool_size,
+        &new.limits.max_mempool_size,
+    );
+    update(
+        "limits.max_block_transactions",
+        &mut config.limits.max_block_transactions,
+        &new.limits.max_block_transactions,
+    );
+    update(
+        "limits.max_block_bytes",
+        &mut config.limits.max_block_bytes,
+        &new.limits.max_block_bytes,
+    );
+    update("peers", &mut config.peers, &new.peers);
+    update("log_level", &mut config.log_level, &new.log_level);
+    if config != *new {
+        warn!("Some changed settings only take effect after a restart");
+    }
+
+    {
+        let mut blockchain = blockchain.lock().unwrap();
+        blockchain.target_block_time_secs = config.target_block_time;
+        blockchain.max_future_drift = Duration::from_secs(config.max_future_drift);
+        blockchain.max_reorg_depth = config.max_reorg_depth;
+        blockchain.amount_granularity = config.amount_granularity;
+        blockchain.allow_zero_amount = config.allow_zero_amount;
+        blockchain.set_max_mempool_size(config.limits.max_mempool_size);
+        blockchain.max_block_transactions = config.limits.max_block_transactions;
+        blockchain.max_block_bytes = config.limits.max_block_bytes;
+    }
+    if config.peers != current.peers {
+        gossip.set_peers(config.peers.clone());
+    }
+    if config.log_level != current.log_level {
+        set_log_level(config.log_level());
+    }
+    Ok(config)
+}
+
+/// Returns true if the genesis allocations `new` points to differ from the
+/// ones the running chain's genesis block pays out. The file is read again,
+/// so an edit to it counts as a change even if its path stays the same.
+fn genesis_allocations_changed(
+    new: &Config,
+    blockchain: &SharedBlockchain,
+) -> Result<bool, ConfigError> {
+    let allocations = match &new.genesis_allocations {
+        Some(path) => read_allocations(path)
+            .map_err(|err| ConfigError::invalid("genesis_allocations", err.to_string()))?,
+        None => GenesisAllocations::new(),
+    };
+    let blockchain = blockchain.lock().unwrap();
+    Ok(blockchain
+        .genesis_block()
+        .is_some_and(|genesis| allocations_of(genesis) != allocations))
+}
+
+/// Starts reloading the configuration on every SIGHUP, reading it the way
+/// `main` did at startup. A reload that fails leaves the node as it was.
+#[cfg(unix)]
+pub fn spawn_reload_on_sighup(
+    cli: CliArgs,
+    config: Config,
+    blockchain: SharedBlockchain,
+    gossip: Arc<Gossip>,
+) -> std::io::Result<JoinHandle<()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = signal(SignalKind::hangup())?;
+    Ok(tokio::spawn(async move {
+        let mut config = config;
+        while hangups.recv().await.is_some() {
+            info!("Received SIGHUP, reloading the configuration");
+            let result = Config::load(&cli, |name| std::env::var(name).ok())
+                .and_then(|new| apply_reload(&config, &new, &blockchain, &gossip));
+            match result {
+                Ok(reloaded) => config = reloaded,
+                Err(err) => error!("Keeping the current configuration: {}", err),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::crypto::Address;
+    use crate::blockchain::test_utils::{MINER, OTHER_MINER};
+    use crate::blockchain::{create_shared_blockchain, Blockchain, Transaction};
+    use crate::gossip::GossipConfig;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_reload_applies_tunables_and_keeps_fixed_settings() {
+        let current = Config {
+            difficulty: 1,
+            mining_reward: 50.0,
+            ..Config::default()
+        };
+        let blockchain = create_shared_blockchain(1, 50.0);
+        let gossip = Gossip::new(GossipConfig::default());
+
+        let mut new = current.clone();
+        new.limits.max_mempool_size = 2;
+        new.peers = vec!["http://10.0.0.2:3013/".to_string()];
+        new.bind_address = "127.0.0.1:4000".parse().unwrap();
+        let reloaded = apply_reload(&current, &new, &blockchain, &gossip).unwrap();
+
+        assert_eq!(reloaded.limits.max_mempool_size, 2);
+        assert_eq!(blockchain.lock().unwrap().max_mempool_size, 2);
+        assert_eq!(gossip.peers(), vec!["http://10.0.0.2:3013"]);
+        // The bind address waits for a restart
+        assert_eq!(reloaded.bind_address, current.bind_address);
+
+        // Changing the difficulty refuses the whole reload
+        let mut refused = reloaded.clone();
+        refused.difficulty = 3;
+        refused.limits.max_mempool_size = 5;
+        let result = apply_reload(&reloaded, &refused, &blockchain, &gossip);
+        assert!(matches!(result, Err(ConfigError::Invalid { key, .. }) if key == "difficulty"));
+
+        // So does switching how blocks commit to their transactions
+        let mut refused = reloaded.clone();
+        refused.legacy_block_hashes = true;
+        let result = apply_reload(&reloaded, &refused, &blockchain, &gossip);
+        assert!(
+            matches!(result, Err(ConfigError::Invalid { key, .. }) if key == "legacy_block_hashes")
+        );
+
+        let blockchain = blockchain.lock().unwrap();
+        assert_eq!(blockchain.difficulty, 1);
+        assert_eq!(blockchain.mining_reward, 50.0);
+        assert_eq!(blockchain.max_mempool_size, 2);
+    }
+
+    #[test]
+    fn test_lowering_the_mempool_cap_evicts_the_cheapest() {
+        let current = Config::default();
+        let blockchain = create_shared_blockchain(1, 50.0);
+        let gossip = Gossip::new(GossipConfig::default());
+        let fees = [0.3, 0.1, 0.2];
+        blockchain.lock().unwrap().pending_transactions = fees
+            .iter()
+            .enumerate()
+            .map(|(i, &fee)| {
+                Transaction::new_with_fee(
+                    Address("sender".to_string()),
+                    Address(format!("recipient{}", i)),
+                    1.0,
+                    fee,
+                )
+            })
+            .collect();
+
+        let mut new = current.clone();
+        new.limits.max_mempool_size = 2;
+        apply_reload(&current, &new, &blockchain, &gossip).unwrap();
+
+        let blockchain = blockchain.lock().unwrap();
+        let kept: Vec<f64> = blockchain
+            .pending_transactions
+            .iter()
+            .map(|tx| tx.fee)
+            .collect();
+        assert_eq!(kept, vec![0.3, 0.2]);
+    }
+
+    #[test]
+    fn test_reload_compares_genesis_allocation_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("allocations.json");
+        std::fs::write(&path, format!(r#"{{"{}": 1000.0}}"#, MINER)).unwrap();
+        let allocations = read_allocations(&path).unwrap();
+        let blockchain = Arc::new(Mutex::new(
+            Blockchain::try_new_with_allocations(1, 50.0, &allocations).unwrap(),
+        ));
+        let gossip = Gossip::new(GossipConfig::default());
+        let current = Config {
+            genesis_allocations: Some(path.clone()),
+            ..Config::default()
+        };
+
+        // The same file reloads fine
+        assert!(apply_reload(&current, &current.clone(), &blockchain, &gossip).is_ok());
+
+        // Editing it in place is a change all the same
+        std::fs::write(&path, format!(r#"{{"{}": 1000.0}}"#, OTHER_MINER)).unwrap();
+        let result = apply_reload(&current, &current.clone(), &blockchain, &gossip);
+        assert!(
+            matches!(result, Err(ConfigError::Invalid { key, .. }) if key == "genesis_allocations")
+        );
+    }
+}
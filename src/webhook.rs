@@ -0,0 +1,230 @@
+//! Notifications about new blocks for external integrations.
+//!
+//! When a block is mined through the API, the node POSTs a `blockMined` event
+//! and then a `transactionConfirmed` event for every transaction in it to the
+//! configured URL. Each body is signed with HMAC-SHA256 over its exact bytes,
+//! sent hex encoded in the [`SIGNATURE_HEADER`] header as `sha256=<hex>`, so
+//! the receiver can check that the event came from a node knowing the secret.
+
+use hmac::{Hmac, Mac};
+use log::{debug, warn};
+use serde::Serialize;
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::blockchain::Block;
+use crate::gossip::{retry_with_backoff, RetryPolicy};
+
+/// Header carrying the HMAC of the request body
+pub const SIGNATURE_HEADER: &str = "X-Fchain-Signature";
+
+/// Reasons an event could not be delivered to the webhook
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("webhook answered with status {0}")]
+    Status(u16),
+}
+
+/// Where to send events and the secret to sign them with
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// URL every event is POSTed to
+    pub url: String,
+    /// Shared secret keying the HMAC
+    pub secret: String,
+    /// Retry policy applied to every delivery
+    pub retry: RetryPolicy,
+}
+
+/// Body of a webhook request
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum WebhookEvent {
+    /// A block was mined and appended to the chain
+    BlockMined { block: Block },
+    /// A transaction was included in a block
+    #[serde(rename_all = "camelCase")]
+    TransactionConfirmed { hash: String, block_index: u64 },
+}
+
+/// Delivers signed events to the configured webhook
+#[derive(Debug)]
+pub struct Webhook {
+    client: reqwest::Client,
+    config: WebhookConfig,
+}
+
+impl Webhook {
+    /// Creates a dispatcher for the given configuration
+    pub fn new(config: WebhookConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config,
+        }
+    }
+
+    /// Value of the signature header for a body
+    pub fn sign(&self, body: &[u8]) -> String {
+        format!("sha256={}", hmac_sha256(&self.config.secret, body))
+    }
+
+    /// Sends the events for a newly mined block: the block itself, then one
+    /// per transaction it confirms
+    pub async fn notify_block_mined(&self, block: &Block) {
+        self.deliver(&WebhookEvent::BlockMined {
+            block: block.clone(),
+        })
+        .await;
+        for transaction in &block.transactions {
+            self.deliver(&WebhookEvent::TransactionConfirmed {
+                hash: transaction.hash.clone(),
+                block_index: block.index,
+            })
+            .await;
+        }
+    }
+
+    /// Sends one event, retrying failed deliveries. Events that still can't
+    /// be delivered after the last attempt are dropped.
+    pub async fn deliver(&self, event: &WebhookEvent) {
+        let body = match serde_json::to_vec(event) {
+            Ok(body) => body,
+            Err(err) => {
+                warn!("Failed to serialize webhook event: {}", err);
+                return;
+            }
+        };
+        let signature = self.sign(&body);
+
+        let result = retry_with_backoff(&self.config.retry, |attempt| {
+            debug!(
+                "Sending webhook event to {} (attempt {})",
+                self.config.url, attempt
+            );
+            self.send(body.clone(), &signature)
+        })
+        .await;
+
+        if let Err(err) = result {
+            warn!(
+                "Dropping webhook event after {} attempts: {}",
+                self.config.retry.max_attempts, err
+            );
+        }
+    }
+
+    async fn send(&self, body: Vec<u8>, signature: &str) -> Result<(), WebhookError> {
+        let response = self
+            .client
+            .post(&self.config.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .body(body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(WebhookError::Status(status.as_u16()));
+        }
+        Ok(())
+    }
+}
+
+/// Hex encoded HMAC-SHA256 of a message
+pub fn hmac_sha256(secret: &str, message: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(message);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use axum::{
+        body::Bytes, extract::State, http::HeaderMap, http::StatusCode, routing::post, Router,
+    };
+
+    use super::*;
+    use crate::blockchain::test_utils::MINER;
+    use crate::blockchain::Blockchain;
+
+    /// Requests received by the mock receiver, as (signature header, body)
+    type Received = Arc<Mutex<Vec<(String, Bytes)>>>;
+
+    async fn receive(
+        State(received): State<Received>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> StatusCode {
+        let signature = headers
+            .get(SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        received.lock().unwrap().push((signature, body));
+        StatusCode::OK
+    }
+
+    async fn spawn_receiver(received: Received) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(received);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/hook", addr)
+    }
+
+    #[test]
+    fn test_hmac_matches_rfc_4231() {
+        // Test case 2 of RFC 4231
+        assert_eq!(
+            hmac_sha256("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_block_event_is_signed() {
+        let received = Received::default();
+        let url = spawn_receiver(received.clone()).await;
+        let webhook = Webhook::new(WebhookConfig {
+            url,
+            secret: "shared-secret".to_string(),
+            retry: RetryPolicy {
+                max_attempts: 2,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(5),
+            },
+        });
+        let block = Blockchain::new(1, 50.0)
+            .mine_pending_transactions(MINER)
+            .unwrap();
+
+        webhook.notify_block_mined(&block).await;
+
+        let received = received.lock().unwrap();
+        // The block, then its only (reward) transaction
+        assert_eq!(received.len(), 2);
+        let (signature, body) = &received[0];
+        assert_eq!(
+            *signature,
+            format!("sha256={}", hmac_sha256("shared-secret", body))
+        );
+        let event: serde_json::Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(event["event"], "blockMined");
+        assert_eq!(event["block"]["hash"], block.hash);
+
+        let event: serde_json::Value = serde_json::from_slice(&received[1].1).unwrap();
+        assert_eq!(event["event"], "transactionConfirmed");
+        assert_eq!(event["hash"], block.transactions[0].hash);
+        assert_eq!(event["blockIndex"], 1);
+    }
+}
@@ -3,8 +3,33 @@ mod routes;
 #[cfg(test)]
 pub(crate) mod test_utils {
     use axum_test::TestServer;
+    use ed25519_dalek::{Signer, SigningKey};
+    use fchain::api::ApiConfig;
     use fchain::blockchain::create_shared_blockchain;
-    use fchain::blockchain::SharedBlockchain;
+    use fchain::blockchain::crypto::{PublicKeyHex, TransactionSignature};
+    use fchain::blockchain::{Address, SharedBlockchain, Transaction};
+    use serde_json::Value;
+
+    /// Well-formed address to send mining rewards to
+    pub const TEST_MINER: &str = "1111111111111111111111111111111111111111";
+
+    /// JSON form of an amount: a decimal string with the `string-amounts`
+    /// feature, a number without it
+    pub fn amount(value: f64) -> Value {
+        if cfg!(feature = "string-amounts") {
+            Value::String(value.to_string())
+        } else {
+            Value::from(value)
+        }
+    }
+
+    /// Reads an amount written in either JSON form, see [`amount`]
+    pub fn amount_of(value: &Value) -> f64 {
+        value
+            .as_f64()
+            .or_else(|| value.as_str().and_then(|text| text.parse().ok()))
+            .unwrap()
+    }
 
     /// Creates a test blockchain with predefined settings
     pub fn create_test_blockchain() -> SharedBlockchain {
@@ -18,4 +43,108 @@ pub(crate) mod test_utils {
         let app = fchain::api::create_router(blockchain);
         TestServer::new(app).unwrap()
     }
+
+    /// Creates a test server serving the given blockchain
+    pub async fn create_test_server_with_blockchain(blockchain: SharedBlockchain) -> TestServer {
+        let app = fchain::api::create_router(blockchain);
+        TestServer::new(app).unwrap()
+    }
+
+    /// Serves the API for the given blockchain on a local port, for clients
+    /// that need a real connection (e.g. WebSockets)
+    pub async fn spawn_test_server(blockchain: SharedBlockchain) -> std::net::SocketAddr {
+        spawn_configured_test_server(blockchain, ApiConfig::default()).await
+    }
+
+    /// Serves the API for the given blockchain on a local port with a custom configuration
+    pub async fn spawn_configured_test_server(
+        blockchain: SharedBlockchain,
+        config: ApiConfig,
+    ) -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = fchain::api::create_router_with_config(blockchain, config);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        addr
+    }
+
+    /// Creates a test server with the API router using a custom configuration
+    pub async fn create_test_server_with_config(config: ApiConfig) -> TestServer {
+        create_configured_test_server(create_test_blockchain(), config).await
+    }
+
+    /// Creates a test server around an existing blockchain with a custom API configuration
+    pub async fn create_configured_test_server(
+        blockchain: SharedBlockchain,
+        config: ApiConfig,
+    ) -> TestServer {
+        let app = fchain::api::create_router_with_config(blockchain, config);
+        TestServer::new(app).unwrap()
+    }
+
+    /// Deterministic key pair standing in for an external wallet
+    pub struct TestWallet {
+        signing_key: SigningKey,
+    }
+
+    impl TestWallet {
+        /// Creates a wallet whose key is derived from a single seed byte
+        pub fn new(seed: u8) -> Self {
+            Self {
+                signing_key: SigningKey::from_bytes(&[seed; 32]),
+            }
+        }
+
+        /// Returns the hex encoded public key
+        pub fn public_key(&self) -> String {
+            hex::encode(self.signing_key.verifying_key().to_bytes())
+        }
+
+        /// Returns the wallet address
+        pub fn address(&self) -> String {
+            PublicKeyHex(self.public_key()).to_address().unwrap().0
+        }
+
+        /// Signs an arbitrary message, returning the hex encoded signature
+        pub fn sign_message(&self, message: &[u8]) -> String {
+            hex::encode(self.signing_key.sign(message).to_bytes())
+        }
+
+        /// Creates a signed transaction from this wallet
+        pub fn transfer(&self, recipient: &str, amount: f64, fee: f64) -> Transaction {
+            let mut transaction = Transaction::new_with_fee(
+                Address(self.address()),
+                Address(recipient.to_string()),
+                amount,
+                fee,
+            );
+            transaction.signature = Some(TransactionSignature(
+                self.sign_message(transaction.hash.as_bytes()),
+            ));
+            transaction.public_key = Some(PublicKeyHex(self.public_key()));
+            transaction
+        }
+
+        /// Creates a signed transaction carrying the given nonce
+        pub fn transfer_with_nonce(
+            &self,
+            recipient: &str,
+            amount: f64,
+            fee: f64,
+            nonce: u64,
+        ) -> Transaction {
+            let mut transaction = Transaction::new_with_fee(
+                Address(self.address()),
+                Address(recipient.to_string()),
+                amount,
+                fee,
+            )
+            .with_nonce(nonce);
+            transaction.signature = Some(TransactionSignature(
+                self.sign_message(transaction.hash.as_bytes()),
+            ));
+            transaction.public_key = Some(PublicKeyHex(self.public_key()));
+            transaction
+        }
+    }
 }
@@ -1,7 +1,24 @@
-use http::StatusCode;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{body::Bytes, extract::State, routing::post, Router};
+use futures_util::StreamExt;
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
 use serde_json::{json, Value};
 
-use super::test_utils::create_test_server;
+use fchain::api::ApiConfig;
+use fchain::blockchain::crypto::{Address, Network, PublicKeyHex, TransactionSignature};
+use fchain::blockchain::merkle::verify_merkle_proof;
+use fchain::blockchain::{create_shared_blockchain, BlockHeader, InclusionProof};
+use fchain::gossip::RetryPolicy;
+use fchain::webhook::{hmac_sha256, WebhookConfig, SIGNATURE_HEADER};
+use fchain::Block;
+
+use super::test_utils::{
+    amount, amount_of, create_configured_test_server, create_test_blockchain, create_test_server,
+    create_test_server_with_blockchain, create_test_server_with_config,
+    spawn_configured_test_server, spawn_test_server, TestWallet, TEST_MINER,
+};
 
 #[tokio::test]
 async fn test_get_blocks() {
@@ -21,7 +38,7 @@ async fn test_get_blocks() {
     // Check genesis block properties
     let genesis = &blocks[0];
     assert_eq!(genesis["index"], 0);
-    assert_eq!(genesis["previous_hash"], "0".repeat(64));
+    assert_eq!(genesis["previousHash"], "0".repeat(64));
     assert!(genesis["transactions"].as_array().unwrap().is_empty());
 }
 
@@ -42,7 +59,7 @@ async fn test_create_transaction() {
         .await;
 
     // Assert
-    response.assert_status(StatusCode::OK);
+    response.assert_status(StatusCode::ACCEPTED);
 
     let result: Value = response.json();
     assert!(result["message"].as_str().unwrap().contains("successfully"));
@@ -50,7 +67,7 @@ async fn test_create_transaction() {
     let tx = &result["transaction"];
     assert_eq!(tx["sender"], "system");
     assert_eq!(tx["recipient"], "recipient");
-    assert_eq!(tx["amount"], 10.0);
+    assert_eq!(tx["amount"], amount(10.0));
 }
 
 #[tokio::test]
@@ -60,7 +77,7 @@ async fn test_create_signed_transaction() {
 
     // First, mine a block to get some coins for the sender
     let mine_data = json!({
-        "miner_address": "sender_address"
+        "minerAddress": "sender_address"
     });
     server.post("/blocks/mine").json(&mine_data).await;
 
@@ -74,12 +91,12 @@ async fn test_create_signed_transaction() {
             "recipient": "recipient",
             "amount": 10.0,
             "signature": "system", // System transactions use "system" as signature
-            "public_key": null // System transactions don't need a public key
+            "publicKey": null // System transactions don't need a public key
         }))
         .await;
 
     // Assert
-    response.assert_status(StatusCode::OK);
+    response.assert_status(StatusCode::ACCEPTED);
 
     let result: Value = response.json();
     assert!(result["message"].as_str().unwrap().contains("successfully"));
@@ -87,7 +104,42 @@ async fn test_create_signed_transaction() {
     let tx = &result["transaction"];
     assert_eq!(tx["sender"], "system");
     assert_eq!(tx["recipient"], "recipient");
-    assert_eq!(tx["amount"], 10.0);
+    assert_eq!(tx["amount"], amount(10.0));
+}
+
+#[tokio::test]
+async fn test_accepted_transaction_points_to_its_status() {
+    // Arrange
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    server
+        .post("/transactions/raw")
+        .json(&wallet.transfer_with_nonce("recipient", 5.0, 0.1, 0))
+        .await;
+
+    // Act - a higher fee puts it ahead of the first transaction
+    let transaction = wallet.transfer_with_nonce("recipient", 5.0, 0.5, 1);
+    let response = server.post("/transactions/raw").json(&transaction).await;
+
+    // Assert
+    response.assert_status(StatusCode::ACCEPTED);
+    let location = response.header("location");
+    assert_eq!(
+        location.to_str().unwrap(),
+        format!("/transactions/{}", transaction.hash)
+    );
+    let body: Value = response.json();
+    assert_eq!(body["mempoolPosition"], 0);
+
+    let status = server.get(location.to_str().unwrap()).await;
+    status.assert_status_ok();
+    let status: Value = status.json();
+    assert_eq!(status["status"]["status"], "pending");
+    assert_eq!(status["transaction"]["hash"], transaction.hash);
 }
 
 #[tokio::test]
@@ -106,7 +158,7 @@ async fn test_mine_block() {
 
     // Act - Mine a block
     let mine_data = json!({
-        "miner_address": "test_miner"
+        "minerAddress": TEST_MINER
     });
     let response = server.post("/blocks/mine").json(&mine_data).await;
 
@@ -116,6 +168,7 @@ async fn test_mine_block() {
     let body: Value = response.json();
     assert_eq!(body["message"], "Block mined successfully");
     assert_eq!(body["block"]["index"], 1); // Genesis is 0, this should be 1
+    assert!(body["block"]["previousHash"].is_string()); // Fields are camelCase
 
     // Verify block was added to chain
     let blocks_response = server.get("/blocks").await;
@@ -223,7 +276,7 @@ async fn test_insufficient_balance() {
         "recipient": "recipient",
         "amount": 20.0,
         "signature": "valid_signature",
-        "public_key": "valid_public_key"
+        "publicKey": "valid_public_key"
     });
 
     // Act
@@ -238,3 +291,2404 @@ async fn test_insufficient_balance() {
     let body: Value = response.json();
     assert!(body.get("error").is_some());
 }
+
+#[tokio::test]
+async fn test_transaction_rejections_carry_error_codes() {
+    // Arrange - a funded wallet with a pending transaction
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    let pending = wallet.transfer_with_nonce("recipient", 5.0, 0.5, 0);
+    server
+        .post("/transactions/raw")
+        .json(&pending)
+        .await
+        .assert_status(StatusCode::ACCEPTED);
+
+    let mut forged = wallet.transfer("recipient", 5.0, 0.0);
+    forged.signature = TestWallet::new(2).transfer("recipient", 5.0, 0.0).signature;
+    let cases = [
+        ("bad_signature", forged),
+        (
+            "insufficient_balance",
+            TestWallet::new(3).transfer("recipient", 5.0, 0.0),
+        ),
+        ("duplicate_transaction", pending.clone()),
+        (
+            "amount_out_of_range",
+            wallet.transfer("recipient", -5.0, 0.0),
+        ),
+        (
+            "replacement_fee_too_low",
+            wallet.transfer_with_nonce("someone else", 5.0, 0.1, 0),
+        ),
+    ];
+
+    for (code, transaction) in cases {
+        // Act
+        let response = server.post("/transactions/raw").json(&transaction).await;
+
+        // Assert
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: Value = response.json();
+        assert_eq!(body["code"], code, "{}", body["error"]);
+    }
+
+    let missing_key = server
+        .post("/transactions")
+        .json(&json!({
+            "sender": wallet.address(),
+            "recipient": "recipient",
+            "amount": 5.0,
+            "signature": "signature"
+        }))
+        .await;
+    missing_key.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(missing_key.json::<Value>()["code"], "missing_public_key");
+
+    let bad_miner = server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": "miner" }))
+        .await;
+    bad_miner.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(bad_miner.json::<Value>()["code"], "invalid_address");
+
+    let unknown = server.get("/search/nothing-here").await;
+    unknown.assert_status(StatusCode::NOT_FOUND);
+    assert_eq!(unknown.json::<Value>()["code"], "not_found");
+}
+
+#[tokio::test]
+async fn test_import_longer_chain() {
+    // Arrange - a peer with the same genesis and two more blocks
+    let server = create_test_server().await;
+
+    let mut peer = fchain::Blockchain::new(1, 50.0);
+    peer.mine_pending_transactions(TEST_MINER).unwrap();
+    peer.mine_pending_transactions(TEST_MINER).unwrap();
+
+    // Act
+    let response = server.post("/chain/import").json(&peer.chain).await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["length"], 3);
+
+    let blocks: Vec<Value> = server.get("/blocks").await.json();
+    assert_eq!(blocks.len(), 3);
+}
+
+#[tokio::test]
+async fn test_import_chain_with_mismatched_genesis() {
+    // Arrange - a peer whose genesis block differs from ours
+    let server = create_test_server().await;
+
+    let mut peer = fchain::Blockchain::new(1, 50.0);
+    peer.chain[0] = fchain::Block::new(0, vec![], "f".repeat(64), 1);
+    peer.mine_pending_transactions(TEST_MINER).unwrap();
+    peer.mine_pending_transactions(TEST_MINER).unwrap();
+    assert!(peer.is_chain_valid().unwrap());
+
+    // Act
+    let response = server.post("/chain/import").json(&peer.chain).await;
+
+    // Assert
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let body: Value = response.json();
+    assert!(body["error"].as_str().unwrap().contains("genesis mismatch"));
+
+    let blocks: Vec<Value> = server.get("/blocks").await.json();
+    assert_eq!(blocks.len(), 1);
+}
+
+#[tokio::test]
+async fn test_chain_stats_reports_supply() {
+    // Arrange - the test chain pays a reward of 50 per block
+    let server = create_test_server().await;
+    let mine_data = json!({ "minerAddress": TEST_MINER });
+    server.post("/blocks/mine").json(&mine_data).await;
+    server.post("/blocks/mine").json(&mine_data).await;
+
+    // Act
+    let response = server.get("/chain/stats").await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["height"], 2);
+    assert_eq!(body["totalSupply"], 100.0);
+    assert_eq!(body["supplyConserved"], true);
+    assert_eq!(body["unspendableSupply"], 0.0);
+}
+
+#[tokio::test]
+async fn test_oversized_body_is_rejected() {
+    // Arrange
+    let server = create_test_server().await;
+    let padding = "x".repeat(fchain::api::config::DEFAULT_MAX_BODY_BYTES);
+
+    // Act
+    let response = server
+        .post("/transactions")
+        .json(&json!({
+            "sender": "system",
+            "recipient": padding,
+            "amount": 10.0,
+            "signature": "system"
+        }))
+        .await;
+
+    // Assert
+    response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+
+    let pending: Vec<Value> = server.get("/transactions/pending").await.json();
+    assert!(pending.is_empty());
+}
+
+#[tokio::test]
+async fn test_import_has_higher_body_limit() {
+    // Arrange - a tiny limit for regular endpoints only
+    let server = create_test_server_with_config(ApiConfig {
+        max_body_bytes: 256,
+        ..ApiConfig::default()
+    })
+    .await;
+
+    let mut peer = fchain::Blockchain::new(1, 50.0);
+    peer.mine_pending_transactions(TEST_MINER).unwrap();
+    assert!(serde_json::to_vec(&peer.chain).unwrap().len() > 256);
+
+    // Act
+    let response = server.post("/chain/import").json(&peer.chain).await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_import_body_limit_is_enforced() {
+    // Arrange
+    let server = create_test_server_with_config(ApiConfig {
+        max_import_body_bytes: 256,
+        ..ApiConfig::default()
+    })
+    .await;
+
+    let mut peer = fchain::Blockchain::new(1, 50.0);
+    peer.mine_pending_transactions(TEST_MINER).unwrap();
+
+    // Act
+    let response = server.post("/chain/import").json(&peer.chain).await;
+
+    // Assert
+    response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn test_validate_address() {
+    // Arrange
+    let server = create_test_server().await;
+    let valid_address = "0123456789abcdef0123456789abcdef01234567";
+
+    // Act
+    let valid = server
+        .get(&format!("/address/{}/validate", valid_address))
+        .await;
+    let too_short = server.get("/address/abc123/validate").await;
+    let not_hex = server.get("/address/system/validate").await;
+    let mainnet = server
+        .get(&format!(
+            "/address/{}/validate",
+            TestWallet::new(1).address()
+        ))
+        .await;
+    let testnet_address = Address::versioned(Network::Testnet, &[1u8; 20]);
+    let testnet = server
+        .get(&format!("/address/{}/validate", testnet_address))
+        .await;
+
+    // Assert
+    valid.assert_status(StatusCode::OK);
+    let body: Value = valid.json();
+    assert_eq!(body["valid"], true);
+
+    let body: Value = too_short.json();
+    assert_eq!(body["valid"], false);
+    assert_eq!(body["message"], "Address must be 40 characters long, got 6");
+
+    let body: Value = not_hex.json();
+    assert_eq!(body["valid"], false);
+    assert_eq!(body["message"], "Address contains non-hex character 's'");
+
+    // The node is on mainnet, so testnet addresses are refused
+    assert_eq!(mainnet.json::<Value>()["valid"], true);
+    let body: Value = testnet.json();
+    assert_eq!(body["valid"], false);
+    assert_eq!(body["message"], "Address is for testnet, expected mainnet");
+}
+
+#[tokio::test]
+async fn test_submit_raw_transaction() {
+    // Arrange - fund the wallet with a mining reward
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+
+    let transaction = wallet.transfer("recipient", 20.0, 0.5);
+
+    // Act
+    let response = server.post("/transactions/raw").json(&transaction).await;
+
+    // Assert - the transaction is stored exactly as signed
+    response.assert_status(StatusCode::ACCEPTED);
+    let body: Value = response.json();
+    assert_eq!(body["transaction"]["hash"], transaction.hash);
+
+    let pending: Vec<fchain::Transaction> = server.get("/transactions/pending").await.json();
+    assert_eq!(pending, vec![transaction]);
+}
+
+#[tokio::test]
+async fn test_submit_raw_transaction_with_mismatched_hash() {
+    // Arrange
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+
+    // The client changes the amount after signing, so the hash no longer matches
+    let mut transaction = wallet.transfer("recipient", 20.0, 0.0);
+    transaction.amount = 40.0;
+
+    // Act
+    let response = server.post("/transactions/raw").json(&transaction).await;
+
+    // Assert
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let pending: Vec<Value> = server.get("/transactions/pending").await.json();
+    assert!(pending.is_empty());
+}
+
+#[tokio::test]
+async fn test_submit_raw_transaction_twice_is_rejected() {
+    // Arrange
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    let transaction = wallet.transfer("recipient", 20.0, 0.0);
+
+    // Act
+    let first = server.post("/transactions/raw").json(&transaction).await;
+    let replay = server.post("/transactions/raw").json(&transaction).await;
+
+    // Assert
+    first.assert_status(StatusCode::ACCEPTED);
+    replay.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_block_times() {
+    // Arrange
+    let server = create_test_server().await;
+    let mine_data = json!({ "minerAddress": TEST_MINER });
+    for _ in 0..3 {
+        server.post("/blocks/mine").json(&mine_data).await;
+    }
+
+    // Act
+    let response = server
+        .get("/chain/blocktimes")
+        .add_query_param("count", 10)
+        .await;
+
+    // Assert - three mined blocks give two intervals after the first one
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["intervals"].as_array().unwrap().len(), 2);
+    assert!(body["mean"].is_number());
+    assert!(body["median"].is_number());
+}
+
+#[tokio::test]
+async fn test_verify_signature() {
+    // Arrange
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    let other = TestWallet::new(2);
+    let message = "hello fchain";
+    let signature = wallet.sign_message(message.as_bytes());
+
+    // Act
+    let valid = server
+        .post("/wallet/verify")
+        .json(&json!({
+            "publicKey": wallet.public_key(),
+            "message": message,
+            "signature": signature,
+            "address": wallet.address()
+        }))
+        .await;
+    let tampered = server
+        .post("/wallet/verify")
+        .json(&json!({
+            "publicKey": wallet.public_key(),
+            "message": "hello fchain!",
+            "signature": signature
+        }))
+        .await;
+    let mismatched_address = server
+        .post("/wallet/verify")
+        .json(&json!({
+            "publicKey": wallet.public_key(),
+            "message": message,
+            "signature": signature,
+            "address": other.address()
+        }))
+        .await;
+
+    // Assert
+    valid.assert_status(StatusCode::OK);
+    let body: Value = valid.json();
+    assert_eq!(body["valid"], true);
+
+    tampered.assert_status(StatusCode::OK);
+    let body: Value = tampered.json();
+    assert_eq!(body["valid"], false);
+
+    mismatched_address.assert_status(StatusCode::OK);
+    let body: Value = mismatched_address.json();
+    assert_eq!(body["valid"], false);
+}
+
+#[tokio::test]
+async fn test_search() {
+    // Arrange - mine a block so there is a block, a transaction and an address to find
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    let mine_response = server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    let block: Value = mine_response.json::<Value>()["block"].clone();
+    let block_hash = block["hash"].as_str().unwrap();
+    let tx_hash = block["transactions"][0]["hash"].as_str().unwrap();
+
+    // Act
+    let by_index = server.get("/search/1").await;
+    let by_block_hash = server.get(&format!("/search/{}", block_hash)).await;
+    let by_tx_hash = server.get(&format!("/search/{}", tx_hash)).await;
+    let by_address = server.get(&format!("/search/{}", wallet.address())).await;
+    let unknown = server.get("/search/nothing-here").await;
+
+    // Assert
+    let body: Value = by_index.json();
+    assert_eq!(body["type"], "block");
+    assert_eq!(body["block"]["hash"], block_hash);
+
+    let body: Value = by_block_hash.json();
+    assert_eq!(body["type"], "block");
+    assert_eq!(body["block"]["index"], 1);
+
+    let body: Value = by_tx_hash.json();
+    assert_eq!(body["type"], "transaction");
+    assert_eq!(body["transaction"]["hash"], tx_hash);
+    assert_eq!(body["blockIndex"], 1);
+
+    let body: Value = by_address.json();
+    assert_eq!(body["type"], "address");
+    assert_eq!(body["address"], wallet.address());
+    assert_eq!(body["balance"], amount(50.0));
+
+    unknown.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_mine_block_rejects_invalid_miner_address() {
+    // Arrange
+    let server = create_test_server().await;
+
+    // Act
+    let empty = server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": "" }))
+        .await;
+    let malformed = server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": "not-an-address" }))
+        .await;
+
+    // Assert
+    empty.assert_status(StatusCode::BAD_REQUEST);
+    malformed.assert_status(StatusCode::BAD_REQUEST);
+    let body: Value = malformed.json();
+    assert_eq!(body["error"], "Invalid transaction: invalid miner address");
+
+    let blocks: Vec<Value> = server.get("/blocks").await.json();
+    assert_eq!(blocks.len(), 1);
+}
+
+#[tokio::test]
+async fn test_get_genesis() {
+    // Arrange
+    let blockchain = create_test_blockchain();
+    let server = create_test_server_with_blockchain(blockchain.clone()).await;
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+
+    // Act
+    let response = server.get("/chain/genesis").await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    let blockchain = blockchain.lock().unwrap();
+    assert_eq!(body["genesis"]["hash"], blockchain.chain[0].hash);
+    assert_eq!(body["chainId"], "fchain-local");
+    assert_eq!(body["difficulty"], 1);
+    assert_eq!(body["miningReward"], 50.0);
+    assert_eq!(body["hashAlgorithm"], "sha256");
+    assert_eq!(body["addressScheme"], "ed25519-sha256-160");
+}
+
+#[tokio::test]
+async fn test_get_transaction_status() {
+    // Arrange - a pending system transaction
+    let server = create_test_server().await;
+    let create_response = server
+        .post("/transactions")
+        .json(&json!({
+            "sender": "system",
+            "recipient": "recipient",
+            "amount": 10.0,
+            "signature": "system"
+        }))
+        .await;
+    let hash = create_response.json::<Value>()["transaction"]["hash"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Act & Assert - pending
+    let response = server.get(&format!("/transactions/{}", hash)).await;
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["status"]["status"], "pending");
+    assert_eq!(body["transaction"]["hash"], hash);
+
+    // Act & Assert - just mined
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+    let body: Value = server.get(&format!("/transactions/{}", hash)).await.json();
+    assert_eq!(body["status"]["status"], "confirmed");
+    assert_eq!(body["status"]["blockIndex"], 1);
+    assert_eq!(body["status"]["confirmations"], 1);
+
+    // Act & Assert - unknown hash
+    let body: Value = server.get("/transactions/unknown").await.json();
+    assert_eq!(body["status"]["status"], "unknown");
+    assert!(body["transaction"].is_null());
+}
+
+#[tokio::test]
+async fn test_get_transaction_by_id() {
+    // Arrange - a pending transaction from a funded wallet
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    let transaction = wallet.transfer_with_nonce("recipient", 5.0, 0.5, 0);
+    server
+        .post("/transactions/raw")
+        .json(&transaction)
+        .await
+        .assert_status(StatusCode::ACCEPTED);
+    let by_hash: Value = server
+        .get(&format!("/transactions/{}", transaction.hash))
+        .await
+        .json();
+    let id = by_hash["id"].as_str().unwrap().to_string();
+
+    // Act
+    let by_id: Value = server.get(&format!("/transactions/{}", id)).await.json();
+
+    // Assert
+    assert_ne!(id, transaction.hash);
+    assert_eq!(by_id["hash"], transaction.hash);
+    assert_eq!(by_id["id"], id);
+    assert_eq!(by_id["status"]["status"], "pending");
+}
+
+#[tokio::test]
+async fn test_blocks_binary_content_negotiation() {
+    // Arrange
+    let blockchain = create_test_blockchain();
+    let server = create_test_server_with_blockchain(blockchain.clone()).await;
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+    let expected = blockchain.lock().unwrap().chain.clone();
+
+    for path in ["/blocks", "/chain/export"] {
+        // Act
+        let binary = server
+            .get(path)
+            .add_header(
+                HeaderName::from_static("accept"),
+                HeaderValue::from_static("application/octet-stream"),
+            )
+            .await;
+        let default = server.get(path).await;
+
+        // Assert
+        binary.assert_status(StatusCode::OK);
+        assert_eq!(
+            binary.header("content-type"),
+            "application/octet-stream",
+            "{}",
+            path
+        );
+        let blocks: Vec<Block> = bincode::deserialize(binary.as_bytes()).unwrap();
+        assert_eq!(blocks, expected);
+
+        default.assert_status(StatusCode::OK);
+        assert_eq!(default.header("content-type"), "application/json");
+        let blocks: Vec<Block> = default.json();
+        assert_eq!(blocks, expected);
+    }
+}
+
+#[tokio::test]
+async fn test_mine_requires_bearer_token_when_configured() {
+    // Arrange
+    let server = create_test_server_with_config(ApiConfig {
+        auth_token: Some("s3cret".to_string()),
+        ..ApiConfig::default()
+    })
+    .await;
+    let mine_data = json!({ "minerAddress": TEST_MINER });
+
+    // Act
+    let missing = server.post("/blocks/mine").json(&mine_data).await;
+    let wrong = server
+        .post("/blocks/mine")
+        .add_header(
+            HeaderName::from_static("authorization"),
+            HeaderValue::from_static("Bearer wrong"),
+        )
+        .json(&mine_data)
+        .await;
+    let authorized = server
+        .post("/blocks/mine")
+        .add_header(
+            HeaderName::from_static("authorization"),
+            HeaderValue::from_static("Bearer s3cret"),
+        )
+        .json(&mine_data)
+        .await;
+    let read = server.get("/blocks").await;
+
+    // Assert
+    missing.assert_status(StatusCode::UNAUTHORIZED);
+    wrong.assert_status(StatusCode::UNAUTHORIZED);
+    authorized.assert_status(StatusCode::OK);
+    let body: Value = authorized.json();
+    assert_eq!(body["block"]["index"], 1);
+
+    // Read endpoints stay public
+    read.assert_status(StatusCode::OK);
+    let blocks: Vec<Value> = read.json();
+    assert_eq!(blocks.len(), 2);
+}
+
+#[tokio::test]
+async fn test_get_latest_blocks() {
+    // Arrange
+    let server = create_test_server().await;
+    let mine_data = json!({ "minerAddress": TEST_MINER });
+    for _ in 0..4 {
+        server.post("/blocks/mine").json(&mine_data).await;
+    }
+
+    // Act
+    let latest = server
+        .get("/blocks/latest")
+        .add_query_param("count", 3)
+        .await;
+    let more_than_chain = server
+        .get("/blocks/latest")
+        .add_query_param("count", 50)
+        .await;
+
+    // Assert
+    latest.assert_status(StatusCode::OK);
+    let indexes: Vec<u64> = latest
+        .json::<Vec<Value>>()
+        .iter()
+        .map(|block| block["index"].as_u64().unwrap())
+        .collect();
+    assert_eq!(indexes, vec![4, 3, 2]);
+    let confirmations: Vec<u64> = latest
+        .json::<Vec<Value>>()
+        .iter()
+        .map(|block| block["confirmations"].as_u64().unwrap())
+        .collect();
+    assert_eq!(confirmations, vec![1, 2, 3]);
+
+    let indexes: Vec<u64> = more_than_chain
+        .json::<Vec<Value>>()
+        .iter()
+        .map(|block| block["index"].as_u64().unwrap())
+        .collect();
+    assert_eq!(indexes, vec![4, 3, 2, 1, 0]);
+}
+
+#[tokio::test]
+async fn test_mempool_websocket() {
+    // Arrange - a live server for the socket and a test server for requests
+    let blockchain = create_test_blockchain();
+    let addr = spawn_test_server(blockchain.clone()).await;
+    let server = create_test_server_with_blockchain(blockchain).await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/mempool", addr))
+        .await
+        .unwrap();
+
+    // Act
+    let response = server
+        .post("/transactions")
+        .json(&json!({
+            "sender": "system",
+            "recipient": "recipient",
+            "amount": 10.0,
+            "signature": "system"
+        }))
+        .await;
+    let hash = response.json::<Value>()["transaction"]["hash"].clone();
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+
+    // Assert - the transaction arrives in full, then its removal when mined
+    let mut events = Vec::new();
+    while events.len() < 2 {
+        let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+            .await
+            .expect("no event received")
+            .unwrap()
+            .unwrap();
+        events.push(serde_json::from_str::<Value>(message.to_text().unwrap()).unwrap());
+    }
+
+    let added = &events[0];
+    assert_eq!(added["type"], "added");
+    assert_eq!(added["transaction"]["hash"], hash);
+    assert_eq!(added["transaction"]["amount"], amount(10.0));
+
+    let removed = &events[1];
+    assert_eq!(removed["type"], "removed");
+    assert_eq!(removed["hashes"], json!([hash]));
+}
+
+#[tokio::test]
+async fn test_get_top_addresses() {
+    // Arrange - the wallet mines 50 coins and pays 20 and 5 of them away
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    for (recipient, amount) in [("recipient1", 20.0), ("recipient2", 5.0)] {
+        server
+            .post("/transactions/raw")
+            .json(&wallet.transfer(recipient, amount, 0.0))
+            .await;
+    }
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+
+    // Act
+    let response = server
+        .get("/addresses/top")
+        .add_query_param("limit", 3)
+        .await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+    let top: Vec<(String, f64)> = response
+        .json::<Vec<Value>>()
+        .iter()
+        .map(|entry| {
+            (
+                entry["address"].as_str().unwrap().to_string(),
+                amount_of(&entry["balance"]),
+            )
+        })
+        .collect();
+    assert_eq!(
+        top,
+        vec![
+            (TEST_MINER.to_string(), 50.0),
+            (wallet.address(), 25.0),
+            ("recipient1".to_string(), 20.0),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_confirmations_increase_with_each_block() {
+    // Arrange
+    let server = create_test_server().await;
+    let create_response = server
+        .post("/transactions")
+        .json(&json!({
+            "sender": "system",
+            "recipient": "recipient",
+            "amount": 10.0,
+            "signature": "system"
+        }))
+        .await;
+    let hash = create_response.json::<Value>()["transaction"]["hash"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    for expected in 0..4 {
+        // Act
+        let status: Value = server.get(&format!("/transactions/{}", hash)).await.json();
+        let search: Value = server.get(&format!("/search/{}", hash)).await.json();
+
+        // Assert - pending transactions have no confirmations
+        assert_eq!(status["confirmations"], expected);
+        assert_eq!(search["confirmations"], expected);
+
+        server
+            .post("/blocks/mine")
+            .json(&json!({ "minerAddress": TEST_MINER }))
+            .await;
+    }
+}
+
+#[tokio::test]
+async fn test_validate_chain_verbose_lists_issues() {
+    // Arrange - tamper with the reward of the only mined block
+    let blockchain = create_test_blockchain();
+    let server = create_test_server_with_blockchain(blockchain.clone()).await;
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+    blockchain.lock().unwrap().chain[1].transactions[0].amount = 1_000.0;
+
+    // Act
+    let response = server
+        .get("/chain/validate")
+        .add_query_param("verbose", true)
+        .await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["valid"], false);
+    assert_eq!(body["issues"][0]["blockIndex"], 1);
+    assert_eq!(body["issues"][0]["kind"], "invalidTransaction");
+}
+
+#[tokio::test]
+async fn test_validate_chain_detailed_flags_the_corrupted_block() {
+    // Arrange - tamper with the nonce of a block in the middle of the chain
+    let blockchain = create_test_blockchain();
+    let server = create_test_server_with_blockchain(blockchain.clone()).await;
+    for _ in 0..3 {
+        server
+            .post("/blocks/mine")
+            .json(&json!({ "minerAddress": TEST_MINER }))
+            .await;
+    }
+    blockchain.lock().unwrap().chain[2].nonce += 1;
+
+    // Act
+    let response = server
+        .get("/chain/validate")
+        .add_query_param("detailed", true)
+        .await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["valid"], false);
+    assert!(body.get("issues").is_none());
+    let blocks = body["blocks"].as_array().unwrap();
+    assert_eq!(blocks.len(), 4);
+    for (index, block) in blocks.iter().enumerate() {
+        assert_eq!(block["index"], index);
+        assert_eq!(block["valid"], index != 2);
+        assert_eq!(block["powOk"], true);
+        assert_eq!(block["linkOk"], true);
+    }
+    assert_eq!(
+        blocks[2]["hash"],
+        blockchain.lock().unwrap().chain[2].hash.as_str()
+    );
+}
+
+#[tokio::test]
+async fn test_slow_request_times_out() {
+    // Arrange - another thread holds the chain lock, so mining hangs
+    let blockchain = create_test_blockchain();
+    let server = create_configured_test_server(
+        blockchain.clone(),
+        ApiConfig {
+            request_timeout: Duration::from_millis(100),
+            ..ApiConfig::default()
+        },
+    )
+    .await;
+    let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+    let holder = std::thread::spawn(move || {
+        let _guard = blockchain.lock().unwrap();
+        locked_tx.send(()).unwrap();
+        std::thread::sleep(Duration::from_millis(500));
+    });
+    locked_rx.recv().unwrap();
+
+    // Act
+    let response = server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+
+    // Assert
+    response.assert_status(StatusCode::REQUEST_TIMEOUT);
+    holder.join().unwrap();
+}
+
+#[tokio::test]
+async fn test_rebroadcast_pending_transaction() {
+    // Arrange
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    let transaction = wallet.transfer("recipient", 20.0, 0.0);
+    server.post("/transactions/raw").json(&transaction).await;
+
+    // Act
+    let response = server
+        .post(&format!("/transactions/{}/rebroadcast", transaction.hash))
+        .await;
+    let unknown = server.post("/transactions/unknown/rebroadcast").await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["status"]["status"], "pending");
+    assert_eq!(body["message"], "Transaction rebroadcast");
+    unknown.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_rebroadcast_drops_transaction_that_became_invalid() {
+    // Arrange - the sender's funds disappear when a longer chain is imported
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    let transaction = wallet.transfer("recipient", 20.0, 0.0);
+    server.post("/transactions/raw").json(&transaction).await;
+
+    let mut peer = fchain::Blockchain::new(1, 50.0);
+    for _ in 0..2 {
+        peer.mine_pending_transactions(TEST_MINER).unwrap();
+    }
+    server
+        .post("/chain/import")
+        .json(&peer.chain)
+        .await
+        .assert_status(StatusCode::OK);
+
+    // Act
+    let response = server
+        .post(&format!("/transactions/{}/rebroadcast", transaction.hash))
+        .await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["status"]["status"], "unknown");
+    assert!(body["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Transaction dropped"));
+    let pending: Vec<Value> = server.get("/transactions/pending").await.json();
+    assert!(pending.is_empty());
+}
+
+#[tokio::test]
+async fn test_get_headers_paginated() {
+    // Arrange
+    let blockchain = create_test_blockchain();
+    let server = create_test_server_with_blockchain(blockchain.clone()).await;
+    let mine_data = json!({ "minerAddress": TEST_MINER });
+    for _ in 0..4 {
+        server.post("/blocks/mine").json(&mine_data).await;
+    }
+
+    // Act
+    let response = server
+        .get("/headers")
+        .add_query_param("start", 1)
+        .add_query_param("limit", 2)
+        .await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+    let body: Vec<Value> = response.json();
+    assert!(body[0].get("transactions").is_none());
+
+    let headers: Vec<BlockHeader> = response.json();
+    let expected: Vec<BlockHeader> = blockchain.lock().unwrap().chain[1..3]
+        .iter()
+        .map(Block::header)
+        .collect();
+    assert_eq!(headers, expected);
+}
+
+#[tokio::test]
+async fn test_requests_beyond_the_concurrency_limit_get_503() {
+    // Arrange - another thread holds the chain lock, so two mining requests
+    // stay in flight and use up the limit
+    let blockchain = create_test_blockchain();
+    let addr = spawn_configured_test_server(
+        blockchain.clone(),
+        ApiConfig {
+            max_concurrent_requests: Some(2),
+            ..ApiConfig::default()
+        },
+    )
+    .await;
+    let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+    let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+    let holder = std::thread::spawn({
+        let blockchain = blockchain.clone();
+        move || {
+            let _guard = blockchain.lock().unwrap();
+            locked_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        }
+    });
+    locked_rx.recv().unwrap();
+    let client = reqwest::Client::new();
+    let mine = || {
+        client
+            .post(format!("http://{}/blocks/mine", addr))
+            .json(&json!({ "minerAddress": TEST_MINER }))
+            .send()
+    };
+    let in_flight = [tokio::spawn(mine()), tokio::spawn(mine())];
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Act
+    let response = tokio::time::timeout(Duration::from_secs(5), mine())
+        .await
+        .expect("request over the limit hung")
+        .unwrap();
+
+    // Assert - the excess request is shed, and the others finish once the lock is free
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.headers().contains_key("retry-after"));
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "server_busy");
+    release_tx.send(()).unwrap();
+    holder.join().unwrap();
+    for request in in_flight {
+        assert_eq!(request.await.unwrap().unwrap().status(), StatusCode::OK);
+    }
+    assert_eq!(blockchain.lock().unwrap().chain_length(), 3);
+}
+
+#[tokio::test]
+async fn test_concurrent_mines_never_mine_a_transaction_twice() {
+    // Arrange - a funded wallet with three pending transfers
+    let blockchain = create_test_blockchain();
+    let server = create_test_server_with_blockchain(blockchain.clone()).await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    let mut hashes = Vec::new();
+    for nonce in 0..3 {
+        let transaction = wallet.transfer_with_nonce("recipient", 5.0, 0.5, nonce);
+        server
+            .post("/transactions/raw")
+            .json(&transaction)
+            .await
+            .assert_status(StatusCode::ACCEPTED);
+        hashes.push(transaction.hash);
+    }
+
+    // Act - both requests reach a live server at once
+    let addr = spawn_test_server(blockchain.clone()).await;
+    let client = reqwest::Client::new();
+    let mine = || {
+        client
+            .post(format!("http://{}/blocks/mine", addr))
+            .json(&json!({ "minerAddress": TEST_MINER }))
+            .send()
+    };
+    let (first, second) = tokio::join!(mine(), mine());
+
+    // Assert
+    let (first, second) = (first.unwrap(), second.unwrap());
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(second.status(), StatusCode::OK);
+    let first: Value = first.json().await.unwrap();
+    let second: Value = second.json().await.unwrap();
+    assert_ne!(first["block"]["index"], second["block"]["index"]);
+
+    let blockchain = blockchain.lock().unwrap();
+    assert!(blockchain.pending_transactions.is_empty());
+    for hash in &hashes {
+        let inclusions = blockchain
+            .chain
+            .iter()
+            .flat_map(|block| block.transactions.iter())
+            .filter(|tx| &tx.hash == hash)
+            .count();
+        assert_eq!(
+            inclusions, 1,
+            "transaction {} mined {} times",
+            hash, inclusions
+        );
+    }
+    assert_eq!(blockchain.get_balance("recipient"), 15.0);
+}
+
+#[tokio::test]
+async fn test_get_block_range() {
+    // Arrange
+    let blockchain = create_test_blockchain();
+    let server = create_test_server_with_blockchain(blockchain.clone()).await;
+    let mine_data = json!({ "minerAddress": TEST_MINER });
+    for _ in 0..4 {
+        server.post("/blocks/mine").json(&mine_data).await;
+    }
+
+    // Act
+    let response = server
+        .get("/blocks/range")
+        .add_query_param("from", 1)
+        .add_query_param("to", 3)
+        .await;
+    let binary = server
+        .get("/blocks/range")
+        .add_query_param("from", 1)
+        .add_query_param("to", 3)
+        .add_header(
+            HeaderName::from_static("accept"),
+            HeaderValue::from_static("application/octet-stream"),
+        )
+        .await;
+
+    // Assert
+    let expected = blockchain.lock().unwrap().chain[1..=3].to_vec();
+    response.assert_status(StatusCode::OK);
+    let blocks: Vec<Block> = response.json();
+    assert_eq!(blocks, expected);
+    let confirmations: Vec<u64> = response
+        .json::<Vec<Value>>()
+        .iter()
+        .map(|block| block["confirmations"].as_u64().unwrap())
+        .collect();
+    assert_eq!(confirmations, vec![4, 3, 2]);
+
+    // Assert - bincode keeps the plain blocks
+    binary.assert_status(StatusCode::OK);
+    let blocks: Vec<Block> = bincode::deserialize(binary.as_bytes()).unwrap();
+    assert_eq!(blocks, expected);
+}
+
+#[tokio::test]
+async fn test_get_block_range_rejects_bad_ranges() {
+    // Arrange
+    let server = create_test_server().await;
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+
+    // Act
+    let inverted = server
+        .get("/blocks/range")
+        .add_query_param("from", 1)
+        .add_query_param("to", 0)
+        .await;
+    let past_tip = server
+        .get("/blocks/range")
+        .add_query_param("from", 0)
+        .add_query_param("to", 2)
+        .await;
+    let too_large = server
+        .get("/blocks/range")
+        .add_query_param("from", 0)
+        .add_query_param("to", 500)
+        .await;
+
+    // Assert
+    for response in [inverted, past_tip, too_large] {
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: Value = response.json();
+        assert_eq!(body["code"], "invalid_range");
+    }
+}
+
+#[tokio::test]
+async fn test_simulate_transaction() {
+    // Arrange
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+
+    // Act
+    let affordable = server
+        .post("/transactions/simulate")
+        .json(&wallet.transfer("recipient", 20.0, 0.5))
+        .await;
+    let unaffordable = server
+        .post("/transactions/simulate")
+        .json(&wallet.transfer("recipient", 80.0, 0.0))
+        .await;
+
+    // Assert
+    affordable.assert_status(StatusCode::OK);
+    let body: Value = affordable.json();
+    assert_eq!(body["wouldAccept"], true);
+    assert_eq!(body["resultingBalance"], amount(29.5));
+    assert!(body.get("reason").is_none());
+
+    let body: Value = unaffordable.json();
+    assert_eq!(body["wouldAccept"], false);
+    assert!(body["reason"]
+        .as_str()
+        .unwrap()
+        .contains("Insufficient balance"));
+    assert_eq!(body["code"], "insufficient_balance");
+    assert_eq!(body["resultingBalance"], amount(50.0));
+
+    // Nothing was submitted
+    let pending: Vec<Value> = server.get("/transactions/pending").await.json();
+    assert!(pending.is_empty());
+}
+
+#[tokio::test]
+async fn test_decode_signed_transaction() {
+    // Arrange
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    let transaction = wallet.transfer("recipient", 20.0, 0.5);
+
+    // Act
+    let response = server.post("/transactions/decode").json(&transaction).await;
+
+    // Assert
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["hash"], transaction.hash.as_str());
+    assert_eq!(body["computedHash"], transaction.hash.as_str());
+    assert_eq!(body["hashMatches"], true);
+    assert_eq!(body["derivedAddress"], wallet.address());
+    assert_eq!(body["addressMatches"], true);
+    assert_eq!(body["signatureValid"], true);
+    assert!(body.get("signatureError").is_none());
+    assert_eq!(body["spendableBalance"], amount(50.0));
+    assert_eq!(body["sufficientBalance"], true);
+    assert_eq!(body["wouldAccept"], true);
+
+    // Nothing was submitted
+    let pending: Vec<Value> = server.get("/transactions/pending").await.json();
+    assert!(pending.is_empty());
+}
+
+#[tokio::test]
+async fn test_decode_flags_a_key_that_is_not_the_senders() {
+    // Arrange - another wallet signs a transfer claiming to come from the funded one
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    let impostor = TestWallet::new(2);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    let mut transaction = wallet.transfer("recipient", 20.0, 0.5);
+    transaction.public_key = Some(PublicKeyHex(impostor.public_key()));
+    transaction.signature = Some(TransactionSignature(
+        impostor.sign_message(transaction.hash.as_bytes()),
+    ));
+
+    // Act
+    let response = server.post("/transactions/decode").json(&transaction).await;
+
+    // Assert - the signature itself is fine, but the key isn't the sender's
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["hashMatches"], true);
+    assert_eq!(body["derivedAddress"], impostor.address());
+    assert_eq!(body["addressMatches"], false);
+    assert_eq!(body["signatureValid"], true);
+    assert_eq!(body["sufficientBalance"], true);
+    assert_eq!(body["wouldAccept"], false);
+    assert_eq!(body["code"], "address_mismatch");
+}
+
+#[tokio::test]
+async fn test_hashrate() {
+    // Arrange
+    let server = create_test_server().await;
+
+    // Act - a fresh chain has no interval to estimate from
+    let response = server.get("/chain/hashrate").await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert!(body["hashesPerSecond"].is_null());
+    assert_eq!(body["blocks"], 0);
+}
+
+#[tokio::test]
+async fn test_balance_at_height() {
+    // Arrange - two blocks mined to the same address
+    let server = create_test_server().await;
+    let mine_data = json!({ "minerAddress": TEST_MINER });
+    server.post("/blocks/mine").json(&mine_data).await;
+    server.post("/blocks/mine").json(&mine_data).await;
+
+    // Act
+    let early = server
+        .get(&format!("/balance/{}", TEST_MINER))
+        .add_query_param("at_height", 1)
+        .await;
+    let tip = server.get(&format!("/balance/{}", TEST_MINER)).await;
+    let future = server
+        .get(&format!("/balance/{}", TEST_MINER))
+        .add_query_param("at_height", 3)
+        .await;
+
+    // Assert
+    early.assert_status(StatusCode::OK);
+    let body: Value = early.json();
+    assert_eq!(body["balance"], amount(50.0));
+    assert_eq!(body["atHeight"], 1);
+
+    tip.assert_status(StatusCode::OK);
+    let body: Value = tip.json();
+    assert_eq!(body["balance"], amount(100.0));
+    assert!(body.get("atHeight").is_none());
+
+    future.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_out_of_constraint_requests_are_rejected_at_the_boundary() {
+    // Arrange
+    let server = create_test_server().await;
+
+    // Act
+    let negative = server
+        .post("/transactions")
+        .json(&json!({
+            "sender": "system",
+            "recipient": TEST_MINER,
+            "amount": -5.0,
+            "signature": ""
+        }))
+        .await;
+    let long_address = server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": "1".repeat(100) }))
+        .await;
+
+    // Assert
+    negative.assert_status(StatusCode::BAD_REQUEST);
+    let body: Value = negative.json();
+    assert_eq!(body["error"], "Invalid request: amount must be at least 0");
+    assert_eq!(body["code"], "invalid_request");
+
+    long_address.assert_status(StatusCode::BAD_REQUEST);
+    let body: Value = long_address.json();
+    assert!(body["error"].as_str().unwrap().contains("minerAddress"));
+}
+
+#[tokio::test]
+async fn test_batch_balances() {
+    // Arrange
+    let server = create_test_server().await;
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+
+    // Act
+    let response = server
+        .post("/balances")
+        .json(&json!({ "addresses": [TEST_MINER, "unknown"] }))
+        .await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["balances"][TEST_MINER], 50.0);
+    assert_eq!(body["balances"]["unknown"], 0.0);
+}
+
+#[tokio::test]
+async fn test_batch_balances_cap() {
+    // Arrange
+    let server = create_test_server().await;
+    let addresses: Vec<String> = (0..101).map(|i| format!("address{}", i)).collect();
+
+    // Act
+    let response = server
+        .post("/balances")
+        .json(&json!({ "addresses": addresses }))
+        .await;
+
+    // Assert
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let body: Value = response.json();
+    assert_eq!(
+        body["error"],
+        "Invalid request: addresses must have at most 100 items"
+    );
+}
+
+#[tokio::test]
+async fn test_mine_block_reports_reward_and_fees() {
+    // Arrange - fund the wallet, then queue two fee-bearing transfers
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    for (amount, fee) in [(10.0, 0.5), (5.0, 0.25)] {
+        let transaction = wallet.transfer("recipient", amount, fee);
+        server
+            .post("/transactions/raw")
+            .json(&transaction)
+            .await
+            .assert_status(StatusCode::ACCEPTED);
+    }
+
+    // Act
+    let response = server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+
+    // Assert - the miner gets the subsidy plus the fees
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["reward"], amount(50.0));
+    assert_eq!(body["totalFees"], amount(0.75));
+    assert_eq!(body["transactionCount"], 2);
+    assert!(body["miningDurationMs"].is_u64());
+    let transactions = body["block"]["transactions"].as_array().unwrap();
+    assert_eq!(transactions.last().unwrap()["amount"], amount(50.75));
+}
+
+#[tokio::test]
+async fn test_mine_block_splits_the_reward_by_weight() {
+    // Arrange - a pool paying three addresses in shares of 1, 1 and 2
+    let server = create_test_server().await;
+    let payouts = [TestWallet::new(1), TestWallet::new(2), TestWallet::new(3)];
+    let recipients: Vec<Value> = payouts
+        .iter()
+        .zip([1, 1, 2])
+        .map(|(wallet, weight)| json!({ "address": wallet.address(), "weight": weight }))
+        .collect();
+
+    // Act
+    let response = server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER, "rewardRecipients": recipients }))
+        .await;
+
+    // Assert - one reward per recipient, adding up to the block reward
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["transactionCount"], 0);
+    let rewards = body["block"]["transactions"].as_array().unwrap();
+    let amounts: Vec<f64> = rewards
+        .iter()
+        .map(|reward| amount_of(&reward["amount"]))
+        .collect();
+    assert_eq!(amounts, vec![12.5, 12.5, 25.0]);
+    for (wallet, expected) in payouts.iter().zip(amounts) {
+        let balance: Value = server
+            .get(&format!("/balance/{}", wallet.address()))
+            .await
+            .json();
+        assert_eq!(balance["balance"], amount(expected));
+    }
+    let miner: Value = server.get(&format!("/balance/{}", TEST_MINER)).await.json();
+    assert_eq!(miner["balance"], amount(0.0));
+    let validation: Value = server.get("/chain/validate").await.json();
+    assert_eq!(validation["valid"], true);
+}
+
+#[tokio::test]
+async fn test_mine_block_rejects_bad_reward_splits() {
+    // Arrange
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+
+    // Act
+    let zero_weight = server
+        .post("/blocks/mine")
+        .json(&json!({
+            "minerAddress": TEST_MINER,
+            "rewardRecipients": [{ "address": wallet.address(), "weight": 0 }]
+        }))
+        .await;
+    let bad_address = server
+        .post("/blocks/mine")
+        .json(&json!({
+            "minerAddress": TEST_MINER,
+            "rewardRecipients": [
+                { "address": wallet.address(), "weight": 1 },
+                { "address": "not-an-address", "weight": 1 }
+            ]
+        }))
+        .await;
+    let empty = server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER, "rewardRecipients": [] }))
+        .await;
+
+    // Assert - nothing was mined
+    zero_weight.assert_status(StatusCode::BAD_REQUEST);
+    bad_address.assert_status(StatusCode::BAD_REQUEST);
+    let body: Value = bad_address.json();
+    assert_eq!(body["code"], "invalid_address");
+    empty.assert_status(StatusCode::BAD_REQUEST);
+    let body: Value = empty.json();
+    assert_eq!(body["code"], "invalid_reward_split");
+    let blocks: Vec<Value> = server.get("/blocks").await.json();
+    assert_eq!(blocks.len(), 1);
+}
+
+#[tokio::test]
+async fn test_validate_next_block() {
+    // Arrange - a correctly mined block on top of the current tip
+    let blockchain = create_test_blockchain();
+    let server = create_test_server_with_blockchain(blockchain.clone()).await;
+    let mut block = blockchain
+        .lock()
+        .unwrap()
+        .prepare_block(TEST_MINER)
+        .unwrap();
+    block.mine();
+
+    // Act
+    let response = server.post("/blocks/validate").json(&block).await;
+
+    // Assert - valid, but not appended
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body, json!({ "valid": true }));
+    assert_eq!(blockchain.lock().unwrap().chain.len(), 1);
+}
+
+#[tokio::test]
+async fn test_validate_block_with_wrong_previous_hash() {
+    // Arrange
+    let blockchain = create_test_blockchain();
+    let server = create_test_server_with_blockchain(blockchain.clone()).await;
+    let mut block = blockchain
+        .lock()
+        .unwrap()
+        .prepare_block(TEST_MINER)
+        .unwrap();
+    block.previous_hash = "f".repeat(64);
+    block.hash = block.calculate_hash();
+    block.mine();
+
+    // Act
+    let response = server.post("/blocks/validate").json(&block).await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["valid"], false);
+    assert!(body["reason"]
+        .as_str()
+        .unwrap()
+        .contains("doesn't match the tip"));
+}
+
+#[tokio::test]
+async fn test_next_nonce_advances_and_reports_gaps() {
+    // Arrange - a funded wallet that hasn't sent anything yet
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    let path = format!("/address/{}/nonce", wallet.address());
+    let fresh: Value = server.get(&path).await.json();
+
+    // Act - send nonces 0 and 1, then skip to 4
+    for nonce in [0, 1, 4] {
+        server
+            .post("/transactions/raw")
+            .json(&wallet.transfer_with_nonce("recipient", 1.0, 0.0, nonce))
+            .await
+            .assert_status(StatusCode::ACCEPTED);
+    }
+    let response = server.get(&path).await;
+
+    // Assert
+    assert_eq!(fresh["nextNonce"], 0);
+    assert_eq!(fresh["gaps"], json!([]));
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["nextNonce"], 2);
+    assert_eq!(body["gaps"], json!([{ "from": 2, "to": 3 }]));
+}
+
+#[tokio::test]
+async fn test_last_spent_height() {
+    // Arrange - the wallet is funded in block 1, spends in block 2, and block 3 is empty
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    let receiver = TestWallet::new(2);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    server
+        .post("/transactions/raw")
+        .json(&wallet.transfer(&receiver.address(), 5.0, 0.0))
+        .await
+        .assert_status(StatusCode::ACCEPTED);
+    for _ in 0..2 {
+        server
+            .post("/blocks/mine")
+            .json(&json!({ "minerAddress": TEST_MINER }))
+            .await
+            .assert_status_ok();
+    }
+
+    // Act
+    let sender = server
+        .get(&format!("/address/{}/last-spent", wallet.address()))
+        .await;
+    let recipient = server
+        .get(&format!("/address/{}/last-spent", receiver.address()))
+        .await;
+
+    // Assert
+    sender.assert_status_ok();
+    let sender: Value = sender.json();
+    assert_eq!(sender["lastSpentHeight"], 2);
+    assert_eq!(sender["height"], 3);
+    recipient.assert_status_ok();
+    let recipient: Value = recipient.json();
+    assert_eq!(recipient["address"], receiver.address());
+    assert!(recipient["lastSpentHeight"].is_null());
+}
+
+#[tokio::test]
+async fn test_mined_block_is_sent_to_webhook() {
+    // Arrange - a receiver recording the signature header and body of every event
+    type Received = Arc<Mutex<Vec<(String, Bytes)>>>;
+    let received = Received::default();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let receiver = Router::new()
+        .route(
+            "/hook",
+            post(
+                |State(received): State<Received>, headers: HeaderMap, body: Bytes| async move {
+                    let signature = headers[SIGNATURE_HEADER].to_str().unwrap().to_string();
+                    received.lock().unwrap().push((signature, body));
+                },
+            ),
+        )
+        .with_state(received.clone());
+    tokio::spawn(async move { axum::serve(listener, receiver).await.unwrap() });
+
+    let server = create_test_server_with_config(ApiConfig {
+        webhook: Some(WebhookConfig {
+            url,
+            secret: "shared-secret".to_string(),
+            retry: RetryPolicy::default(),
+        }),
+        ..ApiConfig::default()
+    })
+    .await;
+
+    // Act
+    let response = server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+
+    // Assert - the block event arrives signed with the shared secret
+    response.assert_status(StatusCode::OK);
+    let mined: Value = response.json();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while received.lock().unwrap().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("webhook was not called");
+
+    let (signature, body) = received.lock().unwrap()[0].clone();
+    assert_eq!(
+        signature,
+        format!("sha256={}", hmac_sha256("shared-secret", &body))
+    );
+    let event: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(event["event"], "blockMined");
+    assert_eq!(event["block"]["hash"], mined["block"]["hash"]);
+}
+
+#[tokio::test]
+async fn test_inclusion_proof_verifies_end_to_end() {
+    // Arrange - confirm a transaction alongside the block reward
+    let server = create_test_server().await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    let transaction = wallet.transfer("recipient", 20.0, 0.5);
+    server.post("/transactions/raw").json(&transaction).await;
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+
+    // Act
+    let response = server
+        .get(&format!("/transactions/{}/inclusion", transaction.hash))
+        .await;
+    let unconfirmed = server.get("/transactions/unknown/inclusion").await;
+
+    // Assert - the proof checks out without the block
+    response.assert_status(StatusCode::OK);
+    let proof: InclusionProof = response.json();
+    assert_eq!(proof.transaction, transaction);
+    assert_eq!(proof.header.index, 2);
+    assert!(verify_merkle_proof(
+        &transaction.hash,
+        &proof.branch,
+        &proof.header.merkle_root
+    ));
+    assert!(proof.header.verify_proof_of_work());
+    assert!(proof.verify());
+
+    // A tampered branch no longer reaches the root
+    let mut tampered = proof.clone();
+    tampered.branch[0].hash = "00".repeat(32);
+    assert!(!tampered.verify());
+
+    // So does a made-up Merkle root under the block's genuine hash
+    let mut forged = proof.clone();
+    forged.header.merkle_root = transaction.hash.clone();
+    forged.branch.clear();
+    assert!(forged.header.verify_proof_of_work());
+    assert!(!forged.verify());
+
+    unconfirmed.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_read_only_node_rejects_mining_and_transactions() {
+    // Arrange
+    let server = create_test_server_with_config(ApiConfig {
+        read_only: true,
+        ..ApiConfig::default()
+    })
+    .await;
+    let wallet = TestWallet::new(1);
+
+    // Act
+    let mine = server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+    let create = server
+        .post("/transactions")
+        .json(&json!({ "sender": "system", "recipient": "bob", "amount": 10.0, "signature": "" }))
+        .await;
+    let raw = server
+        .post("/transactions/raw")
+        .json(&wallet.transfer("recipient", 1.0, 0.0))
+        .await;
+    let blocks = server.get("/blocks").await;
+    let stats = server.get("/chain/stats").await;
+
+    // Assert - writes are forbidden, reads still work
+    mine.assert_status(StatusCode::FORBIDDEN);
+    create.assert_status(StatusCode::FORBIDDEN);
+    raw.assert_status(StatusCode::FORBIDDEN);
+    let body: Value = mine.json();
+    assert_eq!(body["error"], "This node is read-only");
+    assert_eq!(body["code"], "read_only");
+    blocks.assert_status(StatusCode::OK);
+    stats.assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_read_only_node_still_imports_chains() {
+    // Arrange - a peer with the same genesis and one more block
+    let server = create_test_server_with_config(ApiConfig {
+        read_only: true,
+        ..ApiConfig::default()
+    })
+    .await;
+    let mut peer = fchain::Blockchain::new(1, 50.0);
+    peer.mine_pending_transactions(TEST_MINER).unwrap();
+
+    // Act
+    let response = server.post("/chain/import").json(&peer.chain).await;
+
+    // Assert
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["length"], 2);
+}
+
+#[tokio::test]
+async fn test_competing_block_flags_a_fork() {
+    // Arrange - a block mined for height 1 that loses to the chain's own
+    let blockchain = create_test_blockchain();
+    let server = create_test_server_with_blockchain(blockchain.clone()).await;
+    let mut competing = blockchain
+        .lock()
+        .unwrap()
+        .prepare_block(&TestWallet::new(2).address())
+        .unwrap();
+    competing.mine();
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+    let before: Value = server.get("/chain/fork-info").await.json();
+
+    // Act
+    let validated: Value = server
+        .post("/blocks/validate")
+        .json(&competing)
+        .await
+        .json();
+    let response = server.get("/chain/fork-info").await;
+
+    // Assert
+    assert_eq!(before["forkDetected"], false);
+    assert_eq!(validated["valid"], false);
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["forkDetected"], true);
+    assert_eq!(body["tipHeight"], 1);
+    assert_eq!(
+        body["competingTips"],
+        json!([{ "height": 1, "hash": competing.hash }])
+    );
+}
+
+#[tokio::test]
+async fn test_rejected_import_of_competing_chain_flags_a_fork() {
+    // Arrange - a peer chain as long as ours but with a different tip
+    let server = create_test_server().await;
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+    let mut peer = fchain::Blockchain::new(1, 50.0);
+    peer.mine_pending_transactions(&TestWallet::new(2).address())
+        .unwrap();
+
+    // Act
+    let import = server.post("/chain/import").json(&peer.chain).await;
+    let body: Value = server.get("/chain/fork-info").await.json();
+
+    // Assert
+    import.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(body["forkDetected"], true);
+    assert_eq!(body["competingTips"][0]["hash"], peer.chain[1].hash);
+}
+
+#[tokio::test]
+async fn test_address_websocket_reports_mined_reward() {
+    // Arrange - a live server for the socket and a test server for requests
+    let blockchain = create_test_blockchain();
+    let addr = spawn_test_server(blockchain.clone()).await;
+    let server = create_test_server_with_blockchain(blockchain).await;
+    let miner = TestWallet::new(2).address();
+    let (mut socket, _) =
+        tokio_tungstenite::connect_async(format!("ws://{}/ws/address/{}", addr, miner))
+            .await
+            .unwrap();
+
+    // Act - a block that doesn't involve the address, then one rewarding it
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+    let response = server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": miner }))
+        .await;
+    let block_hash = response.json::<Value>()["block"]["hash"].clone();
+
+    // Assert - only the rewarding block is reported
+    let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+        .await
+        .expect("no event received")
+        .unwrap()
+        .unwrap();
+    let event: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+    assert_eq!(event["address"], miner);
+    assert_eq!(event["source"], "block");
+    assert_eq!(event["hash"], block_hash);
+    assert_eq!(event["delta"], amount(50.0));
+    assert_eq!(event["balance"], amount(50.0));
+}
+
+#[tokio::test]
+async fn test_pending_transactions_for_address() {
+    // Arrange - a mempool with transactions for several addresses
+    let server = create_test_server().await;
+    let mut hashes = Vec::new();
+    for (recipient, amount) in [("alice", 10.0), ("bob", 20.0), ("alice", 30.0)] {
+        let response = server
+            .post("/transactions")
+            .json(&json!({
+                "sender": "system",
+                "recipient": recipient,
+                "amount": amount,
+                "signature": "system"
+            }))
+            .await;
+        hashes.push(response.json::<Value>()["transaction"]["hash"].clone());
+    }
+
+    // Act
+    let alice: Vec<Value> = server.get("/transactions/pending/alice").await.json();
+    let bob: Vec<Value> = server.get("/transactions/pending/bob").await.json();
+    let nobody = server.get("/transactions/pending/nobody").await;
+
+    // Assert
+    let hashes_of = |transactions: &[Value]| -> Vec<Value> {
+        transactions.iter().map(|tx| tx["hash"].clone()).collect()
+    };
+    assert_eq!(
+        hashes_of(&alice),
+        vec![hashes[0].clone(), hashes[2].clone()]
+    );
+    assert_eq!(hashes_of(&bob), vec![hashes[1].clone()]);
+    nobody.assert_status_ok();
+    assert_eq!(nobody.json::<Value>(), json!([]));
+}
+
+#[tokio::test]
+async fn test_address_graph_aggregates_counterparties() {
+    // Arrange - alice trades with bob both ways and pays carol
+    let server = create_test_server().await;
+    let alice = TestWallet::new(1);
+    let bob = TestWallet::new(2);
+    for wallet in [&alice, &bob] {
+        server
+            .post("/blocks/mine")
+            .json(&json!({ "minerAddress": wallet.address() }))
+            .await;
+    }
+    let transfers = [
+        alice.transfer_with_nonce(&bob.address(), 10.0, 0.0, 0),
+        bob.transfer_with_nonce(&alice.address(), 10.0, 0.0, 0),
+        alice.transfer_with_nonce("carol", 5.0, 0.0, 1),
+    ];
+    for transfer in &transfers {
+        server
+            .post("/transactions/raw")
+            .json(transfer)
+            .await
+            .assert_status(StatusCode::ACCEPTED);
+    }
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+
+    // Act
+    let all = server
+        .get(&format!("/address/{}/graph", alice.address()))
+        .await;
+    let non_zero = server
+        .get(&format!("/address/{}/graph", alice.address()))
+        .add_query_param("exclude_zero_net", true)
+        .await;
+
+    // Assert
+    all.assert_status_ok();
+    let flow = |counterparties: &Value, counterparty: &str| -> Option<Value> {
+        counterparties
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|flow| flow["counterparty"] == counterparty)
+            .cloned()
+    };
+    let counterparties = all.json::<Value>()["counterparties"].clone();
+    assert_eq!(counterparties.as_array().unwrap().len(), 3);
+    let with_bob = flow(&counterparties, &bob.address()).unwrap();
+    assert_eq!(with_bob["sent"], amount(10.0));
+    assert_eq!(with_bob["received"], amount(10.0));
+    assert_eq!(with_bob["net"], amount(0.0));
+    assert_eq!(flow(&counterparties, "carol").unwrap()["net"], amount(-5.0));
+    assert_eq!(
+        flow(&counterparties, "system").unwrap()["received"],
+        amount(50.0)
+    );
+
+    let counterparties = non_zero.json::<Value>()["counterparties"].clone();
+    assert_eq!(counterparties.as_array().unwrap().len(), 2);
+    assert!(flow(&counterparties, &bob.address()).is_none());
+}
+
+#[tokio::test]
+async fn test_address_statement_csv() {
+    // Arrange - alice is paid a reward, then pays bob
+    let server = create_test_server().await;
+    let alice = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": alice.address() }))
+        .await;
+    server
+        .post("/transactions/raw")
+        .json(&alice.transfer("bob", 10.0, 0.5))
+        .await
+        .assert_status(StatusCode::ACCEPTED);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+
+    // Act
+    let response = server
+        .get(&format!("/address/{}/statement.csv", alice.address()))
+        .await;
+
+    // Assert
+    response.assert_status_ok();
+    assert_eq!(response.header("content-type"), "text/csv; charset=utf-8");
+    assert!(response
+        .header("content-disposition")
+        .to_str()
+        .unwrap()
+        .starts_with("attachment;"));
+
+    let csv = response.text();
+    let rows: Vec<Vec<&str>> = csv.lines().map(|line| line.split(',').collect()).collect();
+    assert_eq!(
+        rows[0],
+        vec![
+            "timestamp",
+            "block_index",
+            "counterparty",
+            "direction",
+            "amount",
+            "balance"
+        ]
+    );
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[1][2..], ["system", "in", "50", "50"]);
+    assert_eq!(rows[2][2..], ["bob", "out", "10.5", "39.5"]);
+
+    let balance: Value = server
+        .get(&format!("/balance/{}", alice.address()))
+        .await
+        .json();
+    let last_balance: f64 = rows[2][5].parse().unwrap();
+    assert_eq!(balance["balance"], amount(last_balance));
+}
+
+#[tokio::test]
+async fn test_mempool_export_and_import() {
+    // Arrange - a mempool with two transactions, exported then cleared
+    let blockchain = create_test_blockchain();
+    let server = create_test_server_with_blockchain(blockchain.clone()).await;
+    let wallet = TestWallet::new(1);
+    server
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": wallet.address() }))
+        .await;
+    for nonce in 0..2 {
+        server
+            .post("/transactions/raw")
+            .json(&wallet.transfer_with_nonce("recipient", 5.0, 0.5, nonce))
+            .await
+            .assert_status(StatusCode::ACCEPTED);
+    }
+    let pending_before: Vec<Value> = server.get("/transactions/pending").await.json();
+
+    let export = server.get("/mempool/export.ndjson").await;
+    export.assert_status_ok();
+    assert_eq!(export.header("content-type"), "application/x-ndjson");
+    let exported = export.text();
+    assert_eq!(exported.lines().count(), 2);
+    blockchain.lock().unwrap().pending_transactions.clear();
+
+    // An unfunded transaction and a line that isn't a transaction at all
+    let unfunded =
+        serde_json::to_string(&TestWallet::new(3).transfer("recipient", 5.0, 0.0)).unwrap();
+    let body = format!("{}{}\nnot json\n", exported, unfunded);
+
+    // Act
+    let response = server.post("/mempool/import").text(body).await;
+
+    // Assert
+    response.assert_status_ok();
+    let result: Value = response.json();
+    assert_eq!(result["imported"], 2);
+    let skipped = result["skipped"].as_array().unwrap();
+    assert_eq!(skipped.len(), 2);
+    assert_eq!(skipped[0]["line"], 3);
+    assert!(skipped[0]["hash"].is_string());
+    assert_eq!(skipped[1]["line"], 4);
+    assert!(skipped[1].get("hash").is_none());
+
+    let pending_after: Vec<Value> = server.get("/transactions/pending").await.json();
+    assert_eq!(pending_after, pending_before);
+}
+
+#[tokio::test]
+async fn test_cancel_mining_job() {
+    // Arrange - a difficulty no test will ever finish mining
+    let server = create_test_server_with_blockchain(create_shared_blockchain(8, 50.0)).await;
+    let response = server
+        .post("/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+    response.assert_status(StatusCode::ACCEPTED);
+    let job_id = response.json::<Value>()["jobId"].as_u64().unwrap();
+
+    // Act
+    let cancelled = server.delete(&format!("/mine/{}", job_id)).await;
+
+    // Assert - the job stays cancelled and no block is ever appended
+    cancelled.assert_status_ok();
+    assert_eq!(cancelled.json::<Value>()["status"], "cancelled");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let job: Value = server.get(&format!("/mine/{}", job_id)).await.json();
+    assert_eq!(job["status"], "cancelled");
+    assert!(job.get("block").is_none());
+    let blocks: Vec<Value> = server.get("/blocks").await.json();
+    assert_eq!(blocks.len(), 1);
+
+    let again = server.delete(&format!("/mine/{}", job_id)).await;
+    again.assert_status(StatusCode::BAD_REQUEST);
+    server
+        .get("/mine/999")
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_mining_job_mines_a_block() {
+    // Arrange
+    let server = create_test_server().await;
+
+    // Act
+    let response = server
+        .post("/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await;
+    let job_id = response.json::<Value>()["jobId"].as_u64().unwrap();
+
+    // Assert - poll until the job is done
+    let mut job = Value::Null;
+    for _ in 0..100 {
+        job = server.get(&format!("/mine/{}", job_id)).await.json();
+        if job["status"] != "running" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(job["status"], "mined");
+    assert_eq!(job["block"]["index"], 1);
+}
+
+#[tokio::test]
+async fn test_mining_job_splits_the_reward_by_weight() {
+    // Arrange
+    let server = create_test_server().await;
+    let payouts = [TestWallet::new(1), TestWallet::new(2)];
+
+    // Act
+    let response = server
+        .post("/mine")
+        .json(&json!({
+            "minerAddress": TEST_MINER,
+            "rewardRecipients": [
+                { "address": payouts[0].address(), "weight": 1 },
+                { "address": payouts[1].address(), "weight": 3 }
+            ]
+        }))
+        .await;
+    let job_id = response.json::<Value>()["jobId"].as_u64().unwrap();
+
+    // Assert - the recipients are paid, not the miner address
+    let mut job = Value::Null;
+    for _ in 0..100 {
+        job = server.get(&format!("/mine/{}", job_id)).await.json();
+        if job["status"] != "running" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(job["status"], "mined");
+    for (wallet, expected) in payouts.iter().zip([12.5, 37.5]) {
+        let balance: Value = server
+            .get(&format!("/balance/{}", wallet.address()))
+            .await
+            .json();
+        assert_eq!(balance["balance"], amount(expected));
+    }
+    let miner: Value = server.get(&format!("/balance/{}", TEST_MINER)).await.json();
+    assert_eq!(miner["balance"], amount(0.0));
+}
+
+#[tokio::test]
+async fn test_reads_answer_503_while_chain_is_locked() {
+    // Arrange - another thread holds the chain lock well past the window
+    let blockchain = create_test_blockchain();
+    let server = create_configured_test_server(
+        blockchain.clone(),
+        ApiConfig {
+            read_lock_timeout: Some(Duration::from_millis(100)),
+            ..ApiConfig::default()
+        },
+    )
+    .await;
+    let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+    let holder = std::thread::spawn(move || {
+        let _guard = blockchain.lock().unwrap();
+        locked_tx.send(()).unwrap();
+        std::thread::sleep(Duration::from_secs(2));
+    });
+    locked_rx.recv().unwrap();
+
+    // Act
+    let started = std::time::Instant::now();
+    let response = server.get("/chain/stats").await;
+    let elapsed = started.elapsed();
+
+    // Assert
+    response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.header("retry-after"), "1");
+    assert_eq!(response.json::<Value>()["code"], "chain_busy");
+    assert!(elapsed < Duration::from_secs(1), "took {:?}", elapsed);
+
+    holder.join().unwrap();
+    server.get("/chain/stats").await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_get_version() {
+    // Arrange
+    let server = create_test_server().await;
+
+    // Act
+    let response = server.get("/version").await;
+
+    // Assert
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert!(!body["version"].as_str().unwrap().is_empty());
+    assert_eq!(
+        body["protocolVersion"],
+        fchain::blockchain::PROTOCOL_VERSION
+    );
+    assert_eq!(body["chainId"], "fchain-local");
+}
+
+#[tokio::test]
+async fn test_unsigned_transactions_require_a_permissioned_gateway() {
+    // Arrange - a public node and a permissioned one, both funding the same wallet
+    let wallet = TestWallet::new(1);
+    let public = create_configured_test_server(
+        create_test_blockchain(),
+        ApiConfig {
+            gateway_token: Some("gateway-secret".to_string()),
+            ..ApiConfig::default()
+        },
+    )
+    .await;
+    let permissioned_chain = Arc::new(Mutex::new(
+        fchain::Blockchain::new(1, 50.0).with_permissioned(true),
+    ));
+    let permissioned = create_configured_test_server(
+        permissioned_chain.clone(),
+        ApiConfig {
+            gateway_token: Some("gateway-secret".to_string()),
+            ..ApiConfig::default()
+        },
+    )
+    .await;
+    for server in [&public, &permissioned] {
+        server
+            .post("/blocks/mine")
+            .json(&json!({ "minerAddress": wallet.address() }))
+            .await;
+    }
+    let unsigned = |nonce: Option<u64>| {
+        json!({
+            "sender": wallet.address(),
+            "recipient": "recipient",
+            "amount": 10.0,
+            "nonce": nonce,
+            "signature": "",
+        })
+    };
+
+    // Act
+    let public_response = public
+        .post("/transactions")
+        .add_header(
+            HeaderName::from_static("x-gateway-token"),
+            HeaderValue::from_static("gateway-secret"),
+        )
+        .json(&unsigned(Some(0)))
+        .await;
+    let without_header = permissioned
+        .post("/transactions")
+        .json(&unsigned(Some(0)))
+        .await;
+    let without_nonce = permissioned
+        .post("/transactions")
+        .add_header(
+            HeaderName::from_static("x-gateway-token"),
+            HeaderValue::from_static("gateway-secret"),
+        )
+        .json(&unsigned(None))
+        .await;
+    let accepted = permissioned
+        .post("/transactions")
+        .add_header(
+            HeaderName::from_static("x-gateway-token"),
+            HeaderValue::from_static("gateway-secret"),
+        )
+        .json(&unsigned(Some(0)))
+        .await;
+
+    // Assert
+    public_response.assert_status(StatusCode::BAD_REQUEST);
+    without_header.assert_status(StatusCode::BAD_REQUEST);
+    without_nonce.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(without_nonce.json::<Value>()["code"], "missing_nonce");
+    accepted.assert_status(StatusCode::ACCEPTED);
+
+    permissioned
+        .post("/blocks/mine")
+        .json(&json!({ "minerAddress": TEST_MINER }))
+        .await
+        .assert_status_ok();
+    let chain = permissioned_chain.lock().unwrap();
+    assert_eq!(chain.get_balance("recipient"), 10.0);
+    assert!(chain.is_chain_valid().unwrap());
+    assert!(chain.get_verified_balance("recipient") == 10.0);
+}
+
+#[tokio::test]
+async fn test_chain_tip_advances_with_mining() {
+    // Arrange
+    let blockchain = create_test_blockchain();
+    let server = create_test_server_with_blockchain(blockchain.clone()).await;
+    let genesis: Value = server.get("/chain/tip").await.json();
+
+    // Act
+    for _ in 0..2 {
+        server
+            .post("/blocks/mine")
+            .json(&json!({ "minerAddress": TEST_MINER }))
+            .await
+            .assert_status_ok();
+    }
+    let response = server.get("/chain/tip").await;
+
+    // Assert
+    response.assert_status_ok();
+    let tip: Value = response.json();
+    let latest = blockchain.lock().unwrap().chain.last().unwrap().clone();
+    assert_eq!(genesis["height"], 0);
+    assert_eq!(tip["height"], 2);
+    assert_eq!(tip["hash"], latest.hash);
+    assert_ne!(tip["hash"], genesis["hash"]);
+    assert_eq!(tip["difficulty"], latest.difficulty);
+    assert!(tip["timestamp"].is_string());
+}
+
+#[tokio::test]
+async fn test_chain_work_sums_block_work() {
+    // Arrange - the test chain mines at difficulty 1, i.e. 16 hashes per block
+    let server = create_test_server().await;
+    for _ in 0..2 {
+        server
+            .post("/blocks/mine")
+            .json(&json!({ "minerAddress": TEST_MINER }))
+            .await
+            .assert_status_ok();
+    }
+
+    // Act
+    let response = server.get("/chain/work").await;
+
+    // Assert
+    response.assert_status_ok();
+    let work: Value = response.json();
+    assert_eq!(work["height"], 2);
+    assert_eq!(work["totalWork"], "48");
+}
@@ -3,6 +3,9 @@ use fchain::{
     Blockchain,
 };
 
+/// Well-formed address to send mining rewards to
+const MINER: &str = "1111111111111111111111111111111111111111";
+
 #[test]
 fn test_blockchain_creation() {
     let blockchain = Blockchain::new(2, 100.0);
@@ -48,9 +51,7 @@ fn test_mine_block() {
     blockchain.create_transaction(tx1).unwrap();
 
     // Mine a block
-    let block = blockchain
-        .mine_pending_transactions("miner_address")
-        .unwrap();
+    let block = blockchain.mine_pending_transactions(MINER).unwrap();
 
     // Verify the block was added
     assert_eq!(blockchain.chain.len(), 2); // Genesis + new block
@@ -72,7 +73,7 @@ fn test_validate_chain() {
         10.0,
     );
     blockchain.create_transaction(tx).unwrap();
-    blockchain.mine_pending_transactions("miner").unwrap();
+    blockchain.mine_pending_transactions(MINER).unwrap();
 
     assert!(blockchain.is_chain_valid().unwrap());
 }
@@ -99,7 +100,7 @@ fn test_transaction_flow() {
     let mut system_tx_with_sig = system_tx;
     system_tx_with_sig.signature = Some(TransactionSignature("system".to_string()));
     chain.create_transaction(system_tx_with_sig).unwrap();
-    chain.mine_pending_transactions("miner").unwrap();
+    chain.mine_pending_transactions(MINER).unwrap();
     drop(chain);
 
     // Create a mock transaction with the same data for comparison